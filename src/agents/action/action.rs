@@ -0,0 +1,85 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::json;
+
+use crate::agents::render_prompt;
+use crate::llm::llm::LLM;
+use crate::services::utils::{retry_wrapper, validate_responses};
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+/// The follow-up actions the Action prompt lets the model choose from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    Answer,
+    Run,
+    Deploy,
+    Feature,
+    Bug,
+    Report,
+}
+
+impl ActionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionKind::Answer => "answer",
+            ActionKind::Run => "run",
+            ActionKind::Deploy => "deploy",
+            ActionKind::Feature => "feature",
+            ActionKind::Bug => "bug",
+            ActionKind::Report => "report",
+        }
+    }
+}
+
+impl FromStr for ActionKind {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action.trim().trim_matches('`').to_lowercase().as_str() {
+            "answer" => Ok(ActionKind::Answer),
+            "run" => Ok(ActionKind::Run),
+            "deploy" => Ok(ActionKind::Deploy),
+            "feature" => Ok(ActionKind::Feature),
+            "bug" => Ok(ActionKind::Bug),
+            "report" => Ok(ActionKind::Report),
+            other => Err(format!("Unknown action: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub struct Action {
+    llm: LLM,
+}
+
+impl Action {
+    pub fn new(base_model: &str) -> Self {
+        Self { llm: LLM::new(Some(base_model.to_string())) }
+    }
+
+    pub fn render(&self, conversation: &[String]) -> Result<String, String> {
+        render_prompt(PROMPT, json!({ "conversation": conversation }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<(String, ActionKind), String> {
+        let response = validate_responses(response)?;
+        let reply = response["response"].as_str().ok_or("Response is missing the `response` field")?;
+        let action = response["action"].as_str().ok_or("Response is missing the `action` field")?;
+        Ok((reply.to_string(), action.parse()?))
+    }
+
+    pub fn execute(&self, conversation: &[String], project_name: &str) -> Result<(String, ActionKind), String> {
+        let prompt = self.render(conversation)?;
+        retry_wrapper(|| {
+            let response = self.llm.inference(&prompt, project_name)?;
+            self.validate_response(&response)
+        })
+    }
+}
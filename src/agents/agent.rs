@@ -0,0 +1,645 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::agents::action::action::{Action, ActionKind};
+use crate::agents::answer::answer::Answer;
+use crate::agents::coder::coder::Coder;
+use crate::agents::control::{self, CANCELLED};
+use crate::agents::decision::decision::{Decision, DecisionStep, FunctionRegistry, GitCloneArgs, UserPromptArgs};
+use crate::agents::feature::feature::Feature;
+use crate::agents::formatter::formatter::Formatter;
+use crate::agents::patcher::patcher::Patcher;
+use crate::agents::planner::planner::Planner;
+use crate::agents::questions;
+use crate::agents::reporter::reporter::Reporter;
+use crate::agents::researcher::researcher::Researcher;
+use crate::agents::runner::runner::Runner;
+use crate::bert::sentence::SentenceBert;
+use crate::browser::fetcher::PageFetcher;
+use crate::browser::search::get_search_engine;
+use crate::config::Config;
+use crate::documenter::architecture_section;
+use crate::documenter::pdf::Pdf;
+use crate::filesystem::patch::PatchOutcome;
+use crate::filesystem::read_code::ReadCode;
+use crate::filesystem::watcher::watch_project;
+use crate::logger::Logger;
+use crate::memory::knowledge_base::KnowledgeBase;
+use crate::memory::rag::project_context;
+use crate::project::ProjectManager;
+use crate::services::deploy::get_deployer;
+use crate::services::git::Git;
+use crate::socket_instance::emit_agent;
+use crate::state::AgentState;
+
+/// What the Coder is told the user said when there was nothing to ask, or no answer.
+const NO_ANSWER: &str = "Nothing from the user.";
+
+/// How far a run got. Saved in the agent state when the run is cancelled, so it can
+/// be resumed from the step after the last one it finished.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RunProgress {
+    prompt: String,
+    base_model: String,
+    search_engine: String,
+    /// The action the Action agent picked, once it has.
+    action: Option<String>,
+    /// The chained function calls of a Decision run.
+    steps: Vec<DecisionStep>,
+    completed_steps: usize,
+    /// A task branch the run still has to start. A Decision run starts it, like the
+    /// repository itself, only after any `git_clone` step, so the clone lands in an
+    /// empty project.
+    #[serde(default)]
+    pending_task_branch: bool,
+}
+
+pub struct Agent {
+    base_model: String,
+    engine: String,
+    logger: Logger,
+    progress: Mutex<RunProgress>,
+
+    /// Accumulate contextual keywords from chained prompts of all preparation agents
+    collected_context_keywords: Mutex<Vec<String>>,
+
+    planner: Planner,
+    researcher: Researcher,
+    formatter: Formatter,
+    coder: Coder,
+    action: Action,
+    answer: Answer,
+    decision: Decision,
+    patcher: Patcher,
+    feature: Feature,
+    runner: Runner,
+    reporter: Reporter,
+
+    project_manager: ProjectManager,
+    agent_state: AgentState,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Agent {
+    pub fn new(base_model: &str, search_engine: &str) -> Result<Self, String> {
+        if base_model.is_empty() {
+            return Err("base_model is required".to_string());
+        }
+        let config = Config::new().map_err(|e| e.to_string())?;
+        // The store's pool starts its upkeep tasks on the current runtime, and agents
+        // run on a thread of their own.
+        let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        let project_manager = {
+            let _guard = runtime.enter();
+            ProjectManager::new().map_err(|e| e.to_string())?
+        };
+
+        Ok(Self {
+            base_model: base_model.to_string(),
+            engine: search_engine.to_lowercase(),
+            logger: Logger::new("devika_agent.log"),
+            progress: Mutex::new(RunProgress::default()),
+
+            collected_context_keywords: Mutex::new(vec![]),
+
+            planner: Planner::new(base_model),
+            researcher: Researcher::new(base_model),
+            formatter: Formatter::new(base_model),
+            coder: Coder::new(base_model),
+            action: Action::new(base_model),
+            answer: Answer::new(base_model),
+            decision: Decision::new(base_model),
+            patcher: Patcher::new(base_model),
+            feature: Feature::new(base_model),
+            runner: Runner::new(base_model),
+            reporter: Reporter::new(base_model),
+
+            project_manager,
+            agent_state: AgentState::new(config.get_agent_state_db()),
+            runtime,
+        })
+    }
+
+    /// Agents run on their own thread, so the async project store is driven from here.
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    fn add_message_from_devika(&self, project_name: &str, message: &str) {
+        if let Err(e) = self.block_on(self.project_manager.add_message_from_devika(project_name, message)) {
+            self.logger.error(&format!("Failed to store message for {}: {}", project_name, e));
+        }
+    }
+
+    /// Researches each query, answering from the knowledge base when it holds fresh
+    /// research for a similar query and storing what the web turns up otherwise.
+    pub fn search_queries(&self, queries: &[String], project_name: &str) -> HashMap<String, String> {
+        let mut results = HashMap::new();
+
+        let mut web_search = get_search_engine(&self.engine);
+        let mut fetcher = PageFetcher::new();
+        let config = Config::new().unwrap();
+        let knowledge_base = if config.get_knowledge_enabled() {
+            let _guard = self.runtime.enter();
+            KnowledgeBase::new().ok()
+        } else {
+            None
+        };
+        let freshness = Duration::from_secs(config.get_knowledge_freshness_hours() * 3600);
+
+        self.logger.info(&format!("Search Engine :: {}", self.engine));
+
+        for query in queries {
+            if control::is_cancelled(project_name) {
+                break;
+            }
+            let query = query.trim().to_lowercase();
+
+            if let Some(knowledge_base) = &knowledge_base {
+                if let Some(knowledge) = self.block_on(knowledge_base.get_knowledge(&query, project_name, freshness)) {
+                    self.logger.info(&format!("Knowledge base has {} for : {}", knowledge.query, query));
+                    results.insert(query.clone(), knowledge.contents);
+                    continue;
+                }
+            }
+
+            if let Err(e) = web_search.search(&query) {
+                self.logger.error(&format!("Search failed for {}: {}", query, e));
+                continue;
+            }
+            let Some(link) = web_search.get_first_link() else { continue };
+            self.logger.info(&format!("Link :: {}", link));
+
+            let page = match fetcher.open_page(project_name, &link) {
+                Ok(page) => page,
+                Err(e) => {
+                    self.logger.error(&format!("Could not open {}: {}", link, e));
+                    continue;
+                }
+            };
+            match self.formatter.execute(&page.text, project_name) {
+                Ok(formatted) => {
+                    if let Some(knowledge_base) = &knowledge_base {
+                        if let Err(e) = self.block_on(knowledge_base.add_knowledge(&query, project_name, &formatted)) {
+                            self.logger.warning(&format!("Could not store the research for {}: {}", query, e));
+                        }
+                    }
+                    results.insert(query.clone(), formatted);
+                    self.logger.info(&format!("got the search results for : {}", query));
+                }
+                Err(e) => self.logger.error(&format!("Formatter failed for {}: {}", query, e)),
+            }
+        }
+        results
+    }
+
+    /// Handlers for the functions the Decision prompt can chain.
+    fn decision_registry(&self) -> FunctionRegistry<'_> {
+        let mut registry = FunctionRegistry::new();
+
+        registry.register("git_clone", |args: GitCloneArgs, project_name| {
+            let project = Git::clone(&args.url)?.import_as_project(project_name)?;
+            Ok(format!("cloned {} into {}", args.url, project.path().display()))
+        });
+        registry.register("generate_pdf_document", |args: UserPromptArgs, project_name| {
+            let pdf_download_url = self.generate_report(&[args.user_prompt], "", project_name)?;
+            Ok(format!("the PDF document is ready at {}", pdf_download_url))
+        });
+        registry.register("browser_interaction", |_args: UserPromptArgs, _project_name| {
+            Err("interactive browsing needs a headless browser, which this server does not run".to_string())
+        });
+        registry.register("coding_project", |args: UserPromptArgs, project_name| {
+            self.coding_project(&args.user_prompt, project_name)
+        });
+
+        registry
+    }
+
+    /// Has the Reporter write the report, adds the project's architecture section
+    /// and renders it to `PDFS_DIR/<project>.pdf`. Returns the URL it can be downloaded from.
+    fn generate_report(&self, conversation: &[String], code_markdown: &str, project_name: &str) -> Result<String, String> {
+        let mut markdown = self.reporter.execute(conversation, code_markdown, project_name)?;
+        if let Some(architecture) = architecture_section(project_name) {
+            markdown.push_str("\n\n");
+            markdown.push_str(&architecture);
+        }
+        Pdf::new()?.markdown_to_pdf(&markdown, project_name)?;
+
+        Ok(format!("http://127.0.0.1:1337/api/download-project-pdf?project_name={}", project_name.replace(' ', "%20")))
+    }
+
+    /// Asks the user `question` and waits for the answer to come in as their next
+    /// message. Past `[ASK_USER] TIMEOUT_SECS` the run goes on without one, or stops
+    /// when `ON_TIMEOUT` is `abort`.
+    fn ask_user(&self, project_name: &str, question: &str) -> Result<String, String> {
+        let config = Config::new().unwrap();
+        let timeout = Duration::from_secs(config.get_ask_user_timeout_secs());
+        // Registered first, so an answer sent right after the question isn't missed.
+        let answer = questions::wait_for_answer(project_name);
+        self.add_message_from_devika(project_name, question);
+
+        let mut new_state = AgentState::new_state();
+        new_state["internal_monologue"] = json!("Waiting for your answer...");
+        new_state["agent_is_active"] = json!(false);
+        new_state["pending_question"] = json!({
+            "question": question,
+            "asked_at": Utc::now().to_rfc3339(),
+            "expires_at": (Utc::now() + timeout).to_rfc3339(),
+        });
+        self.agent_state.add_to_current_state(project_name, &new_state);
+        self.logger.info(&format!("Waiting for the user to answer : {}", question));
+
+        let cancel = control::cancellation_token(project_name);
+        let reply = self.block_on(async {
+            tokio::select! {
+                reply = tokio::time::timeout(timeout, answer) => Some(reply),
+                _ = cancel.cancelled() => None,
+            }
+        });
+        questions::forget(project_name);
+        self.agent_state.clear_pending_question(project_name);
+        let Some(reply) = reply else { return Err(CANCELLED.to_string()) };
+        self.agent_state.set_agent_active(project_name, true);
+
+        match reply {
+            Ok(Ok(reply)) => {
+                if let Err(e) = self.block_on(self.project_manager.add_message_from_user(project_name, &reply)) {
+                    self.logger.error(&format!("Failed to store message for {}: {}", project_name, e));
+                }
+                self.add_message_from_devika(project_name, "Thanks! 🙌");
+                Ok(reply)
+            }
+            _ if config.get_ask_user_on_timeout() == "abort" => {
+                Err(format!("No answer to \"{}\" within {} seconds", question, timeout.as_secs()))
+            }
+            _ => {
+                self.add_message_from_devika(project_name, "I didn't hear back, so I'm going ahead without an answer.");
+                Ok(NO_ANSWER.to_string())
+            }
+        }
+    }
+
+    /// Update the context keywords with the latest sentence/prompt
+    fn update_contextual_keywords(&self, sentence: &str) -> Vec<String> {
+        let top_n = Config::new().unwrap().get_keywords_top_n();
+        let mut collected = self.collected_context_keywords.lock().unwrap();
+        for (keyword, _) in SentenceBert::new(sentence).extract_keywords(top_n) {
+            if !collected.contains(&keyword) {
+                collected.push(keyword);
+            }
+        }
+        collected.clone()
+    }
+
+    /// Call the planner, researcher, coder agents in sequence
+    fn coding_project(&self, user_prompt: &str, project_name: &str) -> Result<String, String> {
+        let plan = self.planner.execute(user_prompt, project_name)?;
+        control::checkpoint(project_name, "the research")?;
+
+        let focus = self.planner.parse_response(&plan).focus;
+        let context_keywords = self.update_contextual_keywords(&focus);
+        self.logger.info(&format!("context_keywords :: {:?}", context_keywords));
+
+        let research = self.researcher.execute(&plan, &context_keywords, project_name)?;
+        let user_context = if research.ask_user.trim().is_empty() {
+            NO_ANSWER.to_string()
+        } else {
+            self.ask_user(project_name, research.ask_user.trim())?
+        };
+        control::checkpoint(project_name, "the web search")?;
+        let search_results = self.search_queries(&research.queries, project_name);
+        control::checkpoint(project_name, "writing the code")?;
+
+        // Empty for a new project; relevant for one that was cloned or built before.
+        let code_markdown = project_context(project_name, &format!("{}\n{}", user_prompt, plan), &self.logger);
+        let code = self.coder.execute(&plan, &user_context, &search_results, &code_markdown, project_name)?;
+        self.coder.save_code_to_project(&code, project_name)?;
+        self.commit_step(project_name, "Coder", user_prompt, &plan);
+
+        Ok(format!("wrote {} file(s)", code.len()))
+    }
+
+    /// Runs the chained function calls picked by the Decision agent in order. Each
+    /// step's reply goes to the user first, then a success or failure line for it;
+    /// after a failure the rest of the chain is skipped, since later calls usually
+    /// build on earlier ones.
+    pub fn make_decision(&self, prompt: &str, project_name: &str) -> Result<(), String> {
+        let decision = self.decision.execute(prompt, project_name)?;
+        self.progress.lock().unwrap().steps = decision.clone();
+        self.run_decision_steps(&decision, 0, project_name)
+    }
+
+    /// Runs `decision` from the step after the first `completed` ones. A cancelled
+    /// step stops the chain at once, so it is the one a resumed run starts from.
+    fn run_decision_steps(&self, decision: &[DecisionStep], completed: usize, project_name: &str) -> Result<(), String> {
+        let registry = self.decision_registry();
+
+        let mut failed = None;
+        for (index, item) in decision.iter().enumerate().skip(completed) {
+            let step = index + 1;
+            if let Some(failed_step) = failed {
+                self.add_message_from_devika(project_name, &format!("Step {} (`{}`) skipped because step {} failed.", step, item.function, failed_step));
+                continue;
+            }
+            control::checkpoint(project_name, &format!("step {} (`{}`)", step, item.function))?;
+            if item.function != "git_clone" {
+                self.prepare_repository(project_name);
+            }
+
+            self.add_message_from_devika(project_name, &item.reply);
+
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| registry.call(item, project_name)))
+                .unwrap_or_else(|_| Err("the handler crashed".to_string()));
+            match outcome {
+                Ok(summary) => {
+                    self.progress.lock().unwrap().completed_steps = step;
+                    self.add_message_from_devika(project_name, &format!("Step {} (`{}`) succeeded: {}", step, item.function, summary));
+                }
+                Err(e) if control::is_cancellation(&e) => return Err(e),
+                Err(e) => {
+                    self.logger.error(&format!("Decision step {} ({}) failed: {}", step, item.function, e));
+                    self.add_message_from_devika(project_name, &format!("Step {} (`{}`) failed: {}", step, item.function, e));
+                    failed = Some(step);
+                }
+            }
+        }
+
+        match failed {
+            Some(step) => Err(format!("decision step {} failed", step)),
+            None => Ok(()),
+        }
+    }
+
+    /// Agentic flow of execution for the first message of a project: the Decision
+    /// agent picks the chain of functions to run, such as `coding_project`.
+    ///
+    /// Like `subsequent_execute` and `resume`, this expects the caller to have
+    /// registered the run with `control::start`, and closes it whatever happens.
+    pub fn execute(&self, prompt: &str, project_name: &str, task_branch: bool) -> Result<(), String> {
+        self.finish_run(project_name, || {
+            self.block_on(self.project_manager.add_message_from_user(project_name, prompt))
+                .map_err(|e| e.to_string())?;
+            self.agent_state.create_state(project_name);
+
+            self.prepare_run(prompt, project_name, task_branch);
+            self.make_decision(prompt, project_name)
+        })
+    }
+
+    /// Subsequent flow of execution: the Action agent picks what to do with the
+    /// user's follow-up and the matching sub-agent handles it. With `task_branch` the
+    /// work happens on a branch of its own that the user can merge or discard later.
+    pub fn subsequent_execute(&self, prompt: &str, project_name: &str, task_branch: bool) -> Result<(), String> {
+        self.finish_run(project_name, || {
+            self.block_on(self.project_manager.add_message_from_user(project_name, prompt))
+                .map_err(|e| e.to_string())?;
+
+            self.prepare_run(prompt, project_name, task_branch);
+            self.prepare_repository(project_name);
+            self.run_action(prompt, project_name)
+        })
+    }
+
+    /// Starts the run and watches the project. `prepare_repository` puts it under
+    /// git and, with `task_branch`, on a branch of its own.
+    fn prepare_run(&self, prompt: &str, project_name: &str, task_branch: bool) {
+        self.agent_state.clear_interruption(project_name);
+        self.start_run(project_name, RunProgress {
+            prompt: prompt.to_string(),
+            base_model: self.base_model.clone(),
+            search_engine: self.engine.clone(),
+            pending_task_branch: task_branch,
+            ..Default::default()
+        });
+        if let Err(e) = watch_project(project_name) {
+            self.logger.warning(&format!("Could not watch {}: {}", project_name, e));
+        }
+    }
+
+    /// Creates the project's repository if needed and starts the run's pending task
+    /// branch, if any.
+    fn prepare_repository(&self, project_name: &str) {
+        let git = match Git::for_project(project_name) {
+            Ok(git) => git,
+            Err(e) => {
+                self.logger.warning(&format!("Could not create a repository for {}: {}", project_name, e));
+                return;
+            }
+        };
+        if let Err(e) = git.init() {
+            self.logger.warning(&format!("Could not create a repository for {}: {}", project_name, e));
+        }
+        let task_branch = std::mem::take(&mut self.progress.lock().unwrap().pending_task_branch);
+        if task_branch {
+            let state_index = self.agent_state.get_current_state(project_name).map_or(0, |stack| stack.len());
+            match git.start_task_branch(state_index) {
+                Ok(branch) => self.add_message_from_devika(project_name, &format!(
+                    "I'm working on the branch `{}`. You can merge it into `{}` or discard it when I'm done.", branch.name, branch.base
+                )),
+                Err(e) => self.logger.warning(&format!("Could not start a task branch for {}: {}", project_name, e)),
+            }
+        }
+    }
+
+    /// Picks a cancelled run up again from where the agent state says it stopped.
+    pub fn resume(&self, project_name: &str) -> Result<(), String> {
+        self.finish_run(project_name, || {
+            let interruption = self.agent_state.get_interruption(project_name)
+                .ok_or_else(|| format!("{} has no interrupted run to resume", project_name))?;
+            let progress: RunProgress = serde_json::from_value(interruption["progress"].clone())
+                .map_err(|e| format!("Could not read where the run stopped: {}", e))?;
+
+            self.agent_state.clear_interruption(project_name);
+            self.add_message_from_devika(project_name, "Picking up where I left off.");
+            self.start_run(project_name, progress.clone());
+
+            self.continue_run(&progress, project_name)
+        })
+    }
+
+    fn continue_run(&self, progress: &RunProgress, project_name: &str) -> Result<(), String> {
+        if !progress.steps.is_empty() {
+            return self.run_decision_steps(&progress.steps, progress.completed_steps, project_name);
+        }
+        match progress.action.as_deref().map(str::parse::<ActionKind>) {
+            Some(Ok(action)) => {
+                let conversation = self.block_on(self.project_manager.get_all_messages_formatted(project_name));
+                self.perform_action(action, &progress.prompt, &conversation, project_name)
+            }
+            _ => self.run_action(&progress.prompt, project_name),
+        }
+    }
+
+    fn start_run(&self, project_name: &str, progress: RunProgress) {
+        *self.progress.lock().unwrap() = progress;
+        self.agent_state.set_agent_active(project_name, true);
+    }
+
+    /// Runs `run` and closes the run whatever it does, panics included, otherwise the
+    /// project stays "active" and every later message is refused. A cancelled run
+    /// leaves a last frame saying where it stopped.
+    fn finish_run<F: FnOnce() -> Result<(), String>>(&self, project_name: &str, run: F) -> Result<(), String> {
+        let result = panic::catch_unwind(AssertUnwindSafe(run))
+            .unwrap_or_else(|_| Err("The agent crashed while handling the request".to_string()));
+        control::finish(project_name);
+
+        let cancelled = matches!(&result, Err(e) if control::is_cancellation(e));
+        if let Err(e) = &result {
+            if cancelled {
+                self.logger.info(&format!("Run for {} was cancelled", project_name));
+            } else {
+                self.logger.error(&format!("Action failed for {}: {}", project_name, e));
+                emit_agent("info", json!({"type": "error", "message": e}));
+                self.add_message_from_devika(project_name, &format!("Sorry, I ran into a problem: {}", e));
+            }
+        }
+
+        self.agent_state.set_agent_active(project_name, false);
+        self.agent_state.set_agent_completed(project_name, true);
+        if cancelled {
+            self.record_interruption(project_name);
+        }
+
+        result
+    }
+
+    fn record_interruption(&self, project_name: &str) {
+        let progress = self.progress.lock().unwrap().clone();
+        let next_step = progress.completed_steps + 1;
+        let next = match (progress.steps.get(progress.completed_steps), &progress.action) {
+            (Some(step), _) => format!("step {} (`{}`)", next_step, step.function),
+            (None, Some(action)) => format!("the {} action", action),
+            (None, None) => "choosing what to do".to_string(),
+        };
+        self.logger.info(&format!("Next up for {} on resume: {}", project_name, next));
+
+        let mut new_state = AgentState::new_state();
+        new_state["internal_monologue"] = json!(format!("Cancelled. Resuming will start from {}.", next));
+        new_state["agent_is_active"] = json!(false);
+        new_state["completed"] = json!(true);
+        new_state["interruption"] = json!({
+            "interrupted_at": Utc::now().to_rfc3339(),
+            "next_step": next_step,
+            "next": next,
+            "progress": progress,
+        });
+        self.agent_state.add_to_current_state(project_name, &new_state);
+        self.add_message_from_devika(project_name, &format!("I stopped as asked. Resume the run to go on from {}.", next));
+    }
+
+    /// Commits the project after a Coder, Patcher or Feature step, so each step shows
+    /// up in the project's history on its own; `details`, such as the plan, go in the
+    /// commit body. A failed commit is logged, not fatal.
+    fn commit_step(&self, project_name: &str, step: &str, description: &str, details: &str) {
+        if !Config::new().unwrap().get_git_auto_commit() {
+            return;
+        }
+        let summary: String = description.lines().next().unwrap_or_default().chars().take(72).collect();
+        let state_index = self.agent_state.get_current_state(project_name).map_or(0, |stack| stack.len());
+        let mut message = format!("{}: {}", step, summary);
+        if !details.trim().is_empty() {
+            message = format!("{}\n\n{}", message, details.trim());
+        }
+        match Git::for_project(project_name).and_then(|git| git.commit_checkpoint(&message, state_index)) {
+            Ok(Some(hash)) => self.logger.info(&format!("Committed {} step for {} as {}", step, project_name, hash)),
+            Ok(None) => {}
+            Err(e) => self.logger.warning(&format!("Could not commit the {} step for {}: {}", step, project_name, e)),
+        }
+    }
+
+    fn run_action(&self, prompt: &str, project_name: &str) -> Result<(), String> {
+        let conversation = self.block_on(self.project_manager.get_all_messages_formatted(project_name));
+        let (response, action) = self.action.execute(&conversation, project_name)?;
+        self.add_message_from_devika(project_name, &response);
+
+        self.logger.info(&format!("action :: {}", action));
+        self.progress.lock().unwrap().action = Some(action.to_string());
+
+        self.perform_action(action, prompt, &conversation, project_name)
+    }
+
+    fn perform_action(&self, action: ActionKind, prompt: &str, conversation: &[String], project_name: &str) -> Result<(), String> {
+        control::checkpoint(project_name, &format!("the {} action", action))?;
+
+        match action {
+            ActionKind::Answer => {
+                let code_markdown = project_context(project_name, prompt, &self.logger);
+                let response = self.answer.execute(conversation, &code_markdown, project_name)?;
+                self.add_message_from_devika(project_name, &response);
+                Ok(())
+            }
+            ActionKind::Feature => {
+                let code_markdown = project_context(project_name, prompt, &self.logger);
+                let outcome = self.feature.execute(conversation, &code_markdown, &system_os(), project_name)?;
+                self.commit_step(project_name, "Feature", prompt, "");
+                self.add_message_from_devika(project_name, &patch_summary(&outcome));
+                Ok(())
+            }
+            ActionKind::Bug => {
+                let code_markdown = project_context(project_name, prompt, &self.logger);
+                let outcome = self.patcher.execute(conversation, &code_markdown, &[], "", &system_os(), project_name)?;
+                self.commit_step(project_name, "Patcher", prompt, "");
+                self.add_message_from_devika(project_name, &patch_summary(&outcome));
+                Ok(())
+            }
+            ActionKind::Run => {
+                let code_markdown = ReadCode::new(project_name).code_set_to_markdown();
+                let outputs = self.runner.execute(conversation, &code_markdown, &system_os(), project_name, |reply| {
+                    self.add_message_from_devika(project_name, reply);
+                }, |command, reply| {
+                    self.commit_step(project_name, "Patcher", reply, &format!("Fix for `{}`", command));
+                })?;
+                self.add_message_from_devika(project_name, &format!("I have run the project, all {} command(s) succeeded.", outputs.len()));
+                Ok(())
+            }
+            ActionKind::Report => {
+                let code_markdown = ReadCode::new(project_name).code_set_to_markdown();
+                let pdf_download_url = self.generate_report(conversation, &code_markdown, project_name)?;
+                self.add_message_from_devika(project_name, &format!("I have generated the PDF document. You can download it from here: {}", pdf_download_url));
+                Ok(())
+            }
+            ActionKind::Deploy => {
+                let deployer = get_deployer(Config::new().map_err(|e| e.to_string())?.get_deploy_target())?;
+                let deployment = deployer.deploy(project_name)?;
+                self.logger.info(&format!("Deployed {} to {} at {}", project_name, deployment.target, deployment.url));
+                let message = match deployment.target {
+                    "netlify" => format!("Done! I deployed your project on Netlify. It is live at {}", deployment.url),
+                    _ => format!("Done! Your project is being served at {}", deployment.url),
+                };
+                self.add_message_from_devika(project_name, &message);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn system_os() -> String {
+    format!("{} {}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Tells the user which files changed and which edits still need a hand.
+fn patch_summary(outcome: &PatchOutcome) -> String {
+    let mut files: Vec<&str> = outcome.applied.iter().map(|patch| patch.file.as_str()).collect();
+    files.dedup();
+
+    let mut summary = if files.is_empty() {
+        "I could not apply any changes.".to_string()
+    } else {
+        format!("I have updated {}.", files.iter().map(|file| format!("`{}`", file)).collect::<Vec<_>>().join(", "))
+    };
+    if !outcome.failed.is_empty() {
+        let failed = outcome.failed.iter()
+            .map(|hunk| format!("- `{}`: {}", hunk.file, hunk.reason))
+            .collect::<Vec<_>>()
+            .join("\n");
+        summary.push_str(&format!("\n\n{} edit(s) could not be applied:\n{}", outcome.failed.len(), failed));
+    }
+    summary
+}
@@ -0,0 +1,39 @@
+use serde_json::json;
+
+use crate::agents::render_prompt;
+use crate::llm::llm::LLM;
+use crate::services::utils::{retry_wrapper, validate_responses};
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+pub struct Answer {
+    llm: LLM,
+}
+
+impl Answer {
+    pub fn new(base_model: &str) -> Self {
+        Self { llm: LLM::new(Some(base_model.to_string())) }
+    }
+
+    pub fn render(&self, conversation: &[String], code_markdown: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({
+            "conversation": conversation,
+            "code_markdown": code_markdown,
+        }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<String, String> {
+        let response = validate_responses(response)?;
+        response["response"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "Response is missing the `response` field".to_string())
+    }
+
+    pub fn execute(&self, conversation: &[String], code_markdown: &str, project_name: &str) -> Result<String, String> {
+        let prompt = self.render(conversation, code_markdown)?;
+        retry_wrapper(|| {
+            let response = self.llm.inference(&prompt, project_name)?;
+            self.validate_response(&response)
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use crate::agents::coder::parser::{code_files_to_markdown, parse_code_files, validate_path, CodeFile};
+use crate::agents::render_prompt;
+use crate::config::Config;
+use crate::filesystem::project_path;
+use crate::filesystem::write_atomically;
+use crate::llm::llm::LLM;
+use crate::logger::Logger;
+use crate::services::utils::retry_wrapper;
+use crate::socket_instance::emit_agent;
+use crate::state::AgentState;
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+pub struct Coder {
+    logger: Logger,
+    llm: LLM,
+}
+
+impl Coder {
+    pub fn new(base_model: &str) -> Self {
+        Self {
+            logger: Logger::new("devika_agent.log"),
+            llm: LLM::new(Some(base_model.to_string())),
+        }
+    }
+
+    pub fn render(&self, step_by_step_plan: &str, user_context: &str, search_results: &HashMap<String, String>, code_markdown: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({
+            "step_by_step_plan": step_by_step_plan,
+            "user_context": user_context,
+            "search_results": search_results,
+            "code_markdown": code_markdown,
+        }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<Vec<CodeFile>, String> {
+        self.logger.debug(&format!("Response from the model: {}", response));
+
+        if !response.contains("~~~") {
+            return Err("Response is missing the ~~~ delimiters".to_string());
+        }
+        parse_code_files(response)
+    }
+
+    /// Writes every file under `PROJECTS_DIR/<project>` through a temp file and a
+    /// rename, so a crash mid-write never leaves a half-written file behind.
+    pub fn save_code_to_project(&self, response: &[CodeFile], project_name: &str) -> Result<PathBuf, String> {
+        let project_path = self.get_project_path(project_name)?;
+        fs::create_dir_all(&project_path).map_err(|e| e.to_string())?;
+        let project_root = project_path.canonicalize().map_err(|e| e.to_string())?;
+
+        let mut written = vec![];
+        for file in response {
+            validate_path(&file.file)?;
+            let file_path = project_path.join(&file.file);
+            write_atomically(&project_root, &file_path, &file.code)?;
+            written.push(file.file.clone());
+        }
+
+        let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
+        let mut new_state = AgentState::new_state();
+        if let Some(current_state) = agent_state.get_latest_state(project_name) {
+            new_state["browser_session"] = current_state["browser_session"].clone();
+        }
+        new_state["internal_monologue"] = json!("Saving the code to the project...");
+        new_state["terminal_session"]["title"] = json!("Saving files");
+        new_state["terminal_session"]["command"] = json!(format!("save {}", written.join(" ")));
+        new_state["terminal_session"]["output"] = json!(format!("Wrote {} file(s) to {}", written.len(), project_path.display()));
+        agent_state.add_to_current_state(project_name, &new_state);
+
+        Ok(project_path)
+    }
+
+    pub fn get_project_path(&self, project_name: &str) -> Result<PathBuf, String> {
+        project_path(project_name)
+    }
+
+    pub fn response_to_markdown_prompt(&self, response: &[CodeFile]) -> String {
+        code_files_to_markdown(response)
+    }
+
+    pub fn emulate_code_writing(&self, code_set: &[CodeFile], project_name: &str) {
+        let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
+        for current_file in code_set {
+            let mut new_state = AgentState::new_state();
+            if let Some(current_state) = agent_state.get_latest_state(project_name) {
+                new_state["browser_session"] = current_state["browser_session"].clone(); // keep the browser session
+            }
+            new_state["internal_monologue"] = json!("Writing code...");
+            new_state["terminal_session"]["title"] = json!(format!("Editing {}", current_file.file));
+            new_state["terminal_session"]["command"] = json!(format!("vim {}", current_file.file));
+            new_state["terminal_session"]["output"] = json!(current_file.code);
+            agent_state.add_to_current_state(project_name, &new_state);
+        }
+        emit_agent("code", json!({
+            "files": code_set,
+            "from": "coder",
+        }));
+    }
+
+    pub fn execute(
+        &self,
+        step_by_step_plan: &str,
+        user_context: &str,
+        search_results: &HashMap<String, String>,
+        code_markdown: &str,
+        project_name: &str,
+    ) -> Result<Vec<CodeFile>, String> {
+        let prompt = self.render(step_by_step_plan, user_context, search_results, code_markdown)?;
+
+        let valid_response = retry_wrapper(|| {
+            let response = self.llm.inference(&prompt, project_name)?;
+            self.validate_response(&response)
+        })?;
+
+        self.emulate_code_writing(&valid_response, project_name);
+
+        Ok(valid_response)
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeFile {
+    pub file: String,
+    pub code: String,
+}
+
+/// Parses the multi-file response format shared by the Coder, Feature and Patcher prompts:
+///
+/// ~~~
+/// File: `src/main.rs`:
+/// ```rs
+/// fn main() {}
+/// ```
+/// ~~~
+///
+/// A file's code block only ends at a bare fence at least as long as its opener that
+/// is followed by the next `File:` header or the end of the response, so files that
+/// contain fenced blocks or `~~~` lines themselves (READMEs, docs, templates) come
+/// through intact.
+pub fn parse_code_files(response: &str) -> Result<Vec<CodeFile>, String> {
+    let lines: Vec<&str> = response.trim().lines().collect();
+    let lines = strip_outer_tildes(&lines);
+
+    let mut result: Vec<CodeFile> = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(file) = parse_file_header(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        validate_path(&file)?;
+        i += 1;
+
+        while i < lines.len() && lines[i].trim().is_empty() {
+            i += 1;
+        }
+        let fence = lines.get(i).and_then(|line| opening_fence(line));
+        let Some(fence) = fence else {
+            return Err(format!("Missing code block for file {}", file));
+        };
+        i += 1;
+
+        let start = i;
+        let mut end = None;
+        while i < lines.len() {
+            if is_closing_fence(lines[i], &fence) && block_ends_here(&lines[i + 1..]) {
+                end = Some(i);
+                break;
+            }
+            i += 1;
+        }
+        // An unterminated block runs to the end of the response.
+        let end = end.unwrap_or(lines.len());
+
+        result.push(CodeFile { file, code: lines[start..end].join("\n") });
+        i = end + 1;
+    }
+
+    if result.is_empty() {
+        return Err("No files found in the response".to_string());
+    }
+    Ok(result)
+}
+
+/// Serializes files back into the format `parse_code_files` reads, picking a fence
+/// longer than any backtick run inside the code.
+pub fn code_files_to_markdown(files: &[CodeFile]) -> String {
+    let body = files.iter()
+        .map(|file| {
+            let longest_run = file.code.lines()
+                .map(|line| line.trim_start().chars().take_while(|c| *c == '`').count())
+                .max()
+                .unwrap_or(0);
+            let fence = "`".repeat(longest_run.max(2) + 1);
+            format!("File: `{}`:\n{}\n{}\n{}", file.file, fence, file.code, fence)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!("~~~\n{}\n~~~", body)
+}
+
+/// What lies between the first line of three or more tildes and the last one at
+/// least as long, so `~~~` lines inside the files are kept.
+pub fn strip_outer_tildes<'a>(lines: &'a [&'a str]) -> &'a [&'a str] {
+    let Some((first, length)) = lines.iter().enumerate()
+        .find_map(|(index, line)| tilde_fence(line).map(|length| (index, length))) else {
+        return lines;
+    };
+    let last = lines.iter().rposition(|line| tilde_fence(line).is_some_and(|closing| closing >= length));
+    match last {
+        Some(last) if first < last => &lines[first + 1..last],
+        _ => &lines[first + 1..],
+    }
+}
+
+fn tilde_fence(line: &str) -> Option<usize> {
+    let line = line.trim();
+    (line.len() >= 3 && line.chars().all(|c| c == '~')).then_some(line.len())
+}
+
+/// Accepts the header variants models actually produce:
+/// "File: `a.py`:", "File: a.py", "**File: `a.py`**", "### File: `a.py`".
+pub fn parse_file_header(line: &str) -> Option<String> {
+    let line = line.trim().trim_start_matches('#').trim().trim_start_matches('*').trim();
+    let rest = line.strip_prefix("File:").or_else(|| line.strip_prefix("file:"))?;
+    let file = rest.trim()
+        .trim_end_matches(':')
+        .trim_matches(|c: char| c == '*' || c == '`' || c.is_whitespace())
+        .trim_end_matches(':')
+        .trim();
+    if file.is_empty() {
+        None
+    } else {
+        Some(file.to_string())
+    }
+}
+
+struct Fence {
+    length: usize,
+}
+
+fn opening_fence(line: &str) -> Option<Fence> {
+    let length = line.trim_start().chars().take_while(|c| *c == '`').count();
+    if length >= 3 {
+        Some(Fence { length })
+    } else {
+        None
+    }
+}
+
+fn is_closing_fence(line: &str, fence: &Fence) -> bool {
+    let line = line.trim();
+    let length = line.chars().take_while(|c| *c == '`').count();
+    length >= fence.length && line[length..].trim().is_empty()
+}
+
+fn block_ends_here(rest: &[&str]) -> bool {
+    match rest.iter().find(|line| !line.trim().is_empty()) {
+        None => true,
+        Some(line) => parse_file_header(line).is_some(),
+    }
+}
+
+/// Rejects anything that could escape the project directory.
+pub fn validate_path(file: &str) -> Result<(), String> {
+    let path = Path::new(file);
+    let is_windows_absolute = file.len() >= 2 && file.as_bytes()[1] == b':' && file.as_bytes()[0].is_ascii_alphabetic();
+    if path.is_absolute() || file.starts_with('/') || file.starts_with('\\') || is_windows_absolute {
+        return Err(format!("Absolute paths are not allowed: {}", file));
+    }
+    for component in path.components() {
+        match component {
+            Component::ParentDir => return Err(format!("Parent directory references are not allowed: {}", file)),
+            Component::Normal(_) | Component::CurDir => {}
+            _ => return Err(format!("Invalid path: {}", file)),
+        }
+    }
+    if file.split(['/', '\\']).any(|part| part == "..") {
+        return Err(format!("Parent directory references are not allowed: {}", file));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_tilde_lines_and_shorter_fences_inside_a_file() {
+        let response = "Here you go:\n~~~\nFile: `README.md`:\n````md\n# Usage\n```sh\nmake\n```\n~~~\nnot the end\n~~~\n````\n\nFile: `Makefile`:\n```\nall:\n```\n~~~";
+        let files = parse_code_files(response).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].file, "README.md");
+        assert_eq!(files[0].code, "# Usage\n```sh\nmake\n```\n~~~\nnot the end\n~~~");
+        assert_eq!(files[1].file, "Makefile");
+        assert_eq!(files[1].code, "all:");
+    }
+
+    #[test]
+    fn round_trips_through_markdown() {
+        let files = vec![
+            CodeFile { file: "docs/guide.md".to_string(), code: "~~~\n```\ncode\n```\n~~~".to_string() },
+            CodeFile { file: "main.py".to_string(), code: "print('hi')".to_string() },
+        ];
+        let parsed = parse_code_files(&code_files_to_markdown(&files)).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].code, files[0].code);
+        assert_eq!(parsed[1].code, files[1].code);
+    }
+
+    #[test]
+    fn only_strips_a_closing_tilde_fence_as_long_as_the_opener() {
+        let lines = ["~~~~", "File: `a.md`:", "~~~", "~~~~"];
+        assert_eq!(strip_outer_tildes(&lines), &lines[1..3]);
+        let unterminated = ["~~~~", "File: `a.md`:", "~~~"];
+        assert_eq!(strip_outer_tildes(&unterminated), &unterminated[1..]);
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_project() {
+        assert!(validate_path("src/main.rs").is_ok());
+        assert!(validate_path("../secrets").is_err());
+        assert!(validate_path("/etc/passwd").is_err());
+        assert!(validate_path("C:\\Windows").is_err());
+    }
+}
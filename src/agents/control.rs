@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::sandbox::code_runner::cancel_executions;
+use crate::state::AgentState;
+
+/// The error every step returns once its run is cancelled, so the orchestrator can
+/// tell an interruption from a failure.
+pub const CANCELLED: &str = "The run was cancelled";
+
+/// The agent runs in progress, by project.
+static RUNS: Lazy<Mutex<HashMap<String, Arc<Run>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn project_key(project_name: &str) -> String {
+    project_name.to_lowercase().replace(' ', "-")
+}
+
+/// Lets `/api/agent/cancel`, `/pause` and `/resume` reach a run on its own thread.
+/// Cancelling is immediate: requests and processes watching the token stop at once.
+/// Pausing takes effect at the run's next step.
+pub struct Run {
+    token: CancellationToken,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl Run {
+    fn new() -> Self {
+        Self { token: CancellationToken::new(), paused: Mutex::new(false), resumed: Condvar::new() }
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap()
+    }
+}
+
+/// Registers a run for the project. Fails while another one is registered, so two
+/// requests racing for the same project can't both start an agent.
+pub fn start(project_name: &str) -> Result<Arc<Run>, String> {
+    let mut runs = RUNS.lock().unwrap();
+    let key = project_key(project_name);
+    if runs.contains_key(&key) {
+        return Err(format!("A run is already in progress for {}", project_name));
+    }
+    let run = Arc::new(Run::new());
+    runs.insert(key, Arc::clone(&run));
+    Ok(run)
+}
+
+pub fn finish(project_name: &str) {
+    RUNS.lock().unwrap().remove(&project_key(project_name));
+}
+
+pub fn current(project_name: &str) -> Option<Arc<Run>> {
+    RUNS.lock().unwrap().get(&project_key(project_name)).cloned()
+}
+
+/// The token of the project's run; one that is never cancelled outside of a run.
+pub fn cancellation_token(project_name: &str) -> CancellationToken {
+    current(project_name).map(|run| run.token()).unwrap_or_default()
+}
+
+pub fn is_cancelled(project_name: &str) -> bool {
+    current(project_name).is_some_and(|run| run.token.is_cancelled())
+}
+
+pub fn is_cancellation(error: &str) -> bool {
+    error == CANCELLED
+}
+
+/// Cancels the project's run and kills the code it is running. False when no run is
+/// in progress.
+pub fn cancel(project_name: &str) -> bool {
+    let Some(run) = current(project_name) else { return false };
+    run.token.cancel();
+    run.resumed.notify_all();
+    cancel_executions(project_name);
+    true
+}
+
+/// Asks the project's run to stop before its next step. False when no run is in progress.
+pub fn pause(project_name: &str) -> bool {
+    let Some(run) = current(project_name) else { return false };
+    *run.paused.lock().unwrap() = true;
+    true
+}
+
+/// Lets a paused run go on. False when the project has no paused run.
+pub fn resume(project_name: &str) -> bool {
+    let Some(run) = current(project_name) else { return false };
+    let mut paused = run.paused.lock().unwrap();
+    if !*paused {
+        return false;
+    }
+    *paused = false;
+    run.resumed.notify_all();
+    true
+}
+
+/// Called by a run between steps: fails once the run is cancelled, and while it is
+/// paused, records that in the agent state and waits to be resumed or cancelled.
+/// `next` tells the user what comes after the pause.
+pub fn checkpoint(project_name: &str, next: &str) -> Result<(), String> {
+    let Some(run) = current(project_name) else { return Ok(()) };
+    if run.token.is_cancelled() {
+        return Err(CANCELLED.to_string());
+    }
+    if !run.is_paused() {
+        return Ok(());
+    }
+
+    let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
+    let mut new_state = AgentState::new_state();
+    new_state["internal_monologue"] = json!(format!("Paused. Next up: {}", next));
+    new_state["agent_is_active"] = json!(false);
+    new_state["paused"] = json!(true);
+    agent_state.add_to_current_state(project_name, &new_state);
+
+    let mut paused = run.paused.lock().unwrap();
+    while *paused && !run.token.is_cancelled() {
+        paused = run.resumed.wait(paused).unwrap();
+    }
+    drop(paused);
+
+    agent_state.set_agent_paused(project_name, false);
+    if run.token.is_cancelled() {
+        return Err(CANCELLED.to_string());
+    }
+    agent_state.set_agent_active(project_name, true);
+    Ok(())
+}
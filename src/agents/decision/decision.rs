@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agents::render_prompt;
+use crate::llm::llm::LLM;
+use crate::services::utils::{retry_wrapper, validate_responses};
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+/// One entry of the chained function calls the Decision prompt asks for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionStep {
+    pub function: String,
+    pub args: Value,
+    pub reply: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitCloneArgs {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserPromptArgs {
+    pub user_prompt: String,
+}
+
+type Handler<'a> = Box<dyn Fn(&Value, &str) -> Result<String, String> + 'a>;
+
+/// Maps the function names the model may emit to handlers. Each handler declares
+/// its own argument type, and the raw JSON args are checked against it before the
+/// handler runs, so a malformed call fails that step instead of panicking.
+pub struct FunctionRegistry<'a> {
+    handlers: HashMap<String, Handler<'a>>,
+}
+
+impl<'a> FunctionRegistry<'a> {
+    pub fn new() -> Self {
+        Self { handlers: HashMap::new() }
+    }
+
+    pub fn register<A, F>(&mut self, name: &str, handler: F)
+    where
+        A: DeserializeOwned,
+        F: Fn(A, &str) -> Result<String, String> + 'a,
+    {
+        let function = name.to_string();
+        self.handlers.insert(name.to_string(), Box::new(move |args: &Value, project_name: &str| {
+            let args = serde_json::from_value::<A>(args.clone())
+                .map_err(|e| format!("invalid arguments for `{}`: {}", function, e))?;
+            handler(args, project_name)
+        }));
+    }
+
+    /// Runs a single step and returns the handler's summary of what it did.
+    pub fn call(&self, step: &DecisionStep, project_name: &str) -> Result<String, String> {
+        let handler = self.handlers.get(&step.function)
+            .ok_or_else(|| format!("unknown function `{}`", step.function))?;
+        handler(&step.args, project_name)
+    }
+}
+
+impl Default for FunctionRegistry<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Decision {
+    llm: LLM,
+}
+
+impl Decision {
+    pub fn new(base_model: &str) -> Self {
+        Self { llm: LLM::new(Some(base_model.to_string())) }
+    }
+
+    pub fn render(&self, prompt: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({ "prompt": prompt }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<Vec<DecisionStep>, String> {
+        let response = match validate_responses(response)? {
+            // Models regularly answer with a single call instead of a list.
+            Value::Object(step) => Value::Array(vec![Value::Object(step)]),
+            response => response,
+        };
+        serde_json::from_value(response).map_err(|e| format!("Invalid decision list: {}", e))
+    }
+
+    pub fn execute(&self, prompt: &str, project_name: &str) -> Result<Vec<DecisionStep>, String> {
+        let rendered_prompt = self.render(prompt)?;
+        retry_wrapper(|| {
+            let response = self.llm.inference(&rendered_prompt, project_name)?;
+            self.validate_response(&response)
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use crate::agents::patcher::patcher::{edit_until_applied, validate_edit_response};
+use crate::agents::render_prompt;
+use crate::filesystem::project_path;
+use crate::filesystem::patch::{FailedHunk, FileEdit, PatchOutcome};
+use crate::llm::llm::LLM;
+use crate::logger::Logger;
+
+const PROMPT: &str = include_str!("edit_prompt.jinja2");
+
+pub struct Feature {
+    logger: Logger,
+    llm: LLM,
+}
+
+impl Feature {
+    pub fn new(base_model: &str) -> Self {
+        Self {
+            logger: Logger::new("devika_agent.log"),
+            llm: LLM::new(Some(base_model.to_string())),
+        }
+    }
+
+    pub fn render(
+        &self,
+        conversation: &[String],
+        code_markdown: &str,
+        system_os: &str,
+        failed_hunks: &[FailedHunk],
+    ) -> Result<String, String> {
+        render_prompt(PROMPT, json!({
+            "conversation": conversation,
+            "code_markdown": code_markdown,
+            "system_os": system_os,
+            "failed_hunks": failed_hunks,
+        }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<Vec<FileEdit>, String> {
+        self.logger.debug(&format!("Response from the model: {}", response));
+        validate_edit_response(response)
+    }
+
+    pub fn get_project_path(&self, project_name: &str) -> Result<PathBuf, String> {
+        project_path(project_name)
+    }
+
+    pub fn execute(
+        &self,
+        conversation: &[String],
+        code_markdown: &str,
+        system_os: &str,
+        project_name: &str,
+    ) -> Result<PatchOutcome, String> {
+        let project_path = self.get_project_path(project_name)?;
+        edit_until_applied(&self.llm, &self.logger, &project_path, project_name, "feature", code_markdown, |code_markdown, failed_hunks| {
+            self.render(conversation, code_markdown, system_os, failed_hunks)
+        })
+    }
+}
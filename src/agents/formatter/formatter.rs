@@ -0,0 +1,25 @@
+use serde_json::json;
+
+use crate::agents::render_prompt;
+use crate::llm::llm::LLM;
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+pub struct Formatter {
+    llm: LLM,
+}
+
+impl Formatter {
+    pub fn new(base_model: &str) -> Self {
+        Self { llm: LLM::new(Some(base_model.to_string())) }
+    }
+
+    pub fn render(&self, raw_text: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({ "raw_text": raw_text }))
+    }
+
+    pub fn execute(&self, raw_text: &str, project_name: &str) -> Result<String, String> {
+        let prompt = self.render(raw_text)?;
+        self.llm.inference(&prompt, project_name)
+    }
+}
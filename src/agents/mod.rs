@@ -0,0 +1,26 @@
+pub mod action;
+pub mod agent;
+pub mod answer;
+pub mod coder;
+pub mod control;
+pub mod decision;
+pub mod feature;
+pub mod formatter;
+pub mod patcher;
+pub mod planner;
+pub mod questions;
+pub mod reporter;
+pub mod researcher;
+pub mod runner;
+
+use minijinja::Environment;
+use serde::Serialize;
+
+/// Renders one of the agents' `prompt.jinja2` templates.
+/// The templates are shared with the Python agents, so Python-style
+/// method calls like `dict.items()` are enabled.
+pub fn render_prompt<S: Serialize>(template: &str, context: S) -> Result<String, String> {
+    let mut env = Environment::new();
+    env.set_unknown_method_callback(minijinja_contrib::pycompat::unknown_method_callback);
+    env.render_str(template.trim(), context).map_err(|e| e.to_string())
+}
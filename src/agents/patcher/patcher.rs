@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+
+use crate::agents::render_prompt;
+use crate::config::Config;
+use crate::filesystem::project_path;
+use crate::filesystem::patch::{apply_edits, parse_edits, AppliedPatch, FailedHunk, FileEdit, PatchOutcome};
+use crate::llm::llm::LLM;
+use crate::logger::Logger;
+use crate::memory::rag::project_context;
+use crate::services::utils::retry_wrapper;
+use crate::socket_instance::emit_agent;
+use crate::state::AgentState;
+
+const PROMPT: &str = include_str!("edit_prompt.jinja2");
+
+/// How many times the model gets to resend edits that did not apply.
+const MAX_EDIT_ATTEMPTS: usize = 3;
+
+pub struct Patcher {
+    logger: Logger,
+    llm: LLM,
+}
+
+impl Patcher {
+    pub fn new(base_model: &str) -> Self {
+        Self {
+            logger: Logger::new("devika_agent.log"),
+            llm: LLM::new(Some(base_model.to_string())),
+        }
+    }
+
+    pub fn render(
+        &self,
+        conversation: &[String],
+        code_markdown: &str,
+        commands: &[String],
+        error: &str,
+        system_os: &str,
+        failed_hunks: &[FailedHunk],
+    ) -> Result<String, String> {
+        render_prompt(PROMPT, json!({
+            "conversation": conversation,
+            "code_markdown": code_markdown,
+            "commands": commands,
+            "error": error,
+            "system_os": system_os,
+            "failed_hunks": failed_hunks,
+        }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<Vec<FileEdit>, String> {
+        self.logger.debug(&format!("Response from the model: {}", response));
+        validate_edit_response(response)
+    }
+
+    pub fn get_project_path(&self, project_name: &str) -> Result<PathBuf, String> {
+        project_path(project_name)
+    }
+
+    pub fn execute(
+        &self,
+        conversation: &[String],
+        code_markdown: &str,
+        commands: &[String],
+        error: &str,
+        system_os: &str,
+        project_name: &str,
+    ) -> Result<PatchOutcome, String> {
+        let project_path = self.get_project_path(project_name)?;
+        edit_until_applied(&self.llm, &self.logger, &project_path, project_name, "patcher", code_markdown, |code_markdown, failed_hunks| {
+            self.render(conversation, code_markdown, commands, error, system_os, failed_hunks)
+        })
+    }
+}
+
+pub fn validate_edit_response(response: &str) -> Result<Vec<FileEdit>, String> {
+    if !response.contains("~~~") {
+        return Err("Response is missing the ~~~ delimiters".to_string());
+    }
+    parse_edits(response)
+}
+
+/// Shared edit loop of the Patcher and Feature agents: ask for edits, apply what
+/// matches, then hand the failed hunks back together with the code retrieved for
+/// them until everything applied or the attempts run out. The returned outcome holds every
+/// applied patch and the hunks that were still failing at the end.
+pub fn edit_until_applied<F>(
+    llm: &LLM,
+    logger: &Logger,
+    project_path: &Path,
+    project_name: &str,
+    from: &str,
+    code_markdown: &str,
+    render: F,
+) -> Result<PatchOutcome, String>
+where
+    F: Fn(&str, &[FailedHunk]) -> Result<String, String>,
+{
+    let mut outcome = PatchOutcome::default();
+    let mut code_markdown = code_markdown.to_string();
+
+    for attempt in 1..=MAX_EDIT_ATTEMPTS {
+        let prompt = render(&code_markdown, &outcome.failed)?;
+        let edits = retry_wrapper(|| {
+            let response = llm.inference(&prompt, project_name)?;
+            validate_edit_response(&response)
+        })?;
+
+        let result = apply_edits(project_path, &edits)?;
+        record_patches(&result.applied, project_name, from);
+        outcome.applied.extend(result.applied);
+        outcome.failed = result.failed;
+
+        if outcome.failed.is_empty() || attempt == MAX_EDIT_ATTEMPTS {
+            break;
+        }
+        let failed_code = outcome.failed.iter()
+            .map(|hunk| format!("{}\n{}", hunk.file, hunk.search))
+            .collect::<Vec<_>>()
+            .join("\n");
+        code_markdown = project_context(project_name, &failed_code, logger);
+    }
+
+    Ok(outcome)
+}
+
+/// Every applied patch becomes its own frame in the agent state timeline, with the
+/// diff in the terminal view and under `patch` for the UI to review.
+fn record_patches(patches: &[AppliedPatch], project_name: &str, from: &str) {
+    if patches.is_empty() {
+        return;
+    }
+    let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
+    for patch in patches {
+        let mut new_state = AgentState::new_state();
+        if let Some(current_state) = agent_state.get_latest_state(project_name) {
+            new_state["browser_session"] = current_state["browser_session"].clone(); // keep the browser session
+        }
+        new_state["internal_monologue"] = json!("Applying patch...");
+        new_state["terminal_session"]["title"] = json!(format!("Patching {}", patch.file));
+        new_state["terminal_session"]["command"] = json!(format!("patch -p1 {}", patch.file));
+        new_state["terminal_session"]["output"] = json!(patch.diff);
+        new_state["patch"] = json!({
+            "file": patch.file,
+            "diff": patch.diff,
+            "from": from,
+        });
+        agent_state.add_to_current_state(project_name, &new_state);
+    }
+    emit_agent("code", json!({
+        "patches": patches,
+        "from": from,
+    }));
+}
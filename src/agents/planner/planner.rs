@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::agents::render_prompt;
+use crate::llm::llm::LLM;
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlannerResponse {
+    pub project: String,
+    pub reply: String,
+    pub focus: String,
+    pub plans: BTreeMap<u32, String>,
+    pub summary: String,
+}
+
+pub struct Planner {
+    llm: LLM,
+}
+
+impl Planner {
+    pub fn new(base_model: &str) -> Self {
+        Self { llm: LLM::new(Some(base_model.to_string())) }
+    }
+
+    pub fn render(&self, prompt: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({ "prompt": prompt }))
+    }
+
+    pub fn parse_response(&self, response: &str) -> PlannerResponse {
+        let mut result = PlannerResponse::default();
+
+        let mut current_section = "";
+        let mut current_step: Option<u32> = None;
+
+        for line in response.lines() {
+            let line = line.trim();
+            let value = || line.split_once(':').map(|(_, value)| value.trim().to_string()).unwrap_or_default();
+
+            if line.starts_with("Project Name:") {
+                current_section = "project";
+                result.project = value();
+            } else if line.starts_with("Your Reply to the Human Prompter:") {
+                current_section = "reply";
+                result.reply = value();
+            } else if line.starts_with("Current Focus:") {
+                current_section = "focus";
+                result.focus = value();
+            } else if line.starts_with("Plan:") {
+                current_section = "plans";
+            } else if line.starts_with("Summary:") {
+                current_section = "summary";
+                result.summary = value();
+            } else if current_section == "reply" {
+                result.reply += &format!(" {}", line);
+            } else if current_section == "focus" {
+                result.focus += &format!(" {}", line);
+            } else if current_section == "plans" {
+                if line.starts_with("- [ ] Step") {
+                    let step = line.split(':').next().unwrap_or_default().trim()
+                        .rsplit(' ').next().unwrap_or_default()
+                        .parse::<u32>().ok();
+                    if let Some(step) = step {
+                        current_step = Some(step);
+                        result.plans.insert(step, value());
+                    }
+                } else if let Some(step) = current_step {
+                    if let Some(plan) = result.plans.get_mut(&step) {
+                        *plan += &format!(" {}", line);
+                    }
+                }
+            } else if current_section == "summary" {
+                result.summary += &format!(" {}", line.replace("```", ""));
+            }
+        }
+
+        result.project = result.project.trim().to_string();
+        result.reply = result.reply.trim().to_string();
+        result.focus = result.focus.trim().to_string();
+        result.summary = result.summary.trim().to_string();
+
+        result
+    }
+
+    pub fn execute(&self, prompt: &str, project_name: &str) -> Result<String, String> {
+        let prompt = self.render(prompt)?;
+        self.llm.inference(&prompt, project_name)
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use tokio::sync::oneshot;
+
+/// Agents waiting for the user to answer a question, by project.
+static PENDING: Lazy<Mutex<HashMap<String, oneshot::Sender<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn project_key(project_name: &str) -> String {
+    project_name.to_lowercase().replace(' ', "-")
+}
+
+/// Registers a question on the project. The receiver gets the next message the user
+/// sends to it; a question asked earlier on the same project is dropped.
+pub fn wait_for_answer(project_name: &str) -> oneshot::Receiver<String> {
+    let (sender, receiver) = oneshot::channel();
+    PENDING.lock().unwrap().insert(project_key(project_name), sender);
+    receiver
+}
+
+pub fn is_waiting(project_name: &str) -> bool {
+    PENDING.lock().unwrap().get(&project_key(project_name)).is_some_and(|sender| !sender.is_closed())
+}
+
+/// Hands `message` to the agent waiting on the project. False when none is, including
+/// one that gave up waiting.
+pub fn answer(project_name: &str, message: &str) -> bool {
+    match PENDING.lock().unwrap().remove(&project_key(project_name)) {
+        Some(sender) => sender.send(message.to_string()).is_ok(),
+        None => false,
+    }
+}
+
+/// Withdraws the project's question, so later messages start a new run.
+pub fn forget(project_name: &str) {
+    PENDING.lock().unwrap().remove(&project_key(project_name));
+}
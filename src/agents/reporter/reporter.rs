@@ -0,0 +1,48 @@
+use serde_json::json;
+
+use crate::agents::render_prompt;
+use crate::llm::llm::LLM;
+use crate::services::utils::retry_wrapper;
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+pub struct Reporter {
+    llm: LLM,
+}
+
+impl Reporter {
+    pub fn new(base_model: &str) -> Self {
+        Self { llm: LLM::new(Some(base_model.to_string())) }
+    }
+
+    pub fn render(&self, conversation: &[String], code_markdown: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({
+            "conversation": conversation,
+            "code_markdown": code_markdown,
+        }))
+    }
+
+    /// The report is raw Markdown; models still like to wrap it in a fence.
+    pub fn validate_response(&self, response: &str) -> Result<String, String> {
+        let mut response = response.trim();
+        for fence in ["```markdown", "```md", "```"] {
+            if let Some(inner) = response.strip_prefix(fence).and_then(|rest| rest.strip_suffix("```")) {
+                response = inner.trim();
+                break;
+            }
+        }
+
+        if response.is_empty() {
+            return Err("The report is empty".to_string());
+        }
+        Ok(response.to_string())
+    }
+
+    pub fn execute(&self, conversation: &[String], code_markdown: &str, project_name: &str) -> Result<String, String> {
+        let prompt = self.render(conversation, code_markdown)?;
+        retry_wrapper(|| {
+            let response = self.llm.inference(&prompt, project_name)?;
+            self.validate_response(&response)
+        })
+    }
+}
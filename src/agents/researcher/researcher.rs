@@ -0,0 +1,63 @@
+use serde::Serialize;
+use serde_json::json;
+
+use crate::agents::render_prompt;
+use crate::llm::llm::LLM;
+use crate::services::utils::{retry_wrapper, validate_responses};
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResearcherResponse {
+    pub queries: Vec<String>,
+    pub ask_user: String,
+}
+
+pub struct Researcher {
+    llm: LLM,
+}
+
+impl Researcher {
+    pub fn new(base_model: &str) -> Self {
+        Self { llm: LLM::new(Some(base_model.to_string())) }
+    }
+
+    pub fn render(&self, step_by_step_plan: &str, contextual_keywords: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({
+            "step_by_step_plan": step_by_step_plan,
+            "contextual_keywords": contextual_keywords,
+        }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<ResearcherResponse, String> {
+        let response = validate_responses(response)?;
+        if response.get("queries").is_none() && response.get("ask_user").is_none() {
+            return Err("Response is missing the `queries` and `ask_user` fields".to_string());
+        }
+        Ok(ResearcherResponse {
+            queries: response["queries"].as_array()
+                .map(|queries| queries.iter().filter_map(|query| query.as_str().map(str::to_string)).collect())
+                .unwrap_or_default(),
+            ask_user: response["ask_user"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    pub fn execute(&self, step_by_step_plan: &str, contextual_keywords: &[String], project_name: &str) -> Result<ResearcherResponse, String> {
+        let contextual_keywords = contextual_keywords.iter()
+            .map(|keyword| {
+                let mut chars = keyword.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let prompt = self.render(step_by_step_plan, &contextual_keywords)?;
+
+        retry_wrapper(|| {
+            let response = self.llm.inference(&prompt, project_name)?;
+            self.validate_response(&response)
+        })
+    }
+}
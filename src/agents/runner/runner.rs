@@ -0,0 +1,330 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::control::{self, CANCELLED};
+use crate::agents::patcher::patcher::Patcher;
+use crate::agents::render_prompt;
+use crate::config::Config;
+use crate::filesystem::project_path;
+use crate::filesystem::read_code::ReadCode;
+use crate::llm::llm::LLM;
+use crate::logger::Logger;
+use crate::sandbox::code_runner::MAX_OUTPUT_BYTES;
+use crate::sandbox::{get_sandbox, kill_leftover_processes, kill_process_group, minimal_env, own_process_group, resolve_workdir, Sandbox, SandboxPolicy};
+use crate::services::utils::{retry_wrapper, validate_responses};
+use crate::state::AgentState;
+#[cfg(unix)]
+use crate::terminal::session::mirror_output;
+
+const PROMPT: &str = include_str!("prompt.jinja2");
+const RERUNNER_PROMPT: &str = include_str!("rerunner.jinja2");
+
+/// What the rerunner prompt decided to do about a failed command.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum RerunAction {
+    Command { command: String, response: String },
+    Patch { response: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub command: String,
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` when the process was killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// What the terminal view and the rerunner prompt get to see.
+    pub fn combined(&self) -> String {
+        let mut output = self.stdout.clone();
+        if !self.stderr.is_empty() {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str(&self.stderr);
+        }
+        output
+    }
+}
+
+/// Splits `command` the way a POSIX shell would (quotes, escapes) and runs it
+/// directly, without a shell, inside `project_path` through the project's sandbox.
+/// Cancelling `cancel` kills the command and everything it started; so does running
+/// past `timeout`, which is reported as a failed command.
+pub fn run_command(command: &str, project_path: &Path, sandbox: &dyn Sandbox, allow_network: bool, timeout: Duration, cancel: &CancellationToken) -> Result<CommandOutput, String> {
+    let words = shell_words::split(command).map_err(|e| format!("Could not parse `{}`: {}", command, e))?;
+    if words.is_empty() {
+        return Err("Empty command".to_string());
+    }
+    let project_path = resolve_workdir(project_path)?;
+
+    let mut process = sandbox.command(&words, &project_path, allow_network)?;
+    minimal_env(&mut process, &project_path);
+    own_process_group(&mut process);
+
+    let mut child = match process.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        // A missing binary is something the rerunner can fix, so it is reported
+        // like any other failed command.
+        Err(e) => return Ok(CommandOutput {
+            command: command.to_string(),
+            stdout: String::new(),
+            stderr: format!("{}: {}", words[0], e),
+            exit_code: Some(127),
+        }),
+    };
+    // Read while waiting, so a chatty command can't fill a pipe and stall.
+    let stdout = child.stdout.take().map(read_to_end);
+    let stderr = child.stderr.take().map(read_to_end);
+
+    let deadline = Instant::now() + timeout;
+    let mut timed_out = false;
+    let exit_code = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status.code(),
+            Ok(None) if cancel.is_cancelled() => {
+                kill_process_group(&mut child);
+                return Err(CANCELLED.to_string());
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                kill_process_group(&mut child);
+                timed_out = true;
+                break None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => {
+                kill_process_group(&mut child);
+                return Err(e.to_string());
+            }
+        }
+    };
+    // Whatever the command left running in the background would keep the pipes open.
+    kill_leftover_processes(child.id());
+    let output_deadline = Instant::now() + Duration::from_secs(2);
+    let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+        let reader = reader?;
+        while !reader.is_finished() && Instant::now() < output_deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        let bytes = if reader.is_finished() { reader.join().ok()? } else { return None };
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    let stdout = collect(stdout).unwrap_or_default();
+    let mut stderr = collect(stderr).unwrap_or_default();
+    if timed_out {
+        if !stderr.is_empty() && !stderr.ends_with('\n') {
+            stderr.push('\n');
+        }
+        stderr.push_str(&format!("Killed after the {}s time limit (RUNNER.TIMEOUT_SECS)", timeout.as_secs()));
+    }
+    Ok(CommandOutput { command: command.to_string(), stdout, stderr, exit_code })
+}
+
+/// Keeps the first `MAX_OUTPUT_BYTES` and drains the rest, so the command doesn't
+/// block on a full pipe.
+fn read_to_end<R: Read + Send + 'static>(mut pipe: R) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        let _ = pipe.by_ref().take(MAX_OUTPUT_BYTES as u64).read_to_end(&mut bytes);
+        if bytes.len() == MAX_OUTPUT_BYTES && std::io::copy(&mut pipe, &mut std::io::sink()).is_ok_and(|dropped| dropped > 0) {
+            bytes.extend_from_slice(b"\n[output truncated]\n");
+        }
+        bytes
+    })
+}
+
+pub struct Runner {
+    max_retries: u32,
+    timeout: Duration,
+    logger: Logger,
+    llm: LLM,
+    patcher: Patcher,
+}
+
+impl Runner {
+    pub fn new(base_model: &str) -> Self {
+        let config = Config::new().unwrap();
+        Self {
+            max_retries: config.get_runner_max_retries(),
+            timeout: Duration::from_secs(config.get_runner_timeout_secs()),
+            logger: Logger::new("devika_agent.log"),
+            llm: LLM::new(Some(base_model.to_string())),
+            patcher: Patcher::new(base_model),
+        }
+    }
+
+    pub fn render(&self, conversation: &[String], code_markdown: &str, system_os: &str) -> Result<String, String> {
+        render_prompt(PROMPT, json!({
+            "conversation": conversation,
+            "code_markdown": code_markdown,
+            "system_os": system_os,
+        }))
+    }
+
+    pub fn render_rerunner(
+        &self,
+        conversation: &[String],
+        code_markdown: &str,
+        system_os: &str,
+        commands: &[String],
+        error: &str,
+    ) -> Result<String, String> {
+        render_prompt(RERUNNER_PROMPT, json!({
+            "conversation": conversation,
+            "code_markdown": code_markdown,
+            "system_os": system_os,
+            "commands": commands,
+            "error": error,
+        }))
+    }
+
+    pub fn validate_response(&self, response: &str) -> Result<Vec<String>, String> {
+        let response = validate_responses(response)?;
+        let commands = response.get("commands").cloned().ok_or("Response is missing `commands`")?;
+        let commands: Vec<String> = serde_json::from_value(commands).map_err(|e| format!("Invalid commands: {}", e))?;
+        if commands.is_empty() {
+            return Err("No commands to run".to_string());
+        }
+        Ok(commands)
+    }
+
+    pub fn validate_rerunner_response(&self, response: &str) -> Result<RerunAction, String> {
+        let response = validate_responses(response)?;
+        serde_json::from_value(response).map_err(|e| format!("Invalid rerunner response: {}", e))
+    }
+
+    pub fn get_project_path(&self, project_name: &str) -> Result<PathBuf, String> {
+        project_path(project_name)
+    }
+
+    fn record_output(&self, project_name: &str, monologue: &str, output: &CommandOutput) {
+        let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
+        let mut new_state = AgentState::new_state();
+        if let Some(current_state) = agent_state.get_latest_state(project_name) {
+            new_state["browser_session"] = current_state["browser_session"].clone(); // keep the browser session
+        }
+        new_state["internal_monologue"] = json!(monologue);
+        new_state["terminal_session"]["title"] = json!("Terminal");
+        new_state["terminal_session"]["command"] = json!(output.command);
+        new_state["terminal_session"]["output"] = json!(output.combined());
+        new_state["terminal_session"]["stdout"] = json!(output.stdout);
+        new_state["terminal_session"]["stderr"] = json!(output.stderr);
+        new_state["terminal_session"]["exit_code"] = output.exit_code.map(Value::from).unwrap_or(Value::Null);
+        agent_state.add_to_current_state(project_name, &new_state);
+    }
+
+    fn run_and_record(&self, command: &str, project_path: &Path, project_name: &str, sandbox: &dyn Sandbox, allow_network: bool) -> Result<CommandOutput, String> {
+        let output = run_command(command, project_path, sandbox, allow_network, self.timeout, &control::cancellation_token(project_name))?;
+        let monologue = if output.success() { "Running code..." } else { "Oh seems like there is some error... :(" };
+        self.record_output(project_name, monologue, &output);
+        #[cfg(unix)]
+        mirror_output(project_name, &format!("\n[devika] $ {}\n{}", command, output.combined()));
+        Ok(output)
+    }
+
+    /// Runs the commands in order. When one fails, the rerunner prompt decides
+    /// between a corrected command and a code fix by the Patcher, and the command
+    /// is retried up to `RUNNER.MAX_RETRIES` times before giving up on the run.
+    /// `report` receives the replies meant for the user; `patched` is called with the
+    /// failing command and the rerunner's reply after each Patcher fix.
+    #[allow(clippy::too_many_arguments)]
+    pub fn run_code<R: Fn(&str), P: Fn(&str, &str)>(
+        &self,
+        commands: &[String],
+        project_name: &str,
+        conversation: &[String],
+        code_markdown: &str,
+        system_os: &str,
+        report: R,
+        patched: P,
+    ) -> Result<Vec<CommandOutput>, String> {
+        let project_path = self.get_project_path(project_name)?;
+        // Installs and builds run here too, so only the isolation applies, not the
+        // CPU and memory limits meant for `/api/run-code`.
+        let policy = SandboxPolicy::for_project(&Config::new().unwrap(), project_name);
+        let sandbox = get_sandbox(&policy.backend)?;
+        let mut outputs = vec![];
+        let mut code_markdown = code_markdown.to_string();
+        let mut executed: Vec<String> = vec![];
+
+        for command in commands {
+            control::checkpoint(project_name, &format!("running `{}`", command))?;
+            let mut command = command.clone();
+            executed.push(command.clone());
+            let mut output = self.run_and_record(&command, &project_path, project_name, sandbox.as_ref(), policy.allow_network)?;
+
+            let mut retries = 0;
+            while !output.success() && retries < self.max_retries {
+                retries += 1;
+
+                let prompt = self.render_rerunner(conversation, &code_markdown, system_os, &executed, &output.combined())?;
+                let action = retry_wrapper(|| {
+                    let response = self.llm.inference(&prompt, project_name)?;
+                    self.validate_rerunner_response(&response)
+                })?;
+
+                match action {
+                    RerunAction::Command { command: fixed, response } => {
+                        report(&response);
+                        command = fixed;
+                        if let Some(last) = executed.last_mut() {
+                            *last = command.clone();
+                        }
+                    }
+                    RerunAction::Patch { response } => {
+                        report(&response);
+                        let patch = self.patcher.execute(conversation, &code_markdown, &executed, &output.combined(), system_os, project_name)?;
+                        if !patch.failed.is_empty() {
+                            self.logger.warning(&format!("{} edit(s) could not be applied", patch.failed.len()));
+                        }
+                        patched(&command, &response);
+                        code_markdown = ReadCode::new(project_name).code_set_to_markdown();
+                    }
+                }
+
+                output = self.run_and_record(&command, &project_path, project_name, sandbox.as_ref(), policy.allow_network)?;
+            }
+
+            let succeeded = output.success();
+            outputs.push(output);
+            if !succeeded {
+                return Err(format!("`{}` still fails after {} retries", command, retries));
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    pub fn execute<R: Fn(&str), P: Fn(&str, &str)>(
+        &self,
+        conversation: &[String],
+        code_markdown: &str,
+        os_system: &str,
+        project_name: &str,
+        report: R,
+        patched: P,
+    ) -> Result<Vec<CommandOutput>, String> {
+        let prompt = self.render(conversation, code_markdown, os_system)?;
+        let commands = retry_wrapper(|| {
+            let response = self.llm.inference(&prompt, project_name)?;
+            self.validate_response(&response)
+        })?;
+
+        self.run_code(&commands, project_name, conversation, code_markdown, os_system, report, patched)
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::config::Config;
+use crate::memory::embedder::{cosine_similarity, get_embedder, Embedder};
+
+/// The scikit-learn English list KeyBERT filters with.
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "about", "above", "across", "after", "afterwards", "again", "against", "all", "almost", "alone", "along",
+    "already", "also", "although", "always", "am", "among", "amongst", "an", "and", "another", "any", "anyhow",
+    "anyone", "anything", "anyway", "anywhere", "are", "around", "as", "at", "back", "be", "became", "because",
+    "become", "becomes", "becoming", "been", "before", "beforehand", "behind", "being", "below", "beside", "besides",
+    "between", "beyond", "both", "but", "by", "can", "cannot", "could", "do", "does", "done", "down", "due", "during",
+    "each", "eg", "either", "else", "elsewhere", "enough", "etc", "even", "ever", "every", "everyone", "everything",
+    "everywhere", "except", "few", "first", "for", "former", "formerly", "from", "further", "get", "give", "go", "had",
+    "has", "have", "he", "hence", "her", "here", "hereafter", "hereby", "herein", "hereupon", "hers", "herself", "him",
+    "himself", "his", "how", "however", "i", "ie", "if", "in", "indeed", "into", "is", "it", "its", "itself", "just",
+    "keep", "last", "latter", "latterly", "least", "less", "made", "many", "may", "me", "meanwhile", "might", "mine",
+    "more", "moreover", "most", "mostly", "much", "must", "my", "myself", "namely", "neither", "never", "nevertheless",
+    "next", "no", "nobody", "none", "noone", "nor", "not", "nothing", "now", "nowhere", "of", "off", "often", "on",
+    "once", "one", "only", "onto", "or", "other", "others", "otherwise", "our", "ours", "ourselves", "out", "over",
+    "own", "part", "per", "perhaps", "please", "put", "rather", "re", "same", "see", "seem", "seemed", "seeming",
+    "seems", "several", "she", "should", "show", "since", "so", "some", "somehow", "someone", "something", "sometime",
+    "sometimes", "somewhere", "still", "such", "take", "than", "that", "the", "their", "them", "themselves", "then",
+    "thence", "there", "thereafter", "thereby", "therefore", "therein", "thereupon", "these", "they", "this", "those",
+    "though", "through", "throughout", "thru", "thus", "to", "together", "too", "toward", "towards", "under", "until",
+    "up", "upon", "us", "very", "via", "was", "we", "well", "were", "what", "whatever", "when", "whence", "whenever",
+    "where", "whereafter", "whereas", "whereby", "wherein", "whereupon", "wherever", "whether", "which", "while",
+    "whither", "who", "whoever", "whole", "whom", "whose", "why", "will", "with", "within", "without", "would", "yet",
+    "you", "your", "yours", "yourself", "yourselves",
+];
+
+/// Words every plan and request is full of; as search keywords they only add noise.
+const INSTRUCTION_STOP_WORDS: &[&str] = &[
+    "able", "add", "allow", "allows", "based", "basic", "build", "building", "create", "creating", "develop",
+    "developing", "ensure", "help", "implement", "implementing", "include", "including", "like", "make", "making",
+    "need", "needs", "new", "proper", "simple", "step", "steps", "sure", "use", "used", "user's", "uses", "using",
+    "want", "way", "work", "works", "write", "writing",
+];
+
+/// How much YAKE counts against RAKE. RAKE alone favours whatever sits in the longest
+/// phrase; YAKE picks out names and terms the text leads with.
+const YAKE_WEIGHT: f32 = 0.7;
+
+/// Extracts the focus keywords of a sentence for the Researcher. Replaces KeyBERT
+/// with statistics a Rust build can compute itself: YAKE's term features and RAKE's
+/// degree over frequency rank the words, and Maximal Marginal Relevance picks a
+/// diverse set from them. When `[RAG] EMBEDDER` is a model, embedding similarity to
+/// the sentence joins the ranking, much like KeyBERT's.
+pub struct SentenceBert {
+    sentence: String,
+    diversity: f32,
+    embedder: Option<Box<dyn Embedder>>,
+}
+
+/// A candidate keyword and the statistics it is ranked by.
+#[derive(Default)]
+struct Term {
+    /// Occurrences in the text.
+    frequency: usize,
+    /// Occurrences capitalized mid-sentence or written as an acronym.
+    capitalized: usize,
+    sentences: Vec<usize>,
+    left: HashSet<String>,
+    left_total: usize,
+    right: HashSet<String>,
+    right_total: usize,
+    /// RAKE's degree: the lengths of the stop-word-free phrases the term is in.
+    degree: usize,
+}
+
+impl SentenceBert {
+    pub fn new(sentence: &str) -> Self {
+        let config = Config::new().unwrap();
+        let embedder_name = config.get_rag_embedder().to_lowercase();
+        // Hashed words say nothing about meaning the statistics don't already.
+        let embedder = (config.get_keywords_embeddings() && matches!(embedder_name.as_str(), "ollama" | "openai"))
+            .then(|| get_embedder(&embedder_name).ok())
+            .flatten();
+        Self {
+            sentence: sentence.to_string(),
+            diversity: config.get_keywords_diversity().clamp(0.0, 1.0),
+            embedder,
+        }
+    }
+
+    /// Up to `top_n` lowercase keywords with their relevance between 0 and 1, in the
+    /// order they were picked.
+    pub fn extract_keywords(&self, top_n: usize) -> Vec<(String, f32)> {
+        let terms = collect_terms(&self.sentence);
+        if terms.is_empty() || top_n == 0 {
+            return vec![];
+        }
+        // Sorted, so ties go the same way on every run.
+        let mut words: Vec<String> = terms.keys().cloned().collect();
+        words.sort();
+        let mut relevance = statistical_relevance(&words, &terms, sentences(&self.sentence).len());
+
+        let embeddings = self.embedder.as_ref().and_then(|embedder| {
+            let mut texts = vec![self.sentence.clone()];
+            texts.extend(words.iter().cloned());
+            // The statistics alone still give keywords when the model is unreachable.
+            embedder.embed(&texts).ok()
+        });
+        let similarity: Box<dyn Fn(usize, usize) -> f32> = match &embeddings {
+            Some(vectors) => {
+                let to_sentence: Vec<f32> = vectors[1..].iter().map(|vector| cosine_similarity(vector, &vectors[0])).collect();
+                let best = to_sentence.iter().cloned().fold(0.0, f32::max);
+                if best > 0.0 {
+                    for (relevance, similarity) in relevance.iter_mut().zip(&to_sentence) {
+                        *relevance = (*relevance + similarity.max(0.0) / best) / 2.0;
+                    }
+                }
+                Box::new(move |a, b| cosine_similarity(&vectors[a + 1], &vectors[b + 1]))
+            }
+            None => Box::new(|a, b| trigram_similarity(&words[a], &words[b])),
+        };
+
+        maximal_marginal_relevance(&relevance, similarity.as_ref(), self.diversity, top_n)
+            .into_iter()
+            .map(|index| (words[index].clone(), relevance[index]))
+            .collect()
+    }
+}
+
+fn is_stop_word(word: &str) -> bool {
+    ENGLISH_STOP_WORDS.contains(&word) || INSTRUCTION_STOP_WORDS.contains(&word)
+}
+
+/// Sentences split at `.`, `!`, `?`, `;` and line breaks, keeping `index.html` and
+/// `v1.2` whole.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let next_is_space = chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if c == '\n' || (matches!(c, '.' | '!' | '?' | ';') && next_is_space) {
+            sentences.push(&text[start..index]);
+            start = index + c.len_utf8();
+        }
+    }
+    sentences.push(&text[start..]);
+    sentences.into_iter().map(str::trim).filter(|sentence| !sentence.is_empty()).collect()
+}
+
+/// Words as written, with the characters of names like `c++`, `c#` and `node.js`.
+fn tokens(sentence: &str) -> Vec<&str> {
+    sentence.split(|c: char| !(c.is_alphanumeric() || matches!(c, '+' | '#' | '.' | '_' | '-' | '\'')))
+        .map(|token| token.trim_matches(|c| matches!(c, '.' | '_' | '-' | '\'')))
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+fn is_candidate(word: &str) -> bool {
+    word.chars().count() >= 2 && word.chars().any(char::is_alphabetic) && !is_stop_word(word)
+}
+
+fn collect_terms(text: &str) -> HashMap<String, Term> {
+    let mut terms: HashMap<String, Term> = HashMap::new();
+    for (sentence_index, sentence) in sentences(text).into_iter().enumerate() {
+        let words = tokens(sentence);
+        let lowercase: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+        let mut phrase_length = 0;
+
+        for (position, word) in words.iter().enumerate() {
+            if !is_candidate(&lowercase[position]) {
+                phrase_length = 0;
+                continue;
+            }
+            phrase_length += 1;
+            let term = terms.entry(lowercase[position].clone()).or_default();
+            term.frequency += 1;
+            let acronym = word.chars().count() > 1 && word.chars().all(|c| !c.is_lowercase()) && word.chars().any(char::is_uppercase);
+            let capitalized = position > 0 && word.starts_with(char::is_uppercase);
+            if acronym || capitalized {
+                term.capitalized += 1;
+            }
+            term.sentences.push(sentence_index);
+            // Like YAKE, only other candidates count as context.
+            if position > 0 && is_candidate(&lowercase[position - 1]) {
+                term.left.insert(lowercase[position - 1].clone());
+                term.left_total += 1;
+            }
+            if position + 1 < words.len() && is_candidate(&lowercase[position + 1]) {
+                term.right.insert(lowercase[position + 1].clone());
+                term.right_total += 1;
+            }
+
+            // At the end of a phrase, every word in it gains the phrase's length.
+            let phrase_ends = position + 1 == words.len() || !is_candidate(&lowercase[position + 1]);
+            if phrase_ends {
+                for word in &lowercase[position + 1 - phrase_length..=position] {
+                    if let Some(term) = terms.get_mut(word) {
+                        term.degree += phrase_length;
+                    }
+                }
+            }
+        }
+    }
+    terms
+}
+
+/// The average of YAKE's and RAKE's scores, each scaled to 0..1.
+fn statistical_relevance(words: &[String], terms: &HashMap<String, Term>, sentence_count: usize) -> Vec<f32> {
+    let frequencies: Vec<f32> = words.iter().map(|word| terms[word].frequency as f32).collect();
+    let mean = frequencies.iter().sum::<f32>() / frequencies.len() as f32;
+    let deviation = (frequencies.iter().map(|frequency| (frequency - mean).powi(2)).sum::<f32>() / frequencies.len() as f32).sqrt();
+    let max_frequency = frequencies.iter().cloned().fold(1.0, f32::max);
+
+    let yake: Vec<f32> = words.iter()
+        .map(|word| {
+            let term = &terms[word];
+            let frequency = term.frequency as f32;
+            let casing = term.capitalized as f32 / (1.0 + frequency.ln());
+            let mut positions = term.sentences.clone();
+            positions.sort_unstable();
+            let middle = positions.len() / 2;
+            let median = if positions.len().is_multiple_of(2) {
+                (positions[middle - 1] + positions[middle]) as f32 / 2.0
+            } else {
+                positions[middle] as f32
+            };
+            let position = (3.0 + median).ln().ln();
+            let normalized_frequency = frequency / (mean + deviation);
+            let spread = |distinct: usize, total: usize| if total == 0 { 0.0 } else { distinct as f32 / total as f32 };
+            let context = 1.0 + (spread(term.left.len(), term.left_total) + spread(term.right.len(), term.right_total)) * frequency / max_frequency;
+            let distinct_sentences = positions.iter().collect::<HashSet<_>>().len() as f32;
+            let sentence_share = distinct_sentences / sentence_count.max(1) as f32;
+            // YAKE's score is lower for better keywords.
+            let score = context * position / (casing + normalized_frequency / context + sentence_share / context);
+            1.0 / score.max(f32::EPSILON)
+        })
+        .collect();
+    let rake: Vec<f32> = words.iter()
+        .map(|word| terms[word].degree as f32 / terms[word].frequency as f32)
+        .collect();
+
+    let yake = scale(&yake);
+    let rake = scale(&rake);
+    yake.iter().zip(&rake).map(|(yake, rake)| YAKE_WEIGHT * yake + (1.0 - YAKE_WEIGHT) * rake).collect()
+}
+
+fn scale(values: &[f32]) -> Vec<f32> {
+    let max = values.iter().cloned().fold(0.0, f32::max);
+    values.iter().map(|value| if max > 0.0 { value / max } else { 0.0 }).collect()
+}
+
+/// Character trigrams two words share, over those of the shorter one, so "route",
+/// "routes" and "routing" count as much the same keyword while "rest" and "rust" don't.
+fn trigram_similarity(a: &str, b: &str) -> f32 {
+    let trigrams = |word: &str| -> HashSet<Vec<char>> {
+        let chars: Vec<char> = word.chars().collect();
+        chars.windows(3).map(<[char]>::to_vec).collect()
+    };
+    let (a, b) = (trigrams(a), trigrams(b));
+    let smaller = a.len().min(b.len());
+    if smaller == 0 { 0.0 } else { a.intersection(&b).count() as f32 / smaller as f32 }
+}
+
+/// Indices picked one at a time for relevance, minus `diversity` times the similarity
+/// to the closest keyword already picked.
+fn maximal_marginal_relevance(relevance: &[f32], similarity: &dyn Fn(usize, usize) -> f32, diversity: f32, top_n: usize) -> Vec<usize> {
+    let mut picked: Vec<usize> = vec![];
+    let mut remaining: Vec<usize> = (0..relevance.len()).collect();
+    while picked.len() < top_n && !remaining.is_empty() {
+        let score = |&candidate: &usize| {
+            let redundancy = picked.iter().map(|&keyword| similarity(candidate, keyword)).fold(0.0, f32::max);
+            (1.0 - diversity) * relevance[candidate] - diversity * redundancy
+        };
+        let (position, _) = remaining.iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| score(a).total_cmp(&score(b)))
+            .unwrap();
+        picked.push(remaining.remove(position));
+    }
+    picked
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::time::Duration;
+
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use serde_json::json;
+use url::Url;
+
+use crate::browser::readability::extract_article;
+use crate::browser::robots::RobotsRules;
+use crate::config::Config;
+use crate::state::AgentState;
+
+const USER_AGENT: &str = "devika-rs/0.1";
+const MAX_REDIRECTS: usize = 10;
+const MAX_PAGE_BYTES: u64 = 2 * 1024 * 1024;
+const MAX_ROBOTS_BYTES: u64 = 512 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+const ALLOWED_CONTENT_TYPES: &[&str] = &[
+    "text/html",
+    "application/xhtml+xml",
+    "text/plain",
+    "text/markdown",
+];
+
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub url: String,
+    pub title: String,
+    pub content_type: String,
+    pub text: String,
+}
+
+/// Plain HTTP replacement for the Playwright-backed `Agent.open_page`.
+///
+/// Redirects are followed by hand so every hop is checked against robots.txt,
+/// and bodies are capped at `MAX_PAGE_BYTES` whatever the server claims.
+pub struct PageFetcher {
+    client: reqwest::blocking::Client,
+    robots_cache: HashMap<String, RobotsRules>,
+}
+
+impl PageFetcher {
+    pub fn new() -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(USER_AGENT)
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .unwrap();
+        Self { client, robots_cache: HashMap::new() }
+    }
+
+    /// Fetches `url`, records it in the project's `browser_session` and returns
+    /// the readable text for the Formatter.
+    pub fn open_page(&mut self, project_name: &str, url: &str) -> Result<FetchedPage, String> {
+        let page = self.fetch(url)?;
+
+        let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
+        let mut new_state = AgentState::new_state();
+        new_state["internal_monologue"] = json!("Browsing the web right now...");
+        new_state["browser_session"]["url"] = json!(page.url);
+        agent_state.add_to_current_state(project_name, &new_state);
+
+        Ok(page)
+    }
+
+    pub fn fetch(&mut self, url: &str) -> Result<FetchedPage, String> {
+        let mut current = Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            if !matches!(current.scheme(), "http" | "https") {
+                return Err(format!("Unsupported URL scheme: {}", current.scheme()));
+            }
+            if !self.is_allowed(&current) {
+                return Err(format!("Fetching {} is disallowed by robots.txt", current));
+            }
+
+            let response = self.client.get(current.clone()).send().map_err(|e| e.to_string())?;
+
+            if response.status().is_redirection() {
+                let location = response.headers().get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .ok_or_else(|| format!("Redirect from {} without a Location header", current))?;
+                current = current.join(location).map_err(|e| e.to_string())?;
+                continue;
+            }
+
+            let response = response.error_for_status().map_err(|e| e.to_string())?;
+            let content_type = response.headers().get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .map(|content_type| content_type.split(';').next().unwrap_or_default().trim().to_lowercase())
+                .unwrap_or_else(|| "text/html".to_string());
+            if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) {
+                return Err(format!("Unsupported content type {} at {}", content_type, current));
+            }
+
+            let declared_length = response.headers().get(CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok())
+                .and_then(|length| length.parse::<u64>().ok());
+            if declared_length.is_some_and(|length| length > MAX_PAGE_BYTES) {
+                return Err(format!("Page at {} is larger than {} bytes", current, MAX_PAGE_BYTES));
+            }
+
+            let body = read_limited(response, MAX_PAGE_BYTES)?;
+            let (title, text) = if content_type.contains("html") {
+                let article = extract_article(&body);
+                (article.title, article.text)
+            } else {
+                (String::new(), body)
+            };
+
+            return Ok(FetchedPage { url: current.to_string(), title, content_type, text });
+        }
+
+        Err(format!("Too many redirects while fetching {}", url))
+    }
+
+    fn is_allowed(&mut self, url: &Url) -> bool {
+        let origin = url.origin().ascii_serialization();
+        if !self.robots_cache.contains_key(&origin) {
+            let rules = self.fetch_robots(&origin);
+            self.robots_cache.insert(origin.clone(), rules);
+        }
+
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        self.robots_cache[&origin].is_allowed(&path)
+    }
+
+    /// A missing or unreadable robots.txt means everything is allowed.
+    fn fetch_robots(&self, origin: &str) -> RobotsRules {
+        let response = match self.client.get(format!("{}/robots.txt", origin)).send() {
+            Ok(response) if response.status().is_success() => response,
+            _ => return RobotsRules::allow_all(),
+        };
+        match read_limited(response, MAX_ROBOTS_BYTES) {
+            Ok(robots_txt) => RobotsRules::parse(&robots_txt, USER_AGENT),
+            Err(_) => RobotsRules::allow_all(),
+        }
+    }
+}
+
+impl Default for PageFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_limited(response: reqwest::blocking::Response, limit: u64) -> Result<String, String> {
+    let mut body = Vec::new();
+    response.take(limit + 1).read_to_end(&mut body).map_err(|e| e.to_string())?;
+    if body.len() as u64 > limit {
+        return Err(format!("Response body exceeds {} bytes", limit));
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
@@ -0,0 +1,4 @@
+pub mod fetcher;
+pub mod readability;
+pub mod robots;
+pub mod search;
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use scraper::{ElementRef, Html, Node, Selector};
+
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "svg", "canvas", "form",
+    "button", "input", "select", "textarea", "nav", "header", "footer", "aside",
+];
+
+const BLOCK_TAGS: &[&str] = &[
+    "p", "div", "section", "article", "main", "blockquote", "table", "tr",
+    "ul", "ol", "dl", "dt", "dd", "figure", "figcaption", "hr",
+];
+
+const POSITIVE_HINTS: &[&str] = &[
+    "article", "content", "main", "post", "entry", "body", "text", "blog", "story", "doc", "markdown",
+];
+
+const NEGATIVE_HINTS: &[&str] = &[
+    "comment", "footer", "header", "nav", "sidebar", "menu", "share", "related", "promo",
+    "cookie", "banner", "social", "breadcrumb", "advert", "popup", "newsletter", "subscribe",
+];
+
+#[derive(Debug, Clone)]
+pub struct Article {
+    pub title: String,
+    pub text: String,
+}
+
+/// Extracts the readable article out of an HTML page, readability-style:
+/// boilerplate containers are dropped, the densest block of prose is picked
+/// as the article root, and `<pre>` blocks are kept verbatim as fenced code.
+pub fn extract_article(html: &str) -> Article {
+    let document = Html::parse_document(html);
+
+    let title = Selector::parse("title").ok()
+        .and_then(|selector| document.select(&selector).next())
+        .map(|title| collapse_whitespace(&title.text().collect::<String>()))
+        .unwrap_or_default();
+
+    let candidates = Selector::parse("article, main, section, div, td, body").unwrap();
+    let paragraphs = Selector::parse("p, pre, li, blockquote, td").unwrap();
+
+    // Each paragraph counts in full for the nearest candidate around it and by half
+    // for the next one up, as in Readability, so a wrapper div does not beat the
+    // article it wraps just by containing it.
+    let mut prose = HashMap::new();
+    for paragraph in document.select(&paragraphs) {
+        let Some(score) = paragraph_score(&paragraph) else { continue };
+        let containers = paragraph.ancestors()
+            .filter_map(ElementRef::wrap)
+            .filter(|ancestor| candidates.matches(ancestor));
+        for (container, share) in containers.zip([1.0, 0.5]) {
+            *prose.entry(container.id()).or_insert(0.0) += score * share;
+        }
+    }
+
+    let root = document.select(&candidates)
+        .filter(|element| !is_boilerplate(element))
+        .map(|element| (score(&element, prose.get(&element.id()).copied().unwrap_or(0.0)), element))
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, element)| element)
+        .unwrap_or_else(|| document.root_element());
+
+    let mut text = String::new();
+    render(&root, &mut text);
+
+    Article { title, text: tidy(&text) }
+}
+
+fn hints(element: &ElementRef) -> String {
+    let value = element.value();
+    format!("{} {}", value.attr("class").unwrap_or_default(), value.id().unwrap_or_default()).to_lowercase()
+}
+
+fn is_boilerplate(element: &ElementRef) -> bool {
+    let name = element.value().name();
+    if SKIPPED_TAGS.contains(&name) {
+        return true;
+    }
+    if matches!(name, "body" | "main" | "article") {
+        return false;
+    }
+    let hints = hints(element);
+    NEGATIVE_HINTS.iter().any(|hint| hints.contains(hint)) && !POSITIVE_HINTS.iter().any(|hint| hints.contains(hint))
+}
+
+/// How much prose a paragraph holds; `None` for ones too short to count.
+fn paragraph_score(paragraph: &ElementRef) -> Option<f64> {
+    let length = paragraph.text().map(str::trim).map(str::len).sum::<usize>();
+    if length < 25 {
+        return None;
+    }
+    let commas = paragraph.text().collect::<String>().matches(',').count() as f64;
+    Some(1.0 + (length as f64 / 100.0).min(3.0) + commas)
+}
+
+/// Scores a candidate from the `prose` credited to it, its class and id hints and
+/// how much of its text is links.
+fn score(element: &ElementRef, prose: f64) -> f64 {
+    let links = Selector::parse("a").unwrap();
+
+    let mut score = prose;
+
+    let text_length = element.text().map(str::len).sum::<usize>().max(1) as f64;
+    let link_length = element.select(&links).flat_map(|link| link.text()).map(str::len).sum::<usize>() as f64;
+    let link_density = link_length / text_length;
+
+    let hints = hints(element);
+    if POSITIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        score += 25.0;
+    }
+    if NEGATIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        score -= 25.0;
+    }
+    if matches!(element.value().name(), "article" | "main") {
+        score += 10.0;
+    }
+
+    score * (1.0 - link_density)
+}
+
+fn render(element: &ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&collapse_inline(text)),
+            Node::Element(_) => {
+                let Some(child) = ElementRef::wrap(child) else { continue };
+                if is_boilerplate(&child) {
+                    continue;
+                }
+                render_element(&child, out);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn render_element(element: &ElementRef, out: &mut String) {
+    let name = element.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            let heading = collapse_whitespace(&element.text().collect::<String>());
+            if !heading.is_empty() {
+                out.push_str(&format!("\n\n{} {}\n\n", "#".repeat(level), heading));
+            }
+        }
+        "pre" => {
+            let code = element.text().collect::<String>();
+            let language = Selector::parse("code").ok()
+                .and_then(|selector| element.select(&selector).next())
+                .and_then(|code| code.value().attr("class").map(str::to_string))
+                .and_then(|class| class.split_whitespace()
+                    .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")).map(str::to_string)))
+                .unwrap_or_default();
+            out.push_str(&format!("\n\n```{}\n{}\n```\n\n", language, code.trim_end_matches('\n')));
+        }
+        "code" => {
+            let code = element.text().collect::<String>();
+            out.push_str(&format!("`{}`", code.trim()));
+        }
+        "br" => out.push('\n'),
+        "li" => {
+            out.push_str("\n- ");
+            render(element, out);
+        }
+        "img" => {
+            if let Some(alt) = element.value().attr("alt").filter(|alt| !alt.trim().is_empty()) {
+                out.push_str(&format!("[image: {}]", alt.trim()));
+            }
+        }
+        "td" | "th" => {
+            render(element, out);
+            out.push_str(" | ");
+        }
+        _ if BLOCK_TAGS.contains(&name) => {
+            out.push_str("\n\n");
+            render(element, out);
+            out.push_str("\n\n");
+        }
+        _ => render(element, out),
+    }
+}
+
+fn collapse_inline(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut last_was_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                collapsed.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            collapsed.push(c);
+            last_was_space = false;
+        }
+    }
+    collapsed
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Trims each line and squeezes blank runs, leaving fenced code blocks untouched.
+fn tidy(text: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            lines.push(line.trim().to_string());
+            continue;
+        }
+        if in_code {
+            lines.push(line.to_string());
+            continue;
+        }
+        let line = line.trim();
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_article_over_the_div_that_wraps_it() {
+        let paragraph = "<p>Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor.</p>";
+        let html = format!(
+            "<html><head><title>Post</title></head><body><div>\
+             <div>Signed in as someone, with a long enough line of chrome text.</div>\
+             <article>{}</article>\
+             <p>Also read: one, two, three, four, five, six, seven, eight, nine, ten, eleven, twelve.</p>\
+             </div></body></html>",
+            paragraph.repeat(3),
+        );
+
+        let article = extract_article(&html);
+        assert_eq!(article.title, "Post");
+        assert!(article.text.starts_with("Lorem ipsum"), "{}", article.text);
+        assert!(!article.text.contains("Also read"), "{}", article.text);
+        assert!(!article.text.contains("Signed in"), "{}", article.text);
+    }
+}
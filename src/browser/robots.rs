@@ -0,0 +1,134 @@
+/// The subset of robots.txt that matters to a single crawler: the `Allow`/`Disallow`
+/// rules of the group matching our user agent, or of the `*` group otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<(bool, String)>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self {
+        Self { rules: vec![] }
+    }
+
+    pub fn parse(robots_txt: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_lowercase();
+        let mut specific = vec![];
+        let mut wildcard = vec![];
+        let mut found_specific = false;
+
+        let mut group_agents: Vec<String> = vec![];
+        let mut in_rules = false;
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        group_agents.clear();
+                        in_rules = false;
+                    }
+                    group_agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow means "allow everything" and carries no rule.
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (key == "allow", value.to_string());
+                    if group_agents.iter().any(|agent| !agent.is_empty() && agent != "*" && user_agent.contains(agent.as_str())) {
+                        found_specific = true;
+                        specific.push(rule.clone());
+                    }
+                    if group_agents.iter().any(|agent| agent == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self { rules: if found_specific { specific } else { wildcard } }
+    }
+
+    /// Longest matching rule wins; `Allow` wins ties, as in Google's implementation.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pattern) in &self.rules {
+            if Self::matches(pattern, path) {
+                let len = pattern.len();
+                best = match best {
+                    Some((best_len, best_allow)) if best_len > len || (best_len == len && best_allow) => Some((best_len, best_allow)),
+                    _ => Some((len, *allow)),
+                };
+            }
+        }
+        best.map(|(_, allow)| allow).unwrap_or(true)
+    }
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        let (pattern, anchored) = match pattern.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (pattern, false),
+        };
+
+        let parts: Vec<&str> = pattern.split('*').collect();
+        let first = parts[0];
+        if !path.starts_with(first) {
+            return false;
+        }
+        if parts.len() == 1 {
+            return !anchored || path.len() == first.len();
+        }
+
+        // An anchored pattern's last part has to end the path, so only the parts
+        // in between are matched left to right.
+        let last = parts[parts.len() - 1];
+        let middle = if anchored { &parts[1..parts.len() - 1] } else { &parts[1..] };
+        let mut position = first.len();
+        for part in middle {
+            match path[position..].find(part) {
+                Some(index) => position += index + part.len(),
+                None => return false,
+            }
+        }
+        !anchored || (path.len() >= position + last.len() && path.ends_with(last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RobotsRules;
+
+    #[test]
+    fn matches_anchored_patterns_at_the_end_only() {
+        assert!(RobotsRules::matches("/foo$", "/foo"));
+        assert!(!RobotsRules::matches("/foo$", "/foo/x/foo"));
+        assert!(!RobotsRules::matches("/foo$", "/foobar"));
+        assert!(RobotsRules::matches("/*.php$", "/index.php"));
+        assert!(!RobotsRules::matches("/*.php$", "/index.php?x=1"));
+        assert!(!RobotsRules::matches("/a*b$", "/ab/"));
+        assert!(RobotsRules::matches("/a*ba$", "/aba"));
+        assert!(!RobotsRules::matches("/ab*b$", "/ab"));
+    }
+
+    #[test]
+    fn matches_unanchored_patterns_as_prefixes() {
+        assert!(RobotsRules::matches("/foo", "/foo/bar"));
+        assert!(RobotsRules::matches("/*/private", "/a/private/x"));
+        assert!(!RobotsRules::matches("/*/private", "/a/public"));
+        assert!(RobotsRules::matches("/", "/anything"));
+    }
+
+    #[test]
+    fn longest_rule_wins_and_allow_wins_ties() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /docs\nAllow: /docs/public\nDisallow: /*.pdf$\n", "devika");
+        assert!(!rules.is_allowed("/docs/secret"));
+        assert!(rules.is_allowed("/docs/public/index.html"));
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf.html"));
+    }
+}
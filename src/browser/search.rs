@@ -0,0 +1,359 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::Config;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub href: String,
+    pub body: String,
+}
+
+pub trait SearchEngine: Send {
+    fn search(&mut self, query: &str) -> Result<(), String>;
+    fn results(&self) -> &[SearchResult];
+
+    fn get_first_link(&self) -> Option<String> {
+        self.results().first().map(|result| result.href.clone())
+    }
+}
+
+/// Picks the search backend matching the engine name sent by the UI.
+/// Anything unknown falls back to DuckDuckGo, same as the Python agent.
+pub fn get_search_engine(engine: &str) -> Box<dyn SearchEngine> {
+    match engine.to_lowercase().as_str() {
+        "bing" => Box::new(BingSearch::new()),
+        "google" => Box::new(GoogleSearch::new()),
+        "searxng" => Box::new(SearxngSearch::new()),
+        "fixture" => Box::new(FixtureSearch::new()),
+        _ => Box::new(DuckDuckGoSearch::new()),
+    }
+}
+
+pub struct BingSearch {
+    client: reqwest::blocking::Client,
+    api_key: String,
+    api_endpoint: String,
+    query_result: Vec<SearchResult>,
+}
+
+impl BingSearch {
+    pub fn new() -> Self {
+        let config = Config::new().unwrap();
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api_key: config.get_bing_api_key().to_string(),
+            api_endpoint: config.get_bing_api_endpoint().to_string(),
+            query_result: vec![],
+        }
+    }
+}
+
+impl Default for BingSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchEngine for BingSearch {
+    fn search(&mut self, query: &str) -> Result<(), String> {
+        let response: Value = self.client.get(&self.api_endpoint)
+            .header("Ocp-Apim-Subscription-Key", &self.api_key)
+            .query(&[("q", query), ("mkt", "en-US")])
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| e.to_string())?;
+
+        self.query_result = response["webPages"]["value"].as_array()
+            .map(|items| items.iter().map(|item| SearchResult {
+                title: item["name"].as_str().unwrap_or_default().to_string(),
+                href: item["url"].as_str().unwrap_or_default().to_string(),
+                body: item["snippet"].as_str().unwrap_or_default().to_string(),
+            }).collect())
+            .unwrap_or_default();
+        Ok(())
+    }
+
+    fn results(&self) -> &[SearchResult] {
+        &self.query_result
+    }
+}
+
+pub struct GoogleSearch {
+    client: reqwest::blocking::Client,
+    api_key: String,
+    search_engine_id: String,
+    api_endpoint: String,
+    query_result: Vec<SearchResult>,
+}
+
+impl GoogleSearch {
+    pub fn new() -> Self {
+        let config = Config::new().unwrap();
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api_key: config.get_google_search_api_key().to_string(),
+            search_engine_id: config.get_google_search_engine_id().to_string(),
+            api_endpoint: config.get_google_search_api_endpoint().to_string(),
+            query_result: vec![],
+        }
+    }
+}
+
+impl Default for GoogleSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchEngine for GoogleSearch {
+    fn search(&mut self, query: &str) -> Result<(), String> {
+        let response: Value = self.client.get(&self.api_endpoint)
+            .query(&[("key", self.api_key.as_str()), ("cx", self.search_engine_id.as_str()), ("q", query)])
+            .send()
+            .and_then(|response| response.json())
+            .map_err(|e| e.to_string())?;
+
+        self.query_result = response["items"].as_array()
+            .map(|items| items.iter().map(|item| SearchResult {
+                title: item["title"].as_str().unwrap_or_default().to_string(),
+                href: item["link"].as_str().unwrap_or_default().to_string(),
+                body: item["snippet"].as_str().unwrap_or_default().to_string(),
+            }).collect())
+            .unwrap_or_default();
+        Ok(())
+    }
+
+    fn results(&self) -> &[SearchResult] {
+        &self.query_result
+    }
+}
+
+pub struct DuckDuckGoSearch {
+    client: reqwest::blocking::Client,
+    query_result: Vec<SearchResult>,
+}
+
+impl DuckDuckGoSearch {
+    pub fn new() -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        Self { client, query_result: vec![] }
+    }
+
+    fn extract_vqd(html: &str) -> Option<String> {
+        let patterns = [("vqd=\"", "\""), ("vqd=", "&"), ("vqd='", "'")];
+        for (start_pattern, end_pattern) in patterns {
+            if let Some(start) = html.find(start_pattern).map(|index| index + start_pattern.len()) {
+                if let Some(end) = html[start..].find(end_pattern) {
+                    return Some(html[start..start + end].to_string());
+                }
+            }
+        }
+        None
+    }
+
+    fn text_extract_json(html: &str) -> Option<Vec<Value>> {
+        let start = html.find("DDG.pageLayout.load('d',")? + 24;
+        let end = html[start..].find(");DDG.duckbar.load(")? + start;
+        serde_json::from_str(&html[start..end]).ok()
+    }
+
+    fn normalize(raw_html: &str) -> String {
+        let mut text = String::new();
+        let mut in_tag = false;
+        for c in raw_html.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                _ if !in_tag => text.push(c),
+                _ => {}
+            }
+        }
+        text.replace("&amp;", "&")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#x27;", "'")
+            .replace("&#39;", "'")
+    }
+
+    fn normalize_url(url: &str) -> String {
+        let bytes = url.replace(' ', "+").into_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let hex = bytes.get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match (bytes[i], hex) {
+                (b'%', Some(byte)) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                (byte, _) => {
+                    decoded.push(byte);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+}
+
+impl Default for DuckDuckGoSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchEngine for DuckDuckGoSearch {
+    fn search(&mut self, query: &str) -> Result<(), String> {
+        let html = self.client.post("https://duckduckgo.com/")
+            .header("Referer", "https://duckduckgo.com/")
+            .form(&[("q", query)])
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|e| e.to_string())?;
+        let vqd = Self::extract_vqd(&html).ok_or("Could not extract vqd from DuckDuckGo response")?;
+
+        let html = self.client.get("https://links.duckduckgo.com/d.js")
+            .header("Referer", "https://duckduckgo.com/")
+            .query(&[("q", query), ("kl", "en-us"), ("p", "1"), ("s", "0"), ("df", ""), ("vqd", vqd.as_str()), ("ex", "")])
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|e| e.to_string())?;
+        let page_data = Self::text_extract_json(&html).ok_or("Could not extract results from DuckDuckGo response")?;
+
+        let google_fallback = format!("http://www.google.com/search?q={}", query);
+        self.query_result = page_data.iter()
+            .filter_map(|row| {
+                let href = row["u"].as_str()?;
+                let body = Self::normalize(row["a"].as_str().unwrap_or_default());
+                if href == google_fallback || body.is_empty() {
+                    return None;
+                }
+                Some(SearchResult {
+                    title: Self::normalize(row["t"].as_str().unwrap_or_default()),
+                    href: Self::normalize_url(href),
+                    body,
+                })
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn results(&self) -> &[SearchResult] {
+        &self.query_result
+    }
+}
+
+/// Queries a self-hosted SearXNG instance through its JSON API.
+/// The instance must have `json` enabled under `search.formats`.
+pub struct SearxngSearch {
+    client: reqwest::blocking::Client,
+    api_endpoint: String,
+    query_result: Vec<SearchResult>,
+}
+
+impl SearxngSearch {
+    pub fn new() -> Self {
+        let config = Config::new().unwrap();
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api_endpoint: config.get_searxng_api_endpoint().to_string(),
+            query_result: vec![],
+        }
+    }
+}
+
+impl Default for SearxngSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchEngine for SearxngSearch {
+    fn search(&mut self, query: &str) -> Result<(), String> {
+        let response: Value = self.client.get(&self.api_endpoint)
+            .query(&[("q", query), ("format", "json"), ("language", "en-US")])
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| e.to_string())?;
+
+        self.query_result = response["results"].as_array()
+            .map(|items| items.iter().map(|item| SearchResult {
+                title: item["title"].as_str().unwrap_or_default().to_string(),
+                href: item["url"].as_str().unwrap_or_default().to_string(),
+                body: item["content"].as_str().unwrap_or_default().to_string(),
+            }).collect())
+            .unwrap_or_default();
+        Ok(())
+    }
+
+    fn results(&self) -> &[SearchResult] {
+        &self.query_result
+    }
+}
+
+/// Offline backend answering queries from canned JSON files in `SEARCH_FIXTURES_DIR`.
+///
+/// A query is looked up as `<slug>.json`, where the slug is the lowercased query with
+/// every non-alphanumeric run replaced by `-`, falling back to `default.json`. Each file
+/// holds an array of `{"title", "href", "body"}` objects.
+pub struct FixtureSearch {
+    fixtures_dir: PathBuf,
+    query_result: Vec<SearchResult>,
+}
+
+impl FixtureSearch {
+    pub fn new() -> Self {
+        let config = Config::new().unwrap();
+        Self::with_dir(config.get_search_fixtures_dir())
+    }
+
+    pub fn with_dir(fixtures_dir: &str) -> Self {
+        Self { fixtures_dir: PathBuf::from(fixtures_dir), query_result: vec![] }
+    }
+
+    pub fn fixture_name(query: &str) -> String {
+        let slug = query.trim().to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        format!("{}.json", slug)
+    }
+}
+
+impl Default for FixtureSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchEngine for FixtureSearch {
+    fn search(&mut self, query: &str) -> Result<(), String> {
+        let path = [Self::fixture_name(query), "default.json".to_string()].into_iter()
+            .map(|name| self.fixtures_dir.join(name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| format!("No search fixture for query: {}", query))?;
+
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        self.query_result = serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid search fixture {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    fn results(&self) -> &[SearchResult] {
+        &self.query_result
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use std::sync::Mutex;
@@ -11,6 +12,24 @@ struct ConfigData {
     STORAGE: Storage,
     LOGGING: Logging,
     TIMEOUT: Timeout,
+    #[serde(default)]
+    RUNNER: Runner,
+    #[serde(default)]
+    EXECUTION: Execution,
+    #[serde(default)]
+    READ_CODE: ReadCode,
+    #[serde(default)]
+    GIT: Git,
+    #[serde(default)]
+    DEPLOY: Deploy,
+    #[serde(default)]
+    KNOWLEDGE: Knowledge,
+    #[serde(default)]
+    RAG: Rag,
+    #[serde(default)]
+    KEYWORDS: Keywords,
+    #[serde(default)]
+    ASK_USER: AskUser,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -19,6 +38,14 @@ struct ApiEndpoints {
     GOOGLE: String,
     OLLAMA: String,
     OPENAI: String,
+    #[serde(default = "default_searxng_endpoint")]
+    SEARXNG: String,
+    #[serde(default = "default_github_endpoint")]
+    GITHUB: String,
+    #[serde(default)]
+    GITEA: String,
+    #[serde(default = "default_netlify_endpoint")]
+    NETLIFY: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -32,6 +59,10 @@ struct ApiKeys {
     MISTRAL: String,
     GROQ: String,
     NETLIFY: String,
+    #[serde(default)]
+    GITHUB: String,
+    #[serde(default)]
+    GITEA: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -42,6 +73,12 @@ struct Storage {
     PROJECTS_DIR: String,
     LOGS_DIR: String,
     REPOS_DIR: String,
+    #[serde(default = "default_search_fixtures_dir")]
+    SEARCH_FIXTURES_DIR: String,
+    #[serde(default = "default_agent_state_db")]
+    AGENT_STATE_DB: String,
+    #[serde(default = "default_terminals_dir")]
+    TERMINALS_DIR: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -55,6 +92,322 @@ struct Timeout {
     INFERENCE: u64,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Runner {
+    #[serde(default = "default_runner_max_retries")]
+    MAX_RETRIES: u32,
+    /// How long one command the Runner agent starts may take before it is killed.
+    #[serde(default = "default_runner_timeout_secs")]
+    TIMEOUT_SECS: u64,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self { MAX_RETRIES: default_runner_max_retries(), TIMEOUT_SECS: default_runner_timeout_secs() }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Execution {
+    #[serde(default = "default_execution_timeout")]
+    TIMEOUT: u64,
+    #[serde(default = "default_execution_cpu_time")]
+    CPU_TIME: u64,
+    #[serde(default = "default_execution_memory_mb")]
+    MEMORY_MB: u64,
+    #[serde(default)]
+    ALLOW_NETWORK: bool,
+    #[serde(default = "default_execution_sandbox")]
+    SANDBOX: String,
+    #[serde(default)]
+    PROJECTS: BTreeMap<String, ProjectExecution>,
+}
+
+impl Default for Execution {
+    fn default() -> Self {
+        Self {
+            TIMEOUT: default_execution_timeout(),
+            CPU_TIME: default_execution_cpu_time(),
+            MEMORY_MB: default_execution_memory_mb(),
+            ALLOW_NETWORK: false,
+            SANDBOX: default_execution_sandbox(),
+            PROJECTS: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct ReadCode {
+    #[serde(default = "default_read_code_max_tokens")]
+    MAX_TOKENS: usize,
+    #[serde(default = "default_read_code_max_file_kb")]
+    MAX_FILE_KB: u64,
+}
+
+impl Default for ReadCode {
+    fn default() -> Self {
+        Self { MAX_TOKENS: default_read_code_max_tokens(), MAX_FILE_KB: default_read_code_max_file_kb() }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Git {
+    #[serde(default = "default_git_author_name")]
+    AUTHOR_NAME: String,
+    #[serde(default = "default_git_author_email")]
+    AUTHOR_EMAIL: String,
+    #[serde(default = "default_git_auto_commit")]
+    AUTO_COMMIT: bool,
+    #[serde(default)]
+    BRANCH_PER_TASK: bool,
+}
+
+impl Default for Git {
+    fn default() -> Self {
+        Self {
+            AUTHOR_NAME: default_git_author_name(),
+            AUTHOR_EMAIL: default_git_author_email(),
+            AUTO_COMMIT: default_git_auto_commit(),
+            BRANCH_PER_TASK: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Deploy {
+    #[serde(default = "default_deploy_target")]
+    TARGET: String,
+    /// Netlify site of each project, keyed by the project's directory name, so a
+    /// redeploy updates the same site.
+    #[serde(default)]
+    NETLIFY_SITES: BTreeMap<String, String>,
+}
+
+impl Default for Deploy {
+    fn default() -> Self {
+        Self { TARGET: default_deploy_target(), NETLIFY_SITES: BTreeMap::new() }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Knowledge {
+    #[serde(default = "default_knowledge_enabled")]
+    ENABLED: bool,
+    /// Stored research older than this is looked up on the web again.
+    #[serde(default = "default_knowledge_freshness_hours")]
+    FRESHNESS_HOURS: u64,
+}
+
+impl Default for Knowledge {
+    fn default() -> Self {
+        Self { ENABLED: default_knowledge_enabled(), FRESHNESS_HOURS: default_knowledge_freshness_hours() }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Rag {
+    #[serde(default = "default_rag_enabled")]
+    ENABLED: bool,
+    /// `ollama`, `openai` (any OpenAI-compatible endpoint) or `hashing`.
+    #[serde(default = "default_rag_embedder")]
+    EMBEDDER: String,
+    #[serde(default = "default_rag_model")]
+    MODEL: String,
+    /// Overrides the Ollama or OpenAI endpoint for embeddings only.
+    #[serde(default)]
+    ENDPOINT: String,
+    #[serde(default = "default_rag_top_k")]
+    TOP_K: usize,
+    #[serde(default = "default_rag_chunk_lines")]
+    CHUNK_LINES: usize,
+}
+
+impl Default for Rag {
+    fn default() -> Self {
+        Self {
+            ENABLED: default_rag_enabled(),
+            EMBEDDER: default_rag_embedder(),
+            MODEL: default_rag_model(),
+            ENDPOINT: String::new(),
+            TOP_K: default_rag_top_k(),
+            CHUNK_LINES: default_rag_chunk_lines(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Keywords {
+    #[serde(default = "default_keywords_top_n")]
+    TOP_N: usize,
+    /// 0 ranks by relevance alone; towards 1 favours keywords unlike those already picked.
+    #[serde(default = "default_keywords_diversity")]
+    DIVERSITY: f32,
+    /// Also rank by embedding similarity when `[RAG] EMBEDDER` is a model.
+    #[serde(default = "default_keywords_embeddings")]
+    EMBEDDINGS: bool,
+}
+
+impl Default for Keywords {
+    fn default() -> Self {
+        Self {
+            TOP_N: default_keywords_top_n(),
+            DIVERSITY: default_keywords_diversity(),
+            EMBEDDINGS: default_keywords_embeddings(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct AskUser {
+    /// How long the agent waits for the user to answer one of its questions.
+    #[serde(default = "default_ask_user_timeout_secs")]
+    TIMEOUT_SECS: u64,
+    /// `proceed` without an answer, or `abort` the run.
+    #[serde(default = "default_ask_user_on_timeout")]
+    ON_TIMEOUT: String,
+}
+
+impl Default for AskUser {
+    fn default() -> Self {
+        Self { TIMEOUT_SECS: default_ask_user_timeout_secs(), ON_TIMEOUT: default_ask_user_on_timeout() }
+    }
+}
+
+/// Per-project overrides of `[EXECUTION]`, keyed by the project's directory name.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+struct ProjectExecution {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    TIMEOUT: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    CPU_TIME: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    MEMORY_MB: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ALLOW_NETWORK: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    SANDBOX: Option<String>,
+}
+
+fn default_searxng_endpoint() -> String {
+    "http://127.0.0.1:8888/search".to_string()
+}
+
+fn default_github_endpoint() -> String {
+    "https://api.github.com".to_string()
+}
+
+fn default_netlify_endpoint() -> String {
+    "https://api.netlify.com/api/v1".to_string()
+}
+
+fn default_search_fixtures_dir() -> String {
+    "data/search_fixtures".to_string()
+}
+
+fn default_agent_state_db() -> String {
+    "data/db/agent_state.json".to_string()
+}
+
+fn default_terminals_dir() -> String {
+    "data/terminals".to_string()
+}
+
+fn default_runner_max_retries() -> u32 {
+    2
+}
+
+fn default_runner_timeout_secs() -> u64 {
+    600
+}
+
+fn default_execution_timeout() -> u64 {
+    30
+}
+
+fn default_execution_cpu_time() -> u64 {
+    20
+}
+
+fn default_execution_memory_mb() -> u64 {
+    512
+}
+
+fn default_execution_sandbox() -> String {
+    "auto".to_string()
+}
+
+fn default_read_code_max_tokens() -> usize {
+    24000
+}
+
+fn default_read_code_max_file_kb() -> u64 {
+    256
+}
+
+fn default_git_author_name() -> String {
+    "Devika".to_string()
+}
+
+fn default_git_author_email() -> String {
+    "devika@localhost".to_string()
+}
+
+fn default_git_auto_commit() -> bool {
+    true
+}
+
+fn default_deploy_target() -> String {
+    "local".to_string()
+}
+
+fn default_knowledge_enabled() -> bool {
+    true
+}
+
+fn default_knowledge_freshness_hours() -> u64 {
+    168
+}
+
+fn default_rag_enabled() -> bool {
+    true
+}
+
+fn default_rag_embedder() -> String {
+    "hashing".to_string()
+}
+
+fn default_rag_model() -> String {
+    "nomic-embed-text".to_string()
+}
+
+fn default_rag_top_k() -> usize {
+    8
+}
+
+fn default_rag_chunk_lines() -> usize {
+    60
+}
+
+fn default_keywords_top_n() -> usize {
+    5
+}
+
+fn default_keywords_diversity() -> f32 {
+    0.7
+}
+
+fn default_keywords_embeddings() -> bool {
+    true
+}
+
+fn default_ask_user_timeout_secs() -> u64 {
+    600
+}
+
+fn default_ask_user_on_timeout() -> String {
+    "proceed".to_string()
+}
+
 static CONFIG: Lazy<Mutex<Config>> = Lazy::new(|| Mutex::new(Config::new().unwrap()));
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -117,6 +470,10 @@ impl Config {
         &self.config.API_ENDPOINTS.OLLAMA
     }
 
+    pub fn get_searxng_api_endpoint(&self) -> &String {
+        &self.config.API_ENDPOINTS.SEARXNG
+    }
+
     pub fn get_claude_api_key(&self) -> &String {
         &self.config.API_KEYS.CLAUDE
     }
@@ -145,10 +502,34 @@ impl Config {
         &self.config.API_KEYS.NETLIFY
     }
 
+    pub fn get_github_api_key(&self) -> &String {
+        &self.config.API_KEYS.GITHUB
+    }
+
+    pub fn get_github_api_endpoint(&self) -> &String {
+        &self.config.API_ENDPOINTS.GITHUB
+    }
+
+    pub fn get_gitea_api_key(&self) -> &String {
+        &self.config.API_KEYS.GITEA
+    }
+
+    pub fn get_gitea_api_endpoint(&self) -> &String {
+        &self.config.API_ENDPOINTS.GITEA
+    }
+
+    pub fn get_netlify_api_endpoint(&self) -> &String {
+        &self.config.API_ENDPOINTS.NETLIFY
+    }
+
     pub fn get_sqlite_db(&self) -> &String {
         &self.config.STORAGE.SQLITE_DB
     }
 
+    pub fn get_agent_state_db(&self) -> &String {
+        &self.config.STORAGE.AGENT_STATE_DB
+    }
+
     pub fn get_screenshots_dir(&self) -> &String {
         &self.config.STORAGE.SCREENSHOTS_DIR
     }
@@ -169,6 +550,14 @@ impl Config {
         &self.config.STORAGE.REPOS_DIR
     }
 
+    pub fn get_search_fixtures_dir(&self) -> &String {
+        &self.config.STORAGE.SEARCH_FIXTURES_DIR
+    }
+
+    pub fn get_terminals_dir(&self) -> &String {
+        &self.config.STORAGE.TERMINALS_DIR
+    }
+
     pub fn get_logging_rest_api(&self) -> bool {
         self.config.LOGGING.LOG_REST_API
     }
@@ -181,6 +570,122 @@ impl Config {
         self.config.TIMEOUT.INFERENCE
     }
 
+    pub fn get_runner_max_retries(&self) -> u32 {
+        self.config.RUNNER.MAX_RETRIES
+    }
+
+    pub fn get_runner_timeout_secs(&self) -> u64 {
+        self.config.RUNNER.TIMEOUT_SECS
+    }
+
+    fn get_execution_project(&self, project: &str) -> Option<&ProjectExecution> {
+        self.config.EXECUTION.PROJECTS.get(&project.to_lowercase().replace(' ', "-"))
+    }
+
+    pub fn get_execution_timeout(&self, project: &str) -> u64 {
+        self.get_execution_project(project).and_then(|p| p.TIMEOUT).unwrap_or(self.config.EXECUTION.TIMEOUT)
+    }
+
+    pub fn get_execution_cpu_time(&self, project: &str) -> u64 {
+        self.get_execution_project(project).and_then(|p| p.CPU_TIME).unwrap_or(self.config.EXECUTION.CPU_TIME)
+    }
+
+    pub fn get_execution_memory_mb(&self, project: &str) -> u64 {
+        self.get_execution_project(project).and_then(|p| p.MEMORY_MB).unwrap_or(self.config.EXECUTION.MEMORY_MB)
+    }
+
+    pub fn get_execution_allow_network(&self, project: &str) -> bool {
+        self.get_execution_project(project).and_then(|p| p.ALLOW_NETWORK).unwrap_or(self.config.EXECUTION.ALLOW_NETWORK)
+    }
+
+    pub fn get_execution_sandbox(&self, project: &str) -> &String {
+        self.get_execution_project(project).and_then(|p| p.SANDBOX.as_ref()).unwrap_or(&self.config.EXECUTION.SANDBOX)
+    }
+
+    pub fn get_read_code_max_tokens(&self) -> usize {
+        self.config.READ_CODE.MAX_TOKENS
+    }
+
+    pub fn get_read_code_max_file_kb(&self) -> u64 {
+        self.config.READ_CODE.MAX_FILE_KB
+    }
+
+    pub fn get_git_author_name(&self) -> &String {
+        &self.config.GIT.AUTHOR_NAME
+    }
+
+    pub fn get_git_author_email(&self) -> &String {
+        &self.config.GIT.AUTHOR_EMAIL
+    }
+
+    pub fn get_git_auto_commit(&self) -> bool {
+        self.config.GIT.AUTO_COMMIT
+    }
+
+    pub fn get_git_branch_per_task(&self) -> bool {
+        self.config.GIT.BRANCH_PER_TASK
+    }
+
+    pub fn get_deploy_target(&self) -> &String {
+        &self.config.DEPLOY.TARGET
+    }
+
+    pub fn get_knowledge_enabled(&self) -> bool {
+        self.config.KNOWLEDGE.ENABLED
+    }
+
+    pub fn get_knowledge_freshness_hours(&self) -> u64 {
+        self.config.KNOWLEDGE.FRESHNESS_HOURS
+    }
+
+    pub fn get_rag_enabled(&self) -> bool {
+        self.config.RAG.ENABLED
+    }
+
+    pub fn get_rag_embedder(&self) -> &String {
+        &self.config.RAG.EMBEDDER
+    }
+
+    pub fn get_rag_model(&self) -> &String {
+        &self.config.RAG.MODEL
+    }
+
+    pub fn get_rag_endpoint(&self) -> &String {
+        &self.config.RAG.ENDPOINT
+    }
+
+    pub fn get_rag_top_k(&self) -> usize {
+        self.config.RAG.TOP_K
+    }
+
+    pub fn get_rag_chunk_lines(&self) -> usize {
+        self.config.RAG.CHUNK_LINES
+    }
+
+    pub fn get_keywords_top_n(&self) -> usize {
+        self.config.KEYWORDS.TOP_N
+    }
+
+    pub fn get_keywords_diversity(&self) -> f32 {
+        self.config.KEYWORDS.DIVERSITY
+    }
+
+    pub fn get_keywords_embeddings(&self) -> bool {
+        self.config.KEYWORDS.EMBEDDINGS
+    }
+
+    pub fn get_ask_user_timeout_secs(&self) -> u64 {
+        self.config.ASK_USER.TIMEOUT_SECS
+    }
+
+    pub fn get_ask_user_on_timeout(&self) -> &String {
+        &self.config.ASK_USER.ON_TIMEOUT
+    }
+
+    pub fn get_deploy_netlify_site(&self, project: &str) -> Option<&String> {
+        self.config.DEPLOY.NETLIFY_SITES.get(&project.to_lowercase().replace(' ', "-"))
+    }
+
     // Define setters for each configuration field
     pub fn set_bing_api_key(&mut self, key: String) {
         self.config.API_KEYS.BING = key;
@@ -212,6 +717,11 @@ impl Config {
         self.save_config().unwrap();
     }
 
+    pub fn set_searxng_api_endpoint(&mut self, endpoint: String) {
+        self.config.API_ENDPOINTS.SEARXNG = endpoint;
+        self.save_config().unwrap();
+    }
+
     pub fn set_claude_api_key(&mut self, key: String) {
         self.config.API_KEYS.CLAUDE = key;
         self.save_config().unwrap();
@@ -247,6 +757,21 @@ impl Config {
         self.save_config().unwrap();
     }
 
+    pub fn set_github_api_key(&mut self, key: String) {
+        self.config.API_KEYS.GITHUB = key;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_gitea_api_key(&mut self, key: String) {
+        self.config.API_KEYS.GITEA = key;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_gitea_api_endpoint(&mut self, endpoint: String) {
+        self.config.API_ENDPOINTS.GITEA = endpoint;
+        self.save_config().unwrap();
+    }
+
     pub fn set_logging_rest_api(&mut self, value: bool) {
         self.config.LOGGING.LOG_REST_API = value;
         self.save_config().unwrap();
@@ -262,6 +787,108 @@ impl Config {
         self.save_config().unwrap();
     }
 
+    pub fn set_runner_max_retries(&mut self, value: u32) {
+        self.config.RUNNER.MAX_RETRIES = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_runner_timeout_secs(&mut self, value: u64) {
+        self.config.RUNNER.TIMEOUT_SECS = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_execution_timeout(&mut self, value: u64) {
+        self.config.EXECUTION.TIMEOUT = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_execution_allow_network(&mut self, value: bool) {
+        self.config.EXECUTION.ALLOW_NETWORK = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_execution_sandbox(&mut self, value: String) {
+        self.config.EXECUTION.SANDBOX = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_read_code_max_tokens(&mut self, value: usize) {
+        self.config.READ_CODE.MAX_TOKENS = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_git_auto_commit(&mut self, value: bool) {
+        self.config.GIT.AUTO_COMMIT = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_git_branch_per_task(&mut self, value: bool) {
+        self.config.GIT.BRANCH_PER_TASK = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_deploy_target(&mut self, value: String) {
+        self.config.DEPLOY.TARGET = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_deploy_netlify_site(&mut self, project: &str, site_id: String) {
+        self.config.DEPLOY.NETLIFY_SITES.insert(project.to_lowercase().replace(' ', "-"), site_id);
+        self.save_config().unwrap();
+    }
+
+    pub fn set_knowledge_enabled(&mut self, value: bool) {
+        self.config.KNOWLEDGE.ENABLED = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_knowledge_freshness_hours(&mut self, value: u64) {
+        self.config.KNOWLEDGE.FRESHNESS_HOURS = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_rag_enabled(&mut self, value: bool) {
+        self.config.RAG.ENABLED = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_rag_embedder(&mut self, embedder: String, model: String) {
+        self.config.RAG.EMBEDDER = embedder;
+        self.config.RAG.MODEL = model;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_keywords_diversity(&mut self, value: f32) {
+        self.config.KEYWORDS.DIVERSITY = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_keywords_embeddings(&mut self, value: bool) {
+        self.config.KEYWORDS.EMBEDDINGS = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_ask_user_timeout_secs(&mut self, value: u64) {
+        self.config.ASK_USER.TIMEOUT_SECS = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_ask_user_on_timeout(&mut self, value: String) {
+        self.config.ASK_USER.ON_TIMEOUT = value;
+        self.save_config().unwrap();
+    }
+
+    pub fn set_execution_project_policy(&mut self, project: &str, sandbox: Option<String>, allow_network: Option<bool>) {
+        let policy = self.config.EXECUTION.PROJECTS.entry(project.to_lowercase().replace(' ', "-")).or_default();
+        if sandbox.is_some() {
+            policy.SANDBOX = sandbox;
+        }
+        if allow_network.is_some() {
+            policy.ALLOW_NETWORK = allow_network;
+        }
+        self.save_config().unwrap();
+    }
+
     pub fn update_config(config: &Config) -> Result<(), std::io::Error> {
         let mut config_guard = CONFIG.lock().unwrap();
         *config_guard = config.to_owned().clone();
@@ -0,0 +1,892 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::filesystem::project_path;
+
+/// Dependencies, build output and virtualenvs say nothing about the project itself.
+const SKIPPED_DIRS: [&str; 10] = [".git", "node_modules", "target", "__pycache__", "venv", ".venv", "dist", "build", ".svelte-kit", ".next"];
+const MAX_FILES: usize = 2000;
+const MAX_FILE_SIZE: u64 = 512 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+}
+
+impl Language {
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Rust => "Rust",
+            Language::Python => "Python",
+            Language::JavaScript => "JavaScript",
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Language::Rust),
+            "py" => Some(Language::Python),
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" | "svelte" | "vue" => Some(Language::JavaScript),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Module {
+    /// Path relative to the project root.
+    pub id: String,
+    pub language: Language,
+    /// Other modules of the project this one imports or declares.
+    pub imports: BTreeSet<String>,
+    /// Top-level names of what it imports from outside the project, standard library included.
+    pub external: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Class {
+    pub name: String,
+    /// `class`, `struct`, `enum`, `trait` or `interface`.
+    pub kind: &'static str,
+    pub module: String,
+    /// Base classes, supertraits, implemented traits and interfaces, by name.
+    pub parents: BTreeSet<String>,
+    pub methods: Vec<String>,
+}
+
+impl Class {
+    fn new(name: &str, kind: &'static str, module: &str) -> Self {
+        Self { name: name.to_string(), kind, module: module.to_string(), parents: BTreeSet::new(), methods: vec![] }
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        matches!(self.kind, "trait" | "interface")
+    }
+}
+
+/// What a static scan of a project's sources finds: its modules with the imports
+/// between them, and the classes, structs, traits and interfaces they define.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Architecture {
+    pub modules: Vec<Module>,
+    pub classes: Vec<Class>,
+}
+
+impl Architecture {
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Identifier-safe, unique names for the classes, for the formats that need them.
+    pub fn class_ids(&self) -> Vec<String> {
+        let mut seen = BTreeSet::new();
+        self.classes.iter()
+            .map(|class| {
+                let base: String = class.name.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+                let mut id = base.clone();
+                let mut suffix = 2;
+                while !seen.insert(id.clone()) {
+                    id = format!("{}_{}", base, suffix);
+                    suffix += 1;
+                }
+                id
+            })
+            .collect()
+    }
+
+    /// `(child, parent)` index pairs for the parents that are classes of the project
+    /// too. A name defined more than once resolves to the definition in the child's
+    /// own module, then in one it imports, then in a file of the same language.
+    pub fn inheritance(&self) -> Vec<(usize, usize)> {
+        let extension = |id: &str| id.rsplit('.').next().unwrap_or_default().to_string();
+        let mut edges = vec![];
+        for (child, class) in self.classes.iter().enumerate() {
+            let imports = self.modules.iter().find(|module| module.id == class.module).map(|module| &module.imports);
+            for parent in &class.parents {
+                let best = self.classes.iter().enumerate()
+                    .filter(|(index, other)| *index != child && &other.name == parent)
+                    .min_by_key(|(_, other)| {
+                        if other.module == class.module {
+                            0
+                        } else if imports.is_some_and(|imports| imports.contains(&other.module)) {
+                            1
+                        } else if extension(&other.module) == extension(&class.module) {
+                            2
+                        } else {
+                            3
+                        }
+                    });
+                if let Some((parent, _)) = best {
+                    edges.push((child, parent));
+                }
+            }
+        }
+        edges
+    }
+}
+
+struct SourceFile {
+    id: String,
+    relative_path: PathBuf,
+    language: Language,
+    text: String,
+}
+
+pub fn scan_project(project_name: &str) -> Result<Architecture, String> {
+    let project_path = project_path(project_name)?;
+    if !project_path.is_dir() {
+        return Err(format!("Project {} has no files yet", project_name));
+    }
+    Ok(scan_directory(&project_path))
+}
+
+pub fn scan_directory(root: &Path) -> Architecture {
+    let files = collect_sources(root);
+    let index = ModuleIndex::new(&files);
+
+    let mut architecture = Architecture::default();
+    let mut impls = vec![];
+    for file in &files {
+        let mut module = Module {
+            id: file.id.clone(),
+            language: file.language,
+            imports: BTreeSet::new(),
+            external: BTreeSet::new(),
+        };
+        match file.language {
+            Language::Rust => {
+                rust_imports(file, &index, &mut module);
+                let (classes, found_impls) = rust_classes(file);
+                architecture.classes.extend(classes);
+                impls.extend(found_impls);
+            }
+            Language::Python => {
+                python_imports(file, &index, &mut module);
+                architecture.classes.extend(python_classes(file));
+            }
+            Language::JavaScript => {
+                javascript_imports(file, &index, &mut module);
+                architecture.classes.extend(javascript_classes(file));
+            }
+        }
+        module.imports.remove(&module.id);
+        architecture.modules.push(module);
+    }
+
+    // `impl` blocks can live anywhere in the crate; prefer the type's own module.
+    for found in impls {
+        let classes = &mut architecture.classes;
+        let position = classes.iter().position(|class| class.name == found.type_name && class.module == found.module)
+            .or_else(|| classes.iter().position(|class| class.name == found.type_name && class.kind != "trait"));
+        if let Some(position) = position {
+            let class = &mut classes[position];
+            class.parents.extend(found.trait_name);
+            class.methods.extend(found.methods);
+        }
+    }
+    architecture
+}
+
+fn collect_sources(root: &Path) -> Vec<SourceFile> {
+    let mut files = vec![];
+    let mut pending = vec![root.to_path_buf()];
+    'walk: while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else { continue };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_dir() {
+                if !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                    pending.push(path);
+                }
+                continue;
+            }
+            // Symlinks are skipped so the scan cannot wander outside the project.
+            if !file_type.is_file() || entry.metadata().map_or(true, |metadata| metadata.len() > MAX_FILE_SIZE) {
+                continue;
+            }
+            let Some(language) = Language::from_path(&path) else { continue };
+            let Ok(text) = fs::read_to_string(&path) else { continue };
+            let Ok(relative_path) = path.strip_prefix(root).map(Path::to_path_buf) else { continue };
+            let id = relative_path.to_string_lossy().replace('\\', "/");
+            files.push(SourceFile { id, relative_path, language, text });
+            if files.len() >= MAX_FILES {
+                break 'walk;
+            }
+        }
+    }
+    files.sort_by(|a, b| a.id.cmp(&b.id));
+    files
+}
+
+/// Lookup tables from the names imports use to module ids.
+struct ModuleIndex {
+    /// `(crate root, module path)` of every Rust file.
+    rust: HashMap<(PathBuf, Vec<String>), String>,
+    /// Dotted names of Python modules, both from the project root and from their
+    /// top-level package, since either can be on `sys.path`.
+    python: HashMap<String, String>,
+    /// JavaScript and TypeScript files; their ids are their paths.
+    javascript: HashSet<String>,
+}
+
+impl ModuleIndex {
+    fn new(files: &[SourceFile]) -> Self {
+        let mut index = Self { rust: HashMap::new(), python: HashMap::new(), javascript: HashSet::new() };
+        let known: BTreeSet<PathBuf> = files.iter().map(|file| file.relative_path.clone()).collect();
+        for file in files {
+            match file.language {
+                Language::Rust => {
+                    index.rust.entry(rust_module_path(&file.relative_path)).or_insert_with(|| file.id.clone());
+                }
+                Language::Python => {
+                    let (full, package_relative) = python_module_names(&file.relative_path, &known);
+                    index.python.insert(full, file.id.clone());
+                    index.python.entry(package_relative).or_insert_with(|| file.id.clone());
+                }
+                Language::JavaScript => {
+                    index.javascript.insert(file.id.clone());
+                }
+            }
+        }
+        index
+    }
+}
+
+/// Drops comments and the contents of string literals, so braces can be counted.
+/// `quotes` are the string delimiters of the language.
+fn strip_code(line: &str, in_comment: &mut bool, quotes: &[char]) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut code = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if *in_comment {
+            if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                *in_comment = false;
+                i += 2;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+        match chars[i] {
+            '/' if chars.get(i + 1) == Some(&'/') => break,
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                *in_comment = true;
+                i += 2;
+            }
+            // Rust char literals like '{'; lifetimes have no closing quote.
+            '\'' if !quotes.contains(&'\'') => {
+                if chars.get(i + 2) == Some(&'\'') {
+                    i += 3;
+                } else if chars.get(i + 1) == Some(&'\\') {
+                    i += chars[i + 2..].iter().position(|c| *c == '\'').map_or(1, |end| end + 3);
+                } else {
+                    code.push('\'');
+                    i += 1;
+                }
+            }
+            quote if quotes.contains(&quote) => {
+                code.push(quote);
+                i += 1;
+                while i < chars.len() && chars[i] != quote {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                code.push(quote);
+                i += 1;
+            }
+            c => {
+                code.push(c);
+                i += 1;
+            }
+        }
+    }
+    code
+}
+
+fn brace_delta(code: &str) -> i64 {
+    code.chars().map(|c| match c {
+        '{' => 1,
+        '}' => -1,
+        _ => 0,
+    }).sum()
+}
+
+/// The name at the start of `text`, without generics, bounds or punctuation.
+fn leading_identifier(text: &str) -> &str {
+    let text = text.trim_start();
+    let end = text.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Removes a leading `<...>` group, nested ones included.
+fn skip_generics(text: &str) -> &str {
+    let text = text.trim_start();
+    if !text.starts_with('<') {
+        return text;
+    }
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &text[index + 1..];
+                }
+            }
+            _ => {}
+        }
+    }
+    ""
+}
+
+/// The bare name of a type as written in an `impl` header: no path, references,
+/// `dyn` or generic arguments.
+fn type_name(text: &str) -> String {
+    let text = text.trim().trim_start_matches('&').trim_start_matches("mut ").trim_start_matches("dyn ").trim();
+    let without_generics = text.split('<').next().unwrap_or(text);
+    leading_identifier(without_generics.rsplit("::").next().unwrap_or(without_generics)).to_string()
+}
+
+/// A file's crate root (the directory holding `src`) and its module path in the crate.
+fn rust_module_path(relative_path: &Path) -> (PathBuf, Vec<String>) {
+    let components: Vec<String> = relative_path.iter().map(|part| part.to_string_lossy().into_owned()).collect();
+    let split = components.iter().position(|part| part == "src").map_or(components.len() - 1, |src| src + 1);
+    let root: PathBuf = components[..split].iter().collect();
+    let mut path: Vec<String> = components[split..].to_vec();
+
+    let file = path.pop().unwrap_or_default();
+    let stem = file.trim_end_matches(".rs");
+    let crate_root = path.is_empty() && matches!(stem, "main" | "lib");
+    if stem != "mod" && !crate_root {
+        path.push(stem.to_string());
+    }
+    (root, path)
+}
+
+/// Expands a `use` tree like `crate::a::{b, c::{d, e as f}}` into its paths.
+fn expand_use_tree(tree: &str) -> Vec<String> {
+    let mut cleaned = String::new();
+    let mut tokens = tree.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == "as" {
+            if let Some(alias) = tokens.next() {
+                cleaned.extend(alias.chars().skip_while(|c| c.is_alphanumeric() || *c == '_'));
+            }
+        } else {
+            cleaned.push_str(token);
+        }
+    }
+    expand_cleaned_tree(&cleaned)
+}
+
+fn expand_cleaned_tree(tree: &str) -> Vec<String> {
+    let (Some(open), Some(close)) = (tree.find('{'), tree.rfind('}')) else {
+        return vec![tree.trim_end_matches("::*").to_string()];
+    };
+    let prefix = tree[..open].trim_end_matches("::");
+    let mut paths = vec![];
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (index, c) in tree[..close].char_indices().skip(open + 1) {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                paths.extend(expand_cleaned_tree(&tree[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    paths.extend(expand_cleaned_tree(&tree[start..close]));
+    paths.into_iter()
+        .filter(|path| !path.is_empty())
+        .map(|path| if path == "self" { prefix.to_string() } else { format!("{}::{}", prefix, path) })
+        .collect()
+}
+
+fn rust_imports(file: &SourceFile, index: &ModuleIndex, module: &mut Module) {
+    let (root, own_path) = rust_module_path(&file.relative_path);
+
+    let mut in_comment = false;
+    let mut statement: Option<String> = None;
+    for line in file.text.lines() {
+        let code = strip_code(line, &mut in_comment, &['"']);
+        let trimmed = code.trim();
+
+        let declaration = trimmed.trim_start_matches("pub ").trim_start_matches("pub(crate) ");
+        if let Some(name) = declaration.strip_prefix("mod ").filter(|rest| rest.trim_end().ends_with(';')) {
+            let mut path = own_path.clone();
+            path.push(leading_identifier(name).to_string());
+            if let Some(id) = index.rust.get(&(root.clone(), path)) {
+                module.imports.insert(id.clone());
+            }
+            continue;
+        }
+
+        if statement.is_none() && (declaration.starts_with("use ") || declaration.starts_with("pub(super) use ")) {
+            statement = Some(String::new());
+        }
+        let Some(buffer) = statement.as_mut() else { continue };
+        buffer.push_str(trimmed);
+        buffer.push(' ');
+        if !trimmed.contains(';') {
+            continue;
+        }
+
+        let tree = buffer.split(';').next().unwrap_or_default();
+        let tree = tree.split_once("use ").map_or("", |(_, tree)| tree);
+        for path in expand_use_tree(tree) {
+            let segments: Vec<&str> = path.split("::").filter(|segment| !segment.is_empty()).collect();
+            let (base, rest) = match segments.first() {
+                Some(&"crate") => (vec![], &segments[1..]),
+                Some(&"self") => (own_path.clone(), &segments[1..]),
+                Some(&"super") => {
+                    let mut base = own_path.clone();
+                    let mut rest = &segments[..];
+                    while rest.first() == Some(&"super") {
+                        base.pop();
+                        rest = &rest[1..];
+                    }
+                    (base, rest)
+                }
+                Some(name) => {
+                    module.external.insert(name.to_string());
+                    continue;
+                }
+                None => continue,
+            };
+            // The longest prefix that is a module; the rest are items inside it.
+            for length in (0..=rest.len()).rev() {
+                let mut candidate = base.clone();
+                candidate.extend(rest[..length].iter().map(|segment| segment.to_string()));
+                if let Some(id) = index.rust.get(&(root.clone(), candidate)) {
+                    module.imports.insert(id.clone());
+                    break;
+                }
+            }
+        }
+        statement = None;
+    }
+}
+
+struct ImplBlock {
+    module: String,
+    type_name: String,
+    trait_name: Option<String>,
+    methods: Vec<String>,
+}
+
+/// Structs, enums and traits, plus the `impl` blocks to attach afterwards.
+fn rust_classes(file: &SourceFile) -> (Vec<Class>, Vec<ImplBlock>) {
+    let mut classes: Vec<Class> = vec![];
+    let mut impls: Vec<ImplBlock> = vec![];
+    // The open `impl` or `trait` whose methods are being collected, and its depth.
+    let mut open: Option<(bool, usize, i64)> = None;
+    let mut depth = 0i64;
+    let mut in_comment = false;
+
+    for line in file.text.lines() {
+        let code = strip_code(line, &mut in_comment, &['"']);
+        let mut declaration = code.trim();
+        loop {
+            let stripped = if declaration.starts_with("pub(") {
+                declaration.split_once(')').map_or(declaration, |(_, rest)| rest.trim_start())
+            } else {
+                declaration.trim_start_matches("pub ").trim_start_matches("unsafe ").trim_start_matches("async ").trim_start_matches("const ")
+            };
+            if stripped == declaration {
+                break;
+            }
+            declaration = stripped;
+        }
+        let start_depth = depth;
+        depth += brace_delta(&code);
+
+        if let Some((is_impl, index, open_depth)) = open {
+            if start_depth <= open_depth {
+                open = None;
+            } else if start_depth == open_depth + 1 {
+                if let Some(rest) = declaration.strip_prefix("fn ") {
+                    let name = leading_identifier(rest).to_string();
+                    if is_impl {
+                        impls[index].methods.push(name);
+                    } else {
+                        classes[index].methods.push(name);
+                    }
+                }
+                continue;
+            } else {
+                continue;
+            }
+        }
+
+        let keyword = leading_identifier(declaration);
+        let rest = declaration[keyword.len()..].trim_start();
+        match keyword {
+            "struct" | "enum" | "union" | "trait" => {
+                let name = leading_identifier(rest);
+                if name.is_empty() {
+                    continue;
+                }
+                let kind = match keyword {
+                    "trait" => "trait",
+                    "enum" => "enum",
+                    _ => "struct",
+                };
+                let mut class = Class::new(name, kind, &file.id);
+                if kind == "trait" {
+                    let bounds = skip_generics(&rest[name.len()..]);
+                    if let Some(bounds) = bounds.trim_start().strip_prefix(':') {
+                        let bounds = bounds.split(['{', ';']).next().unwrap_or_default().split(" where ").next().unwrap_or_default();
+                        class.parents.extend(bounds.split('+').map(type_name).filter(|name| !name.is_empty() && !matches!(name.as_str(), "Send" | "Sync" | "Sized")));
+                    }
+                    if code.contains('{') && depth > start_depth {
+                        open = Some((false, classes.len(), start_depth));
+                    }
+                }
+                classes.push(class);
+            }
+            "impl" => {
+                let header = skip_generics(rest);
+                let header = header.split('{').next().unwrap_or_default().split(" where ").next().unwrap_or_default();
+                let (trait_name, implemented) = match header.split_once(" for ") {
+                    Some((trait_name, implemented)) => (Some(type_name(trait_name.trim_start_matches('!'))), implemented),
+                    None => (None, header),
+                };
+                impls.push(ImplBlock {
+                    module: file.id.clone(),
+                    type_name: type_name(implemented),
+                    trait_name: trait_name.filter(|name| !name.is_empty()),
+                    methods: vec![],
+                });
+                if depth > start_depth {
+                    open = Some((true, impls.len() - 1, start_depth));
+                }
+            }
+            _ => {}
+        }
+    }
+    (classes, impls)
+}
+
+/// The module's dotted name from the project root, and from the first directory
+/// above it that is not a package.
+fn python_module_names(relative_path: &Path, known: &BTreeSet<PathBuf>) -> (String, String) {
+    let mut parts: Vec<String> = relative_path.with_extension("").iter().map(|part| part.to_string_lossy().into_owned()).collect();
+    if parts.last().is_some_and(|last| last == "__init__") {
+        parts.pop();
+    }
+
+    let mut package_start = parts.len().saturating_sub(1);
+    let mut directory = relative_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    if relative_path.file_stem().is_some_and(|stem| stem == "__init__") {
+        // The package is the directory itself; its parent decides where it starts.
+        directory = directory.parent().map(Path::to_path_buf).unwrap_or_default();
+    }
+    while !directory.as_os_str().is_empty() && known.contains(&directory.join("__init__.py")) {
+        package_start = package_start.saturating_sub(1);
+        directory = directory.parent().map(Path::to_path_buf).unwrap_or_default();
+    }
+    (parts.join("."), parts[package_start.min(parts.len())..].join("."))
+}
+
+/// Joins Python lines continued by open brackets or a backslash.
+fn python_logical_lines(text: &str) -> Vec<(usize, String)> {
+    let mut lines = vec![];
+    let mut current: Option<(usize, String)> = None;
+    let mut depth = 0i64;
+    for line in text.lines() {
+        let code = line.split('#').next().unwrap_or_default();
+        let (_, buffer) = current.get_or_insert_with(|| (line.len() - line.trim_start().len(), String::new()));
+        buffer.push_str(code.trim().trim_end_matches('\\'));
+        buffer.push(' ');
+        depth += code.chars().map(|c| match c {
+            '(' | '[' | '{' => 1,
+            ')' | ']' | '}' => -1,
+            _ => 0,
+        }).sum::<i64>();
+        if depth <= 0 && !code.trim_end().ends_with('\\') {
+            depth = 0;
+            lines.extend(current.take());
+        }
+    }
+    lines.extend(current);
+    lines
+}
+
+fn python_imports(file: &SourceFile, index: &ModuleIndex, module: &mut Module) {
+    // The package the file is in; for `__init__.py` that is its own directory.
+    let mut package: Vec<String> = file.relative_path.with_extension("").iter().map(|part| part.to_string_lossy().into_owned()).collect();
+    package.pop();
+
+    let resolve = |name: &str| -> Option<String> {
+        let mut parts: Vec<&str> = name.split('.').collect();
+        while !parts.is_empty() {
+            if let Some(id) = index.python.get(&parts.join(".")) {
+                return Some(id.clone());
+            }
+            parts.pop();
+        }
+        None
+    };
+
+    for (_, line) in python_logical_lines(&file.text) {
+        let line = line.trim();
+        if let Some(names) = line.strip_prefix("import ") {
+            for name in names.split(',') {
+                let name = name.split(" as ").next().unwrap_or_default().trim();
+                match resolve(name) {
+                    Some(id) => {
+                        module.imports.insert(id);
+                    }
+                    None if !name.is_empty() => {
+                        module.external.insert(name.split('.').next().unwrap_or(name).to_string());
+                    }
+                    None => {}
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("from ") {
+            let Some((source, names)) = rest.split_once(" import ") else { continue };
+            let source = source.trim();
+            let level = source.chars().take_while(|c| *c == '.').count();
+            let absolute = if level > 0 {
+                let mut package = package.clone();
+                for _ in 1..level {
+                    package.pop();
+                }
+                let relative = &source[level..];
+                if !relative.is_empty() {
+                    package.push(relative.to_string());
+                }
+                package.join(".")
+            } else {
+                source.to_string()
+            };
+
+            let names = names.trim().trim_start_matches('(').trim_end_matches(')');
+            let mut found = false;
+            for name in names.split(',') {
+                let name = name.split(" as ").next().unwrap_or_default().trim();
+                if name.is_empty() || name == "*" {
+                    continue;
+                }
+                // `from package import submodule` imports the submodule itself.
+                let submodule = if absolute.is_empty() { name.to_string() } else { format!("{}.{}", absolute, name) };
+                if let Some(id) = index.python.get(&submodule) {
+                    module.imports.insert(id.clone());
+                    found = true;
+                }
+            }
+            if !found {
+                match resolve(&absolute) {
+                    Some(id) => {
+                        module.imports.insert(id);
+                    }
+                    None if level == 0 => {
+                        module.external.insert(absolute.split('.').next().unwrap_or_default().to_string());
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+fn python_classes(file: &SourceFile) -> Vec<Class> {
+    let mut classes: Vec<Class> = vec![];
+    // The class being read, its indentation and the indentation of its body.
+    let mut open: Option<(usize, usize, Option<usize>)> = None;
+
+    for (indent, line) in python_logical_lines(&file.text) {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some((index, class_indent, body_indent)) = open.as_mut() {
+            if indent > *class_indent {
+                // Only direct members; nested functions are indented further.
+                if indent == *body_indent.get_or_insert(indent) {
+                    let definition = line.strip_prefix("async ").unwrap_or(line);
+                    if let Some(rest) = definition.strip_prefix("def ") {
+                        classes[*index].methods.push(leading_identifier(rest).to_string());
+                    }
+                }
+                continue;
+            }
+            open = None;
+        }
+
+        let Some(rest) = line.strip_prefix("class ") else { continue };
+        let name = leading_identifier(rest);
+        if name.is_empty() {
+            continue;
+        }
+        let mut class = Class::new(name, "class", &file.id);
+        if let Some(bases) = rest[name.len()..].trim_start().strip_prefix('(') {
+            let bases = bases.rsplit_once(')').map_or(bases, |(bases, _)| bases);
+            for base in bases.split(',') {
+                let base = base.trim();
+                if base.is_empty() || base.contains('=') {
+                    continue;
+                }
+                let base = base.split('[').next().unwrap_or(base);
+                let base = leading_identifier(base.rsplit('.').next().unwrap_or(base));
+                if !base.is_empty() && base != "object" {
+                    class.parents.insert(base.to_string());
+                }
+            }
+        }
+        open = Some((classes.len(), indent, None));
+        classes.push(class);
+    }
+    classes
+}
+
+/// Normalizes `a/./b/../c` without touching the filesystem.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(part) => normalized.push(part),
+            _ => return None,
+        }
+    }
+    Some(normalized)
+}
+
+/// Every string literal that follows one of the import forms on the line.
+fn javascript_specifiers(line: &str) -> Vec<&str> {
+    let mut specifiers = vec![];
+    for pattern in ["from", "import", "require(", "import("] {
+        let mut search = line;
+        while let Some(position) = search.find(pattern) {
+            let after = search[position + pattern.len()..].trim_start();
+            let before_ok = position == 0 || !search[..position].ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '.');
+            if let Some(quote) = after.chars().next().filter(|c| before_ok && matches!(c, '\'' | '"' | '`')) {
+                if let Some(end) = after[1..].find(quote) {
+                    specifiers.push(&after[1..1 + end]);
+                }
+            }
+            search = &search[position + pattern.len()..];
+        }
+    }
+    specifiers
+}
+
+fn javascript_imports(file: &SourceFile, index: &ModuleIndex, module: &mut Module) {
+    const EXTENSIONS: [&str; 10] = ["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "svelte", "vue"];
+    let directory = file.relative_path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let mut in_comment = false;
+    for line in file.text.lines() {
+        // Only to skip commented out imports; the specifiers come from the raw line.
+        let code = strip_code(line, &mut in_comment, &['"', '\'', '`']);
+        if code.trim().is_empty() {
+            continue;
+        }
+        for specifier in javascript_specifiers(line) {
+            if !specifier.starts_with('.') {
+                let package = if specifier.starts_with('@') {
+                    specifier.splitn(3, '/').take(2).collect::<Vec<_>>().join("/")
+                } else {
+                    specifier.split('/').next().unwrap_or(specifier).to_string()
+                };
+                if !package.is_empty() {
+                    module.external.insert(package);
+                }
+                continue;
+            }
+
+            let Some(target) = normalize(&directory.join(specifier)) else { continue };
+            let target = target.to_string_lossy().replace('\\', "/");
+            let candidates = std::iter::once(target.clone())
+                .chain(EXTENSIONS.iter().map(|extension| format!("{}.{}", target, extension)))
+                .chain(EXTENSIONS.iter().map(|extension| format!("{}/index.{}", target, extension)));
+            // `./util.js` in TypeScript sources usually means `./util.ts`.
+            let stem = target.rsplit_once('.').map(|(stem, _)| stem.to_string());
+            let candidates = candidates.chain(stem.into_iter().flat_map(|stem| EXTENSIONS.iter().map(move |extension| format!("{}.{}", stem, extension))));
+            if let Some(id) = candidates.filter_map(|candidate| index.javascript.get(&candidate)).next() {
+                module.imports.insert(id.clone());
+            }
+        }
+    }
+}
+
+fn javascript_classes(file: &SourceFile) -> Vec<Class> {
+    const NOT_METHODS: [&str; 9] = ["if", "for", "while", "switch", "catch", "function", "return", "super", "constructor"];
+    let mut classes: Vec<Class> = vec![];
+    let mut open: Option<(usize, i64)> = None;
+    let mut depth = 0i64;
+    let mut in_comment = false;
+
+    for line in file.text.lines() {
+        let code = strip_code(line, &mut in_comment, &['"', '\'', '`']);
+        let start_depth = depth;
+        depth += brace_delta(&code);
+
+        if let Some((index, class_depth)) = open {
+            if start_depth <= class_depth {
+                open = None;
+            } else if start_depth == class_depth + 1 {
+                let mut member = code.trim();
+                for modifier in ["public ", "private ", "protected ", "static ", "async ", "get ", "set ", "readonly ", "override ", "*"] {
+                    member = member.strip_prefix(modifier).unwrap_or(member).trim_start();
+                }
+                let name = leading_identifier(member);
+                let after = member[name.len()..].trim_start();
+                if !name.is_empty() && !NOT_METHODS.contains(&name) && (after.starts_with('(') || after.starts_with('<')) {
+                    classes[index].methods.push(name.to_string());
+                }
+                continue;
+            } else if start_depth > class_depth {
+                continue;
+            }
+        }
+
+        let mut tokens = code.split_whitespace().peekable();
+        while tokens.peek().is_some_and(|token| matches!(*token, "export" | "default" | "abstract" | "declare")) {
+            tokens.next();
+        }
+        let kind = match tokens.next() {
+            Some("class") => "class",
+            Some("interface") => "interface",
+            _ => continue,
+        };
+        let Some(name) = tokens.next().map(leading_identifier).filter(|name| !name.is_empty()) else { continue };
+        let mut class = Class::new(name, kind, &file.id);
+
+        let header = code.split('{').next().unwrap_or_default();
+        let header = skip_generics(header.split_once(name).map_or("", |(_, rest)| rest));
+        let mut parents = header.replace(" extends ", " , ").replace(" implements ", " , ");
+        parents = parents.trim_start().trim_start_matches("extends ").trim_start_matches("implements ").to_string();
+        for parent in parents.split(',') {
+            let parent = type_name(parent.trim().rsplit('.').next().unwrap_or_default());
+            if !parent.is_empty() {
+                class.parents.insert(parent);
+            }
+        }
+
+        if depth > start_depth {
+            open = Some((classes.len(), start_depth));
+        }
+        classes.push(class);
+    }
+    classes
+}
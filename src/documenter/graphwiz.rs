@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::documenter::architecture::Architecture;
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Record labels give `{`, `}`, `|`, `<` and `>` a meaning of their own.
+fn escape_record(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Module dependency graph in Graphviz DOT, with a cluster per directory.
+pub fn module_graph(architecture: &Architecture) -> String {
+    let mut directories: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for module in &architecture.modules {
+        let directory = module.id.rsplit_once('/').map_or("", |(directory, _)| directory);
+        directories.entry(directory).or_default().push(&module.id);
+    }
+
+    let mut dot = String::from("digraph modules {\n    rankdir=LR;\n    node [shape=box, style=rounded, fontname=\"Helvetica\"];\n");
+    for (index, (directory, modules)) in directories.iter().enumerate() {
+        let indent = if directory.is_empty() { "    " } else { "        " };
+        if !directory.is_empty() {
+            let _ = writeln!(dot, "    subgraph cluster_{} {{\n        label={};\n        style=dashed;", index, quote(directory));
+        }
+        for id in modules {
+            let label = id.rsplit('/').next().unwrap_or(id);
+            let _ = writeln!(dot, "{}{} [label={}];", indent, quote(id), quote(label));
+        }
+        if !directory.is_empty() {
+            dot.push_str("    }\n");
+        }
+    }
+    for module in &architecture.modules {
+        for import in &module.imports {
+            let _ = writeln!(dot, "    {} -> {};", quote(&module.id), quote(import));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Class diagram in Graphviz DOT: a record per class with its methods, hollow
+/// arrows to base classes and dashed ones to implemented traits and interfaces.
+pub fn class_graph(architecture: &Architecture) -> String {
+    let ids = architecture.class_ids();
+    let mut dot = String::from("digraph classes {\n    rankdir=BT;\n    node [shape=record, fontname=\"Helvetica\"];\n");
+    for (class, id) in architecture.classes.iter().zip(&ids) {
+        let mut label = String::new();
+        if class.kind != "class" {
+            let _ = write!(label, "\\<\\<{}\\>\\>\\n", class.kind);
+        }
+        label.push_str(&escape_record(&class.name));
+        label.push('|');
+        for method in &class.methods {
+            let _ = write!(label, "{}()\\l", escape_record(method));
+        }
+        let _ = writeln!(dot, "    {} [label=\"{{{}}}\", tooltip={}];", id, label, quote(&class.module));
+    }
+    for (child, parent) in architecture.inheritance() {
+        let style = if architecture.classes[parent].is_abstract() { "dashed" } else { "solid" };
+        let _ = writeln!(dot, "    {} -> {} [arrowhead=empty, style={}];", ids[child], ids[parent], style);
+    }
+    dot.push_str("}\n");
+    dot
+}
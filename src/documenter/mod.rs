@@ -0,0 +1,64 @@
+pub mod architecture;
+pub mod graphwiz;
+pub mod pdf;
+pub mod uml;
+
+use std::fmt::Write;
+
+use crate::documenter::architecture::{scan_project, Architecture};
+
+/// Rows of the module table in a report; the diagrams still show everything.
+const MAX_REPORTED_MODULES: usize = 60;
+
+/// Renders a `modules` or `classes` diagram as `dot`, `mermaid` or `plantuml`.
+pub fn render_diagram(architecture: &Architecture, kind: &str, format: &str) -> Result<String, String> {
+    match (kind, format) {
+        ("modules", "dot") => Ok(graphwiz::module_graph(architecture)),
+        ("classes", "dot") => Ok(graphwiz::class_graph(architecture)),
+        ("modules", "mermaid") => Ok(uml::mermaid_modules(architecture)),
+        ("classes", "mermaid") => Ok(uml::mermaid_classes(architecture)),
+        ("modules", "plantuml") => Ok(uml::plantuml_modules(architecture)),
+        ("classes", "plantuml") => Ok(uml::plantuml_classes(architecture)),
+        ("modules" | "classes", _) => Err(format!("Unknown diagram format: {} (expected dot, mermaid or plantuml)", format)),
+        _ => Err(format!("Unknown diagram kind: {} (expected modules or classes)", kind)),
+    }
+}
+
+/// An "Architecture" section for the project's report: a table of the modules and
+/// what they depend on, followed by links to the module and class diagrams.
+/// `None` when the project has no source files the scanner understands.
+pub fn architecture_section(project_name: &str) -> Option<String> {
+    let architecture = scan_project(project_name).ok().filter(|architecture| !architecture.is_empty())?;
+
+    let mut section = String::from("# Architecture\n\n");
+    let _ = writeln!(section, "The project has {} source module(s) and {} type(s).\n", architecture.modules.len(), architecture.classes.len());
+
+    section.push_str("| Module | Language | Depends on | External imports |\n|---|---|---|---|\n");
+    for module in architecture.modules.iter().take(MAX_REPORTED_MODULES) {
+        let list = |items: Vec<&str>| if items.is_empty() { "-".to_string() } else { items.join(", ") };
+        let _ = writeln!(
+            section,
+            "| `{}` | {} | {} | {} |",
+            module.id,
+            module.language.name(),
+            list(module.imports.iter().map(String::as_str).collect()),
+            list(module.external.iter().map(String::as_str).collect()),
+        );
+    }
+    if architecture.modules.len() > MAX_REPORTED_MODULES {
+        let _ = writeln!(section, "\n...and {} more module(s).", architecture.modules.len() - MAX_REPORTED_MODULES);
+    }
+
+    // The PDF renderer can't draw Mermaid, so the report links to the diagrams instead.
+    let diagram_url = |kind: &str| format!(
+        "http://127.0.0.1:1337/api/project-diagram?project_name={}&kind={}",
+        project_name.replace(' ', "%20"),
+        kind,
+    );
+    section.push_str("\n## Diagrams\n\n");
+    let _ = writeln!(section, "- Module diagram: {}", diagram_url("modules"));
+    if !architecture.classes.is_empty() {
+        let _ = writeln!(section, "- Class diagram: {}", diagram_url("classes"));
+    }
+    Some(section)
+}
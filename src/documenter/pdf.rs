@@ -0,0 +1,1066 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use crate::config::Config;
+use crate::filesystem::project_path;
+
+// A4, in points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.0;
+const TABLE_SIZE: f32 = 9.5;
+const FOOTER_SIZE: f32 = 8.0;
+const LINE_HEIGHT: f32 = 1.35;
+const LIST_INDENT: f32 = 18.0;
+const CELL_PADDING: f32 = 4.0;
+const CODE_PADDING: f32 = 6.0;
+
+const TEXT_COLOR: &str = "0.1 0.1 0.1";
+const QUOTE_COLOR: &str = "0.4 0.4 0.4";
+const LINK_COLOR: &str = "0.0 0.33 0.73";
+
+/// Renders the Markdown reports of the Reporter agent, without xhtml2pdf or any
+/// other external tool.
+pub struct Pdf {
+    pdf_path: PathBuf,
+}
+
+impl Pdf {
+    pub fn new() -> Result<Self, String> {
+        let config = Config::new().map_err(|e| e.to_string())?;
+        Ok(Self {
+            pdf_path: PathBuf::from(config.get_pdfs_dir()),
+        })
+    }
+
+    /// Writes `PDFS_DIR/<project>.pdf`, the file `/api/download-project-pdf` serves.
+    /// Images are only read from inside the project directory.
+    pub fn markdown_to_pdf(&self, markdown: &str, project_name: &str) -> Result<PathBuf, String> {
+        let out_file_path = pdf_file_path(&self.pdf_path, project_name)?;
+        let image_root = project_path(project_name)?;
+
+        let document = render_markdown(markdown, &image_root);
+        fs::create_dir_all(&self.pdf_path).map_err(|e| e.to_string())?;
+        fs::write(&out_file_path, document).map_err(|e| format!("Error generating PDF: {}", e))?;
+        Ok(out_file_path)
+    }
+}
+
+/// Where a project's report lives. The name comes from the client on download, so
+/// anything that could leave `PDFS_DIR` is refused.
+pub fn pdf_file_path(pdf_path: &Path, project_name: &str) -> Result<PathBuf, String> {
+    if project_name.is_empty() || project_name.contains(['/', '\\']) || project_name.starts_with('.') {
+        return Err(format!("Invalid project name: {}", project_name));
+    }
+    Ok(pdf_path.join(format!("{}.pdf", project_name)))
+}
+
+/// Lays the document out on A4 pages and returns the PDF file.
+pub fn render_markdown(markdown: &str, image_root: &Path) -> Vec<u8> {
+    let blocks = parse_blocks(markdown);
+    let mut layout = Layout::new(image_root);
+    for block in &blocks {
+        layout.block(block);
+    }
+    layout.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 5] = [Font::Regular, Font::Bold, Font::Italic, Font::BoldItalic, Font::Mono];
+
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Italic => "F3",
+            Font::BoldItalic => "F4",
+            Font::Mono => "F5",
+        }
+    }
+
+    /// One of the standard Type 1 fonts every PDF reader ships, so nothing is embedded.
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Italic => "Helvetica-Oblique",
+            Font::BoldItalic => "Helvetica-BoldOblique",
+            Font::Mono => "Courier",
+        }
+    }
+
+    /// Advance width of a WinAnsi character, in thousandths of the font size.
+    fn glyph_width(self, byte: u8) -> f32 {
+        let table = match self {
+            Font::Mono => return 600.0,
+            Font::Regular | Font::Italic => &HELVETICA_WIDTHS,
+            Font::Bold | Font::BoldItalic => &HELVETICA_BOLD_WIDTHS,
+        };
+        let width = match byte {
+            32..=126 => table[(byte - 32) as usize],
+            0x85 | 0x89 | 0x97 | 0x99 => 1000,
+            0x91 | 0x92 | 0x82 => 222,
+            0x93 | 0x94 | 0x84 => 333,
+            0x95 => 350,
+            0xa0 => 278,
+            _ => 556,
+        };
+        width as f32
+    }
+
+    fn text_width(self, text: &str, size: f32) -> f32 {
+        text.chars().map(|c| self.glyph_width(win_ansi(c))).sum::<f32>() * size / 1000.0
+    }
+}
+
+/// Helvetica and Helvetica-Bold widths for ' ' through '~', from the Adobe AFM files.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// The standard fonts only cover WinAnsi; anything else prints as `?`.
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '\t' => b' ',
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '€' => 0x80, '‚' => 0x82, 'ƒ' => 0x83, '„' => 0x84, '…' => 0x85, '†' => 0x86,
+        '‡' => 0x87, 'ˆ' => 0x88, '‰' => 0x89, 'Š' => 0x8a, '‹' => 0x8b, 'Œ' => 0x8c,
+        'Ž' => 0x8e, '‘' => 0x91, '’' => 0x92, '“' => 0x93, '”' => 0x94, '•' => 0x95,
+        '–' => 0x96, '—' => 0x97, '˜' => 0x98, '™' => 0x99, 'š' => 0x9a, '›' => 0x9b,
+        'œ' => 0x9c, 'ž' => 0x9e, 'Ÿ' => 0x9f,
+        _ => b'?',
+    }
+}
+
+/// A PDF string literal in WinAnsi.
+fn pdf_string(text: &str) -> String {
+    let mut literal = String::from("(");
+    for byte in text.chars().map(win_ansi) {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                literal.push('\\');
+                literal.push(byte as char);
+            }
+            32..=126 => literal.push(byte as char),
+            _ => {
+                let _ = write!(literal, "\\{:03o}", byte);
+            }
+        }
+    }
+    literal.push(')');
+    literal
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+    link: bool,
+}
+
+impl Style {
+    fn font(self) -> Font {
+        match (self.code, self.bold, self.italic) {
+            (true, _, _) => Font::Mono,
+            (false, true, true) => Font::BoldItalic,
+            (false, true, false) => Font::Bold,
+            (false, false, true) => Font::Italic,
+            (false, false, false) => Font::Regular,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Span {
+    text: String,
+    style: Style,
+}
+
+#[derive(Debug)]
+enum Block {
+    Heading { level: usize, spans: Vec<Span> },
+    Paragraph { spans: Vec<Span>, depth: usize, marker: Option<String>, quote: bool },
+    Code(String),
+    Table { header: Vec<Vec<Span>>, rows: Vec<Vec<Vec<Span>>> },
+    Image { source: String, alt: String },
+    Rule,
+}
+
+#[derive(Default)]
+struct TableBuilder {
+    header: Vec<Vec<Span>>,
+    rows: Vec<Vec<Vec<Span>>>,
+    row: Vec<Vec<Span>>,
+}
+
+/// Flattens the Markdown events into the blocks the layout knows how to draw.
+#[derive(Default)]
+struct BlockBuilder {
+    blocks: Vec<Block>,
+    spans: Vec<Span>,
+    style: Style,
+    bold: usize,
+    italic: usize,
+    links: usize,
+    /// The next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    marker: Option<String>,
+    quotes: usize,
+    heading: Option<usize>,
+    code: Option<String>,
+    table: Option<TableBuilder>,
+    image: Option<(String, String)>,
+}
+
+impl BlockBuilder {
+    fn push_text(&mut self, text: &str, style: Style) {
+        if let Some((_, alt)) = &mut self.image {
+            alt.push_str(text);
+        } else if let Some(code) = &mut self.code {
+            code.push_str(text);
+        } else {
+            self.spans.push(Span { text: text.to_string(), style });
+        }
+    }
+
+    fn update_style(&mut self) {
+        self.style.bold = self.bold > 0;
+        self.style.italic = self.italic > 0;
+        self.style.link = self.links > 0;
+    }
+
+    fn flush_paragraph(&mut self) {
+        if self.spans.iter().all(|span| span.text.trim().is_empty()) {
+            self.spans.clear();
+            return;
+        }
+        self.blocks.push(Block::Paragraph {
+            spans: std::mem::take(&mut self.spans),
+            depth: self.lists.len(),
+            marker: self.marker.take(),
+            quote: self.quotes > 0,
+        });
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.push_text(&text, self.style),
+            Event::Code(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                self.push_text(&text, Style { code: true, ..self.style });
+            }
+            Event::SoftBreak => self.push_text(" ", self.style),
+            Event::HardBreak => self.push_text("\n", self.style),
+            Event::Rule => {
+                self.flush_paragraph();
+                self.blocks.push(Block::Rule);
+            }
+            Event::TaskListMarker(checked) => {
+                self.push_text(if checked { "[x] " } else { "[ ] " }, Style { code: true, ..self.style });
+            }
+            Event::FootnoteReference(name) => self.push_text(&format!("[{}]", name), self.style),
+            Event::Html(_) | Event::InlineHtml(_) => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush_paragraph();
+                self.heading = Some(heading_level(level));
+            }
+            Tag::BlockQuote(_) => {
+                self.flush_paragraph();
+                self.quotes += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.flush_paragraph();
+                self.code = Some(String::new());
+            }
+            Tag::List(start) => {
+                self.flush_paragraph();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush_paragraph();
+                self.marker = Some(match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "•".to_string(),
+                });
+            }
+            Tag::Table(_) => {
+                self.flush_paragraph();
+                self.table = Some(TableBuilder::default());
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.row.clear();
+                }
+            }
+            Tag::TableCell => self.spans.clear(),
+            Tag::Emphasis => {
+                self.italic += 1;
+                self.update_style();
+            }
+            Tag::Strong => {
+                self.bold += 1;
+                self.update_style();
+            }
+            Tag::Link { .. } => {
+                self.links += 1;
+                self.update_style();
+            }
+            Tag::Image { dest_url, .. } => self.image = Some((dest_url.to_string(), String::new())),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::Item => self.flush_paragraph(),
+            TagEnd::Heading(_) => {
+                let spans = std::mem::take(&mut self.spans);
+                self.blocks.push(Block::Heading { level: self.heading.take().unwrap_or(1), spans });
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush_paragraph();
+                self.quotes = self.quotes.saturating_sub(1);
+            }
+            TagEnd::CodeBlock => {
+                let code = self.code.take().unwrap_or_default();
+                self.blocks.push(Block::Code(code.trim_end_matches('\n').to_string()));
+            }
+            TagEnd::List(_) => {
+                self.flush_paragraph();
+                self.lists.pop();
+            }
+            TagEnd::TableCell => {
+                let cell = std::mem::take(&mut self.spans);
+                if let Some(table) = &mut self.table {
+                    table.row.push(cell);
+                }
+            }
+            TagEnd::TableHead => {
+                if let Some(table) = &mut self.table {
+                    table.header = std::mem::take(&mut table.row);
+                }
+            }
+            TagEnd::TableRow => {
+                if let Some(table) = &mut self.table {
+                    let row = std::mem::take(&mut table.row);
+                    table.rows.push(row);
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.blocks.push(Block::Table { header: table.header, rows: table.rows });
+                }
+            }
+            TagEnd::Emphasis => {
+                self.italic = self.italic.saturating_sub(1);
+                self.update_style();
+            }
+            TagEnd::Strong => {
+                self.bold = self.bold.saturating_sub(1);
+                self.update_style();
+            }
+            TagEnd::Link => {
+                self.links = self.links.saturating_sub(1);
+                self.update_style();
+            }
+            TagEnd::Image => {
+                if let Some((source, alt)) = self.image.take() {
+                    if self.table.is_some() || self.heading.is_some() {
+                        // No room for a picture inside a cell or a heading.
+                        self.push_text(&alt, Style { italic: true, ..self.style });
+                    } else {
+                        // Images are drawn as blocks of their own, between the text around them.
+                        self.flush_paragraph();
+                        self.blocks.push(Block::Image { source, alt });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+fn parse_blocks(markdown: &str) -> Vec<Block> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES;
+    let mut builder = BlockBuilder::default();
+    for event in Parser::new_ext(markdown, options) {
+        builder.event(event);
+    }
+    builder.flush_paragraph();
+    builder.blocks
+}
+
+/// A piece of a laid out line, all in one style.
+#[derive(Debug, Clone)]
+struct Run {
+    text: String,
+    style: Style,
+}
+
+fn push_run(line: &mut Vec<Run>, text: &str, style: Style) {
+    match line.last_mut() {
+        Some(run) if run.style == style => run.text.push_str(text),
+        _ => line.push(Run { text: text.to_string(), style }),
+    }
+}
+
+fn finish_line(lines: &mut Vec<Vec<Run>>, line: &mut Vec<Run>) {
+    if let Some(run) = line.last_mut() {
+        let trimmed = run.text.trim_end().len();
+        run.text.truncate(trimmed);
+    }
+    lines.push(std::mem::take(line));
+}
+
+/// Greedy word wrapping; words wider than a whole line are broken between characters.
+fn wrap(spans: &[Span], size: f32, max_width: f32) -> Vec<Vec<Run>> {
+    let mut lines = vec![];
+    let mut line = vec![];
+    let mut width = 0.0;
+
+    for span in spans {
+        let font = span.style.font();
+        for (index, paragraph) in span.text.split('\n').enumerate() {
+            if index > 0 {
+                finish_line(&mut lines, &mut line);
+                width = 0.0;
+            }
+            for (is_space, token) in tokens(paragraph) {
+                if is_space {
+                    if !line.is_empty() {
+                        push_run(&mut line, " ", span.style);
+                        width += font.text_width(" ", size);
+                    }
+                    continue;
+                }
+
+                let token_width = font.text_width(token, size);
+                if width + token_width > max_width && !line.is_empty() {
+                    finish_line(&mut lines, &mut line);
+                    width = 0.0;
+                }
+                if token_width <= max_width {
+                    push_run(&mut line, token, span.style);
+                    width += token_width;
+                    continue;
+                }
+                for c in token.chars() {
+                    let char_width = font.text_width(c.encode_utf8(&mut [0; 4]), size);
+                    if width + char_width > max_width && !line.is_empty() {
+                        finish_line(&mut lines, &mut line);
+                        width = 0.0;
+                    }
+                    push_run(&mut line, c.encode_utf8(&mut [0; 4]), span.style);
+                    width += char_width;
+                }
+            }
+        }
+    }
+    if !line.is_empty() {
+        finish_line(&mut lines, &mut line);
+    }
+    lines
+}
+
+/// Splits text into alternating runs of whitespace and non-whitespace.
+fn tokens(text: &str) -> Vec<(bool, &str)> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    let mut in_space = None;
+    for (index, c) in text.char_indices() {
+        let is_space = c.is_whitespace();
+        if in_space.is_some_and(|space| space != is_space) {
+            tokens.push((in_space.unwrap(), &text[start..index]));
+            start = index;
+        }
+        in_space = Some(is_space);
+    }
+    if let Some(is_space) = in_space {
+        tokens.push((is_space, &text[start..]));
+    }
+    tokens
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = 4 - expanded.chars().count() % 4;
+            expanded.push_str(&" ".repeat(spaces));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    decode: Option<&'static str>,
+    filter: &'static str,
+    data: Vec<u8>,
+    alpha: Option<Vec<u8>>,
+}
+
+/// JPEGs are embedded as they are; only the frame header is read for the size.
+fn load_jpeg(bytes: Vec<u8>) -> Option<PdfImage> {
+    let mut index = 2;
+    while index + 9 < bytes.len() {
+        if bytes[index] != 0xff {
+            return None;
+        }
+        let marker = bytes[index + 1];
+        if marker == 0xff || (0xd0..=0xd9).contains(&marker) || marker == 0x01 {
+            index += if marker == 0xff { 1 } else { 2 };
+            continue;
+        }
+        let length = u16::from_be_bytes([bytes[index + 2], bytes[index + 3]]) as usize;
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            let height = u16::from_be_bytes([bytes[index + 5], bytes[index + 6]]) as u32;
+            let width = u16::from_be_bytes([bytes[index + 7], bytes[index + 8]]) as u32;
+            let (color_space, decode) = match bytes[index + 9] {
+                1 => ("/DeviceGray", None),
+                3 => ("/DeviceRGB", None),
+                // CMYK JPEGs are nearly always written inverted, the Adobe way.
+                4 => ("/DeviceCMYK", Some("[1 0 1 0 1 0 1 0]")),
+                _ => return None,
+            };
+            return Some(PdfImage { width, height, color_space, decode, filter: "/DCTDecode", data: bytes, alpha: None });
+        }
+        index += 2 + length;
+    }
+    None
+}
+
+/// PNGs are decoded to 8-bit samples and recompressed, with any alpha channel as a soft mask.
+fn load_png(bytes: Vec<u8>) -> Option<PdfImage> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().ok()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut pixels).ok()?;
+    pixels.truncate(frame.buffer_size());
+
+    let (channels, color_space) = match frame.color_type {
+        png::ColorType::Grayscale => (1, "/DeviceGray"),
+        png::ColorType::GrayscaleAlpha => (2, "/DeviceGray"),
+        png::ColorType::Rgb => (3, "/DeviceRGB"),
+        png::ColorType::Rgba => (4, "/DeviceRGB"),
+        png::ColorType::Indexed => return None,
+    };
+    let (data, alpha) = if channels == 2 || channels == 4 {
+        let mut color = Vec::with_capacity(pixels.len());
+        let mut alpha = Vec::with_capacity(pixels.len() / channels);
+        for pixel in pixels.chunks_exact(channels) {
+            color.extend_from_slice(&pixel[..channels - 1]);
+            alpha.push(pixel[channels - 1]);
+        }
+        (color, Some(deflate(&alpha)))
+    } else {
+        (pixels, None)
+    };
+
+    Some(PdfImage {
+        width: frame.width,
+        height: frame.height,
+        color_space,
+        decode: None,
+        filter: "/FlateDecode",
+        data: deflate(&data),
+        alpha,
+    })
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+/// Places the blocks top to bottom, starting a new page whenever the next piece
+/// does not fit.
+struct Layout {
+    image_root: Option<PathBuf>,
+    pages: Vec<String>,
+    content: String,
+    y: f32,
+    images: Vec<PdfImage>,
+    image_ids: HashMap<String, Option<usize>>,
+}
+
+impl Layout {
+    fn new(image_root: &Path) -> Self {
+        Self {
+            image_root: image_root.canonicalize().ok(),
+            pages: vec![],
+            content: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+            images: vec![],
+            image_ids: HashMap::new(),
+        }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.content));
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn at_page_top(&self) -> bool {
+        self.y >= PAGE_HEIGHT - MARGIN
+    }
+
+    /// Moves to a new page unless `height` still fits on this one.
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN && !self.at_page_top() {
+            self.new_page();
+        }
+    }
+
+    fn space(&mut self, height: f32) {
+        if !self.at_page_top() {
+            self.y -= height;
+        }
+    }
+
+    fn draw_runs(&mut self, x: f32, baseline: f32, line: &[Run], size: f32, color: &str) {
+        let _ = write!(self.content, "BT {:.2} {:.2} Td ", x, baseline);
+        for run in line {
+            let color = if run.style.link { LINK_COLOR } else { color };
+            let _ = write!(self.content, "/{} {:.2} Tf {} rg {} Tj ", run.style.font().resource(), size, color, pdf_string(&run.text));
+        }
+        self.content.push_str("ET\n");
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, gray: f32) {
+        let _ = writeln!(self.content, "{:.2} g {:.2} {:.2} {:.2} {:.2} re f", gray, x, y, width, height);
+    }
+
+    fn stroke_line(&mut self, from: (f32, f32), to: (f32, f32), gray: f32, width: f32) {
+        let _ = writeln!(self.content, "{:.2} G {:.2} w {:.2} {:.2} m {:.2} {:.2} l S", gray, width, from.0, from.1, to.0, to.1);
+    }
+
+    fn block(&mut self, block: &Block) {
+        match block {
+            Block::Heading { level, spans } => self.heading(*level, spans),
+            Block::Paragraph { spans, depth, marker, quote } => self.paragraph(spans, *depth, marker.as_deref(), *quote),
+            Block::Code(code) => self.code(code),
+            Block::Table { header, rows } => self.table(header, rows),
+            Block::Image { source, alt } => self.image(source, alt),
+            Block::Rule => {
+                self.reserve(16.0);
+                self.space(8.0);
+                let y = self.y;
+                self.stroke_line((MARGIN, y), (PAGE_WIDTH - MARGIN, y), 0.75, 0.5);
+                self.y -= 8.0;
+            }
+        }
+    }
+
+    fn heading(&mut self, level: usize, spans: &[Span]) {
+        let size = match level {
+            1 => 20.0,
+            2 => 16.0,
+            3 => 13.5,
+            _ => 12.0,
+        };
+        let spans: Vec<Span> = spans.iter()
+            .map(|span| Span { text: span.text.clone(), style: Style { bold: true, ..span.style } })
+            .collect();
+        let lines = wrap(&spans, size, CONTENT_WIDTH);
+        let leading = size * 1.2;
+
+        self.space(size * 0.8);
+        // Keep the heading together with the start of its section.
+        self.reserve(leading * lines.len() as f32 + BODY_SIZE * LINE_HEIGHT * 2.0);
+        for line in &lines {
+            let baseline = self.y - size;
+            self.draw_runs(MARGIN, baseline, line, size, TEXT_COLOR);
+            self.y -= leading;
+        }
+        if level <= 2 {
+            self.y -= 3.0;
+            let y = self.y;
+            self.stroke_line((MARGIN, y), (PAGE_WIDTH - MARGIN, y), 0.8, 0.5);
+        }
+        self.y -= size * 0.4;
+    }
+
+    fn paragraph(&mut self, spans: &[Span], depth: usize, marker: Option<&str>, quote: bool) {
+        let indent = LIST_INDENT * depth as f32 + if quote { LIST_INDENT } else { 0.0 };
+        let x = MARGIN + indent;
+        let leading = BODY_SIZE * LINE_HEIGHT;
+        let color = if quote { QUOTE_COLOR } else { TEXT_COLOR };
+
+        for (index, line) in wrap(spans, BODY_SIZE, CONTENT_WIDTH - indent).iter().enumerate() {
+            self.reserve(leading);
+            let baseline = self.y - BODY_SIZE;
+            if let (0, Some(marker)) = (index, marker) {
+                let marker_x = x - Font::Regular.text_width(marker, BODY_SIZE) - 5.0;
+                let marker_run = [Run { text: marker.to_string(), style: Style::default() }];
+                self.draw_runs(marker_x, baseline, &marker_run, BODY_SIZE, TEXT_COLOR);
+            }
+            if quote {
+                let bar_x = x - 10.0;
+                let top = self.y;
+                self.stroke_line((bar_x, top), (bar_x, top - leading), 0.8, 2.0);
+            }
+            self.draw_runs(x, baseline, line, BODY_SIZE, color);
+            self.y -= leading;
+        }
+        self.y -= if depth > 0 { 3.0 } else { 7.0 };
+    }
+
+    fn code(&mut self, code: &str) {
+        let leading = CODE_SIZE * 1.3;
+        let columns = ((CONTENT_WIDTH - 2.0 * CODE_PADDING) / (CODE_SIZE * 0.6)).floor().max(1.0) as usize;
+
+        let mut lines = vec![];
+        for line in code.lines() {
+            let chars: Vec<char> = expand_tabs(line).chars().collect();
+            if chars.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(chars.chunks(columns).map(|chunk| chunk.iter().collect::<String>()));
+        }
+
+        self.space(2.0);
+        let mut remaining = &lines[..];
+        while !remaining.is_empty() {
+            let fits = ((self.y - MARGIN - 2.0 * CODE_PADDING) / leading).floor().max(0.0) as usize;
+            // Do not leave a lone line or two of a longer block at the bottom of a page.
+            if fits < remaining.len().min(3) && !self.at_page_top() {
+                self.new_page();
+                continue;
+            }
+            let (chunk, rest) = remaining.split_at(fits.clamp(1, remaining.len()));
+            let height = chunk.len() as f32 * leading + 2.0 * CODE_PADDING;
+            let top = self.y;
+            self.fill_rect(MARGIN, top - height, CONTENT_WIDTH, height, 0.95);
+            let mut baseline = top - CODE_PADDING - CODE_SIZE;
+            for line in chunk {
+                let run = [Run { text: line.clone(), style: Style { code: true, ..Style::default() } }];
+                self.draw_runs(MARGIN + CODE_PADDING, baseline, &run, CODE_SIZE, TEXT_COLOR);
+                baseline -= leading;
+            }
+            self.y -= height;
+            remaining = rest;
+        }
+        self.y -= 8.0;
+    }
+
+    /// Columns get what their widest cell needs when everything fits; otherwise
+    /// narrow columns keep their natural width and the rest share what is left.
+    fn column_widths(header: &[Vec<Span>], rows: &[Vec<Vec<Span>>]) -> Vec<f32> {
+        let columns = rows.iter().map(Vec::len).chain([header.len()]).max().unwrap_or(0);
+        let mut natural = vec![0.0f32; columns];
+        let all_rows = std::iter::once((header, true)).chain(rows.iter().map(|row| (row.as_slice(), false)));
+        for (row, bold) in all_rows {
+            for (column, cell) in row.iter().enumerate() {
+                let width: f32 = cell.iter()
+                    .map(|span| Style { bold: bold || span.style.bold, ..span.style }.font().text_width(&span.text, TABLE_SIZE))
+                    .sum();
+                natural[column] = natural[column].max(width + 2.0 * CELL_PADDING + 1.0);
+            }
+        }
+        if natural.iter().sum::<f32>() <= CONTENT_WIDTH {
+            return natural;
+        }
+
+        let fair = CONTENT_WIDTH / columns as f32;
+        let mut widths: Vec<f32> = natural.iter().map(|width| width.min(fair)).collect();
+        let left = CONTENT_WIDTH - widths.iter().sum::<f32>();
+        let wanted: f32 = natural.iter().map(|width| (width - fair).max(0.0)).sum();
+        for (width, natural) in widths.iter_mut().zip(&natural) {
+            *width += left * (natural - fair).max(0.0) / wanted;
+        }
+        widths
+    }
+
+    fn table(&mut self, header: &[Vec<Span>], rows: &[Vec<Vec<Span>>]) {
+        let widths = Self::column_widths(header, rows);
+        if widths.is_empty() {
+            return;
+        }
+        let header: Vec<Vec<Span>> = header.iter()
+            .map(|cell| cell.iter().map(|span| Span { text: span.text.clone(), style: Style { bold: true, ..span.style } }).collect())
+            .collect();
+
+        self.space(4.0);
+        let has_header = !header.is_empty();
+        if has_header {
+            self.table_row(&header, &widths, true);
+        }
+        for row in rows {
+            let height = Self::row_height(row, &widths);
+            if self.y - height < MARGIN && !self.at_page_top() {
+                self.new_page();
+                // Repeat the header so every page of the table can be read on its own.
+                if has_header {
+                    self.table_row(&header, &widths, true);
+                }
+            }
+            self.table_row(row, &widths, false);
+        }
+        self.y -= 10.0;
+    }
+
+    fn row_height(row: &[Vec<Span>], widths: &[f32]) -> f32 {
+        let lines = row.iter().zip(widths)
+            .map(|(cell, width)| wrap(cell, TABLE_SIZE, width - 2.0 * CELL_PADDING).len().max(1))
+            .max()
+            .unwrap_or(1);
+        lines as f32 * TABLE_SIZE * 1.25 + 2.0 * CELL_PADDING
+    }
+
+    fn table_row(&mut self, row: &[Vec<Span>], widths: &[f32], is_header: bool) {
+        let height = Self::row_height(row, widths);
+        self.reserve(height);
+        let top = self.y;
+        let mut x = MARGIN;
+        for (column, width) in widths.iter().enumerate() {
+            if is_header {
+                self.fill_rect(x, top - height, *width, height, 0.92);
+            }
+            let _ = writeln!(self.content, "0.6 G 0.5 w {:.2} {:.2} {:.2} {:.2} re S", x, top - height, width, height);
+
+            let cell = row.get(column).map(Vec::as_slice).unwrap_or(&[]);
+            let mut baseline = top - CELL_PADDING - TABLE_SIZE;
+            for line in wrap(cell, TABLE_SIZE, width - 2.0 * CELL_PADDING) {
+                self.draw_runs(x + CELL_PADDING, baseline, &line, TABLE_SIZE, TEXT_COLOR);
+                baseline -= TABLE_SIZE * 1.25;
+            }
+            x += width;
+        }
+        self.y -= height;
+    }
+
+    /// Local JPEG and PNG files inside the project. Remote images and anything that
+    /// cannot be read are replaced by their alt text.
+    fn load_image(&mut self, source: &str) -> Option<usize> {
+        if let Some(id) = self.image_ids.get(source) {
+            return *id;
+        }
+        let id = self.read_image(source).map(|image| {
+            self.images.push(image);
+            self.images.len() - 1
+        });
+        self.image_ids.insert(source.to_string(), id);
+        id
+    }
+
+    fn read_image(&self, source: &str) -> Option<PdfImage> {
+        if source.contains("://") || source.starts_with("data:") {
+            return None;
+        }
+        let root = self.image_root.as_ref()?;
+        let path = root.join(source.replace("%20", " ").trim_start_matches('/')).canonicalize().ok()?;
+        if !path.starts_with(root) {
+            return None;
+        }
+        let bytes = fs::read(path).ok()?;
+        if bytes.starts_with(&[0xff, 0xd8]) {
+            load_jpeg(bytes)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            load_png(bytes)
+        } else {
+            None
+        }
+    }
+
+    fn image(&mut self, source: &str, alt: &str) {
+        let Some(id) = self.load_image(source) else {
+            let text = if alt.is_empty() { source.to_string() } else { alt.to_string() };
+            let spans = [Span { text: format!("[Image: {}]", text), style: Style { italic: true, ..Style::default() } }];
+            self.paragraph(&spans, 0, None, false);
+            return;
+        };
+
+        // Pixels at 96 dpi, shrunk to fit the text column and a page.
+        let image = &self.images[id];
+        let mut width = image.width as f32 * 0.75;
+        let mut height = image.height as f32 * 0.75;
+        let max_height = PAGE_HEIGHT - 2.0 * MARGIN - 2.0 * BODY_SIZE * LINE_HEIGHT;
+        let scale = (CONTENT_WIDTH / width).min(max_height / height).min(1.0);
+        width *= scale;
+        height *= scale;
+
+        self.space(4.0);
+        self.reserve(height);
+        let x = MARGIN + (CONTENT_WIDTH - width) / 2.0;
+        let _ = writeln!(self.content, "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q", width, height, x, self.y - height, id + 1);
+        self.y -= height + 4.0;
+
+        if !alt.is_empty() {
+            let caption = wrap(&[Span { text: alt.to_string(), style: Style { italic: true, ..Style::default() } }], FOOTER_SIZE + 1.0, CONTENT_WIDTH);
+            for line in caption {
+                let line_width: f32 = line.iter().map(|run| run.style.font().text_width(&run.text, FOOTER_SIZE + 1.0)).sum();
+                self.reserve(FOOTER_SIZE * 1.6);
+                let baseline = self.y - FOOTER_SIZE - 1.0;
+                self.draw_runs(MARGIN + (CONTENT_WIDTH - line_width) / 2.0, baseline, &line, FOOTER_SIZE + 1.0, QUOTE_COLOR);
+                self.y -= FOOTER_SIZE * 1.6;
+            }
+        }
+        self.y -= 6.0;
+    }
+
+    /// Adds the page numbers and serializes everything into a PDF file.
+    fn finish(mut self) -> Vec<u8> {
+        if !self.content.is_empty() || self.pages.is_empty() {
+            self.new_page();
+        }
+        let page_count = self.pages.len();
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            let label = format!("{} / {}", index + 1, page_count);
+            let x = (PAGE_WIDTH - Font::Regular.text_width(&label, FOOTER_SIZE)) / 2.0;
+            let _ = writeln!(page, "BT {:.2} {:.2} Td /F1 {:.2} Tf {} rg {} Tj ET", x, MARGIN / 2.0, FOOTER_SIZE, QUOTE_COLOR, pdf_string(&label));
+        }
+
+        let mut writer = PdfWriter::default();
+        let (catalog_id, pages_id, resources_id) = (1, 2, 3);
+        let font_ids: Vec<usize> = (0..Font::ALL.len()).map(|index| 4 + index).collect();
+        let mut next_id = 4 + Font::ALL.len();
+
+        let mut image_ids = vec![];
+        for image in &self.images {
+            image_ids.push(next_id);
+            next_id += if image.alpha.is_some() { 2 } else { 1 };
+        }
+        let page_ids: Vec<usize> = (0..page_count).map(|index| next_id + 2 * index).collect();
+
+        writer.object(catalog_id, format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id).as_bytes());
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+        writer.object(pages_id, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_count).as_bytes());
+
+        let fonts: Vec<String> = Font::ALL.iter().zip(&font_ids).map(|(font, id)| format!("/{} {} 0 R", font.resource(), id)).collect();
+        let xobjects: Vec<String> = image_ids.iter().enumerate().map(|(index, id)| format!("/Im{} {} 0 R", index + 1, id)).collect();
+        writer.object(resources_id, format!("<< /Font << {} >> /XObject << {} >> >>", fonts.join(" "), xobjects.join(" ")).as_bytes());
+        for (font, id) in Font::ALL.iter().zip(&font_ids) {
+            let body = format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", font.base_font());
+            writer.object(*id, body.as_bytes());
+        }
+
+        for (image, id) in self.images.iter().zip(&image_ids) {
+            let mut dict = format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8 /Filter {}",
+                image.width, image.height, image.color_space, image.filter,
+            );
+            if let Some(decode) = image.decode {
+                let _ = write!(dict, " /Decode {}", decode);
+            }
+            if let Some(alpha) = &image.alpha {
+                let _ = write!(dict, " /SMask {} 0 R", id + 1);
+                let mask = format!("/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode", image.width, image.height);
+                writer.object(id + 1, &stream(&mask, alpha));
+            }
+            writer.object(*id, &stream(&dict, &image.data));
+        }
+
+        for (page, id) in self.pages.iter().zip(&page_ids) {
+            let body = format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources {} 0 R /Contents {} 0 R >>",
+                pages_id, PAGE_WIDTH, PAGE_HEIGHT, resources_id, id + 1,
+            );
+            writer.object(*id, body.as_bytes());
+            writer.object(id + 1, &stream("/Filter /FlateDecode", &deflate(page.as_bytes())));
+        }
+
+        writer.finish(catalog_id)
+    }
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+#[derive(Default)]
+struct PdfWriter {
+    buffer: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn object(&mut self, id: usize, body: &[u8]) {
+        if self.buffer.is_empty() {
+            // The binary comment tells transfer tools the file is not text.
+            self.buffer.extend_from_slice(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n");
+        }
+        if self.offsets.len() <= id {
+            self.offsets.resize(id + 1, 0);
+        }
+        self.offsets[id] = self.buffer.len();
+        self.buffer.extend_from_slice(format!("{} 0 obj\n", id).as_bytes());
+        self.buffer.extend_from_slice(body);
+        self.buffer.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn finish(mut self, root_id: usize) -> Vec<u8> {
+        let xref_offset = self.buffer.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len());
+        for offset in &self.offsets[1..] {
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(xref, "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n", self.offsets.len(), root_id, xref_offset);
+        self.buffer.extend_from_slice(xref.as_bytes());
+        self.buffer
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt::Write;
+
+use crate::documenter::architecture::Architecture;
+
+/// Mermaid flowchart of the module dependencies. Module paths are not valid
+/// Mermaid ids, so nodes are numbered and labelled with their path.
+pub fn mermaid_modules(architecture: &Architecture) -> String {
+    let mut diagram = String::from("graph LR\n");
+    let index = |id: &str| architecture.modules.iter().position(|module| module.id == id);
+    for (number, module) in architecture.modules.iter().enumerate() {
+        let _ = writeln!(diagram, "    m{}[\"{}\"]", number, module.id.replace('"', "#quot;"));
+    }
+    for (number, module) in architecture.modules.iter().enumerate() {
+        for import in module.imports.iter().filter_map(|import| index(import)) {
+            let _ = writeln!(diagram, "    m{} --> m{}", number, import);
+        }
+    }
+    diagram
+}
+
+pub fn mermaid_classes(architecture: &Architecture) -> String {
+    let ids = architecture.class_ids();
+    let mut diagram = String::from("classDiagram\n");
+    for (class, id) in architecture.classes.iter().zip(&ids) {
+        // Duplicate names get a numbered id, but keep their name as the label.
+        if *id == class.name {
+            let _ = writeln!(diagram, "    class {} {{", id);
+        } else {
+            let _ = writeln!(diagram, "    class {}[\"{}\"] {{", id, class.name);
+        }
+        match class.kind {
+            "class" => {}
+            "enum" => diagram.push_str("        <<enumeration>>\n"),
+            kind => {
+                let _ = writeln!(diagram, "        <<{}>>", kind);
+            }
+        }
+        for method in &class.methods {
+            let _ = writeln!(diagram, "        +{}()", method);
+        }
+        diagram.push_str("    }\n");
+    }
+    for (child, parent) in architecture.inheritance() {
+        let arrow = if architecture.classes[parent].is_abstract() { "<|.." } else { "<|--" };
+        let _ = writeln!(diagram, "    {} {} {}", ids[parent], arrow, ids[child]);
+    }
+    diagram
+}
+
+pub fn plantuml_modules(architecture: &Architecture) -> String {
+    let mut diagram = String::from("@startuml\nleft to right direction\n");
+    let index = |id: &str| architecture.modules.iter().position(|module| module.id == id);
+    for (number, module) in architecture.modules.iter().enumerate() {
+        let _ = writeln!(diagram, "component \"{}\" as m{}", module.id.replace('"', "'"), number);
+    }
+    for (number, module) in architecture.modules.iter().enumerate() {
+        for import in module.imports.iter().filter_map(|import| index(import)) {
+            let _ = writeln!(diagram, "m{} --> m{}", number, import);
+        }
+    }
+    diagram.push_str("@enduml\n");
+    diagram
+}
+
+pub fn plantuml_classes(architecture: &Architecture) -> String {
+    let ids = architecture.class_ids();
+    let mut diagram = String::from("@startuml\n");
+    for (class, id) in architecture.classes.iter().zip(&ids) {
+        let name = format!("\"{}\" as {}", class.name, id);
+        let declaration = match class.kind {
+            "interface" => format!("interface {}", name),
+            "trait" => format!("interface {} <<trait>>", name),
+            "enum" => format!("enum {}", name),
+            "struct" => format!("class {} <<struct>>", name),
+            _ => format!("class {}", name),
+        };
+        let _ = writeln!(diagram, "{} {{", declaration);
+        for method in &class.methods {
+            let _ = writeln!(diagram, "  +{}()", method);
+        }
+        diagram.push_str("}\n");
+    }
+    for (child, parent) in architecture.inheritance() {
+        let arrow = if architecture.classes[parent].is_abstract() { "<|.." } else { "<|--" };
+        let _ = writeln!(diagram, "{} {} {}", ids[parent], arrow, ids[child]);
+    }
+    diagram.push_str("@enduml\n");
+    diagram
+}
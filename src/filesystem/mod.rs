@@ -0,0 +1,108 @@
+pub mod patch;
+pub mod preview;
+pub mod project_tree;
+pub mod read_code;
+pub mod watcher;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::Config;
+
+/// Tells apart the temp files of concurrent writes to the same file.
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// `PROJECTS_DIR/<project>`, canonical once it exists. Project names come from
+/// clients, so names with separators, `..` or a leading dot are refused, and so is a
+/// project directory that resolves outside `PROJECTS_DIR`, such as a symlink.
+pub fn project_path(project_name: &str) -> Result<PathBuf, String> {
+    let key = project_name.to_lowercase().replace(' ', "-");
+    if key.is_empty() {
+        return Err("No project selected".to_string());
+    }
+    if key.starts_with('.') || key.contains(['/', '\\', '\0']) || key.contains("..") {
+        return Err(format!("Invalid project name: {}", project_name));
+    }
+
+    let config = Config::new().map_err(|e| e.to_string())?;
+    let projects_dir = Path::new(config.get_projects_dir());
+    fs::create_dir_all(projects_dir).map_err(|e| e.to_string())?;
+    let projects_dir = projects_dir.canonicalize().map_err(|e| e.to_string())?;
+
+    let project_path = projects_dir.join(key);
+    match project_path.canonicalize() {
+        Ok(canonical) if canonical.starts_with(&projects_dir) => Ok(canonical),
+        Ok(_) => Err(format!("Invalid project name: {}", project_name)),
+        Err(_) => Ok(project_path),
+    }
+}
+
+/// Writes `contents` through a temp file and a rename, so a crash mid-write never
+/// leaves a half-written file behind. `project_root` must be canonical; writes that
+/// would land outside it, or go through a symlink, are refused.
+pub fn write_atomically(project_root: &Path, file_path: &Path, contents: &str) -> Result<(), String> {
+    let parent = file_path.parent().ok_or_else(|| format!("Invalid file path: {}", file_path.display()))?;
+
+    // Symlinks inside the project could still point outside of it, so the directories
+    // that already exist are checked before any missing ones are created under them.
+    let existing_ancestor = parent.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| format!("Invalid file path: {}", file_path.display()))?;
+    let canonical_ancestor = existing_ancestor.canonicalize().map_err(|e| e.to_string())?;
+    if !canonical_ancestor.starts_with(project_root) {
+        return Err(format!("Refusing to write outside the project: {}", file_path.display()));
+    }
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+
+    let canonical_parent = parent.canonicalize().map_err(|e| e.to_string())?;
+    if !canonical_parent.starts_with(project_root) {
+        return Err(format!("Refusing to write outside the project: {}", file_path.display()));
+    }
+
+    let file_name = file_path.file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", file_path.display()))?
+        .to_string_lossy();
+    let target = canonical_parent.join(file_name.as_ref());
+    if target.is_symlink() {
+        return Err(format!("Refusing to write through a symlink: {}", file_path.display()));
+    }
+
+    let counter = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp_path = canonical_parent.join(format!(".{}.{}.{}.tmp", file_name, std::process::id(), counter));
+    fs::write(&temp_path, contents).map_err(|e| e.to_string())?;
+    // The rename replaces the file, so it would lose an executable bit otherwise.
+    if let Ok(metadata) = fs::metadata(&target) {
+        if let Err(e) = fs::set_permissions(&temp_path, metadata.permissions()) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.to_string());
+        }
+    }
+    fs::rename(&temp_path, &target).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        e.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::write_atomically;
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_the_permissions_of_the_file_it_replaces() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+        let script = root.join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        write_atomically(&root, &script, "#!/bin/sh\necho hi\n").unwrap();
+        assert_eq!(fs::read_to_string(&script).unwrap(), "#!/bin/sh\necho hi\n");
+        assert_eq!(fs::metadata(&script).unwrap().permissions().mode() & 0o777, 0o755);
+    }
+}
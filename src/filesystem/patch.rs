@@ -0,0 +1,470 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::Serialize;
+use similar::TextDiff;
+
+use crate::agents::coder::parser::{parse_file_header, strip_outer_tildes, validate_path};
+use crate::filesystem::write_atomically;
+
+/// Below this similarity a fuzzy match is treated as "not found" rather than guessed.
+const FUZZY_THRESHOLD: f32 = 0.85;
+
+/// One replacement inside a file. An empty `search` creates the file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hunk {
+    pub search: String,
+    pub replace: String,
+    /// 1-based line from a unified diff `@@` header, used to pick between repeated matches.
+    pub line_hint: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileEdit {
+    pub file: String,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedPatch {
+    pub file: String,
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedHunk {
+    pub file: String,
+    pub search: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PatchOutcome {
+    pub applied: Vec<AppliedPatch>,
+    pub failed: Vec<FailedHunk>,
+}
+
+/// Parses edits in either of the two formats the edit prompts allow, mixed freely:
+///
+/// File: `src/main.rs`:
+/// <<<<<<< SEARCH
+/// old lines
+/// =======
+/// new lines
+/// >>>>>>> REPLACE
+///
+/// or a unified diff (`--- a/file`, `+++ b/file`, `@@ ... @@` hunks).
+pub fn parse_edits(response: &str) -> Result<Vec<FileEdit>, String> {
+    let lines: Vec<&str> = response.trim().lines().collect();
+    let lines = strip_outer_tildes(&lines);
+
+    let mut edits: BTreeMap<String, Vec<Hunk>> = BTreeMap::new();
+    let mut order: Vec<String> = vec![];
+    let mut current_file: Option<String> = None;
+
+    let mut push = |file: &str, hunk: Hunk, edits: &mut BTreeMap<String, Vec<Hunk>>| {
+        if !edits.contains_key(file) {
+            order.push(file.to_string());
+        }
+        edits.entry(file.to_string()).or_default().push(hunk);
+    };
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+
+        if let Some(file) = parse_file_header(line) {
+            validate_path(&file)?;
+            current_file = Some(file);
+            i += 1;
+            continue;
+        }
+
+        if line.trim_start().starts_with("<<<<<<<") {
+            let file = current_file.clone().ok_or("SEARCH block without a preceding `File:` header")?;
+            let (hunk, next) = parse_search_replace(lines, i + 1)?;
+            push(&file, hunk, &mut edits);
+            i = next;
+            continue;
+        }
+
+        if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ ")) {
+            let file = diff_path(&lines[i + 1][4..]).or_else(|| diff_path(&line[4..]))
+                .ok_or("Unified diff without a file name")?;
+            validate_path(&file)?;
+            current_file = Some(file);
+            i += 2;
+            continue;
+        }
+
+        if line.starts_with("@@") {
+            let file = current_file.clone().ok_or("Diff hunk without a file header")?;
+            let (hunk, next) = parse_unified_hunk(lines, i);
+            push(&file, hunk, &mut edits);
+            i = next;
+            continue;
+        }
+
+        i += 1;
+    }
+
+    if edits.is_empty() {
+        return Err("No edits found in the response".to_string());
+    }
+    Ok(order.into_iter()
+        .map(|file| FileEdit { hunks: edits.remove(&file).unwrap_or_default(), file })
+        .collect())
+}
+
+fn parse_search_replace(lines: &[&str], start: usize) -> Result<(Hunk, usize), String> {
+    let mut search = vec![];
+    let mut replace = vec![];
+    let mut in_replace = false;
+
+    for (offset, line) in lines[start..].iter().enumerate() {
+        let trimmed = line.trim();
+        if !in_replace && trimmed.starts_with("=======") && trimmed.chars().all(|c| c == '=') {
+            in_replace = true;
+        } else if in_replace && trimmed.starts_with(">>>>>>>") {
+            let hunk = Hunk { search: search.join("\n"), replace: replace.join("\n"), line_hint: None };
+            return Ok((hunk, start + offset + 1));
+        } else if in_replace {
+            replace.push(*line);
+        } else {
+            search.push(*line);
+        }
+    }
+    Err("Unterminated SEARCH/REPLACE block".to_string())
+}
+
+/// The old and new line counts of a hunk header, `@@ -12,5 +12,6 @@` -> (5, 6). A
+/// missing count means one line, as in `@@ -3 +3 @@`.
+fn hunk_counts(header: &str) -> Option<(usize, usize)> {
+    let count = |prefix: char| -> Option<usize> {
+        let range = header.split_whitespace().find_map(|part| part.strip_prefix(prefix))?;
+        match range.split_once(',') {
+            Some((_, count)) => count.parse().ok(),
+            None => range.parse::<usize>().ok().map(|_| 1),
+        }
+    };
+    Some((count('-')?, count('+')?))
+}
+
+fn parse_unified_hunk(lines: &[&str], start: usize) -> (Hunk, usize) {
+    // @@ -12,5 +12,6 @@
+    let line_hint = lines[start].split_whitespace()
+        .find(|part| part.starts_with('-'))
+        .and_then(|part| part[1..].split(',').next())
+        .and_then(|line| line.parse::<usize>().ok());
+    // The counts say where the hunk ends, so a removed `-- comment` line, which reads
+    // `--- comment`, isn't taken for the next file's header. Headers without them
+    // end at the first line that can't be part of a hunk.
+    let mut remaining = hunk_counts(lines[start]);
+
+    let mut search = vec![];
+    let mut replace = vec![];
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i];
+        if remaining == Some((0, 0)) {
+            break;
+        }
+        let is_file_header = line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "));
+        if line.starts_with("@@")
+            || (remaining.is_none() && is_file_header)
+            || line.trim_start().starts_with("```")
+            || parse_file_header(line).is_some()
+        {
+            break;
+        }
+        let (old, new) = match line.chars().next() {
+            Some(' ') => {
+                search.push(&line[1..]);
+                replace.push(&line[1..]);
+                (1, 1)
+            }
+            Some('-') => {
+                search.push(&line[1..]);
+                (1, 0)
+            }
+            Some('+') => {
+                replace.push(&line[1..]);
+                (0, 1)
+            }
+            Some('\\') => (0, 0), // "\ No newline at end of file"
+            None => {
+                search.push("");
+                replace.push("");
+                (1, 1)
+            }
+            Some(_) => break,
+        };
+        if let Some((old_left, new_left)) = remaining.as_mut() {
+            *old_left = old_left.saturating_sub(old);
+            *new_left = new_left.saturating_sub(new);
+        }
+        i += 1;
+    }
+
+    let hunk = Hunk { search: search.join("\n"), replace: replace.join("\n"), line_hint };
+    (hunk, i)
+}
+
+fn diff_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" || path.is_empty() {
+        return None;
+    }
+    let path = path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Applies every edit under `project_path`. Hunks that cannot be placed are collected
+/// instead of aborting, so the caller can hand them back to the model; the rest of
+/// the file's hunks still apply.
+pub fn apply_edits(project_path: &Path, edits: &[FileEdit]) -> Result<PatchOutcome, String> {
+    fs::create_dir_all(project_path).map_err(|e| e.to_string())?;
+    let project_root = project_path.canonicalize().map_err(|e| e.to_string())?;
+
+    let mut outcome = PatchOutcome::default();
+    for edit in edits {
+        validate_path(&edit.file)?;
+        let file_path = project_path.join(&edit.file);
+        // Only a missing file may be created from scratch; one that can't be read
+        // (permissions, not UTF-8) must not be overwritten with the hunks alone.
+        let original = match fs::read_to_string(&file_path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => {
+                outcome.failed.extend(edit.hunks.iter().map(|hunk| FailedHunk {
+                    file: edit.file.clone(),
+                    search: hunk.search.clone(),
+                    reason: format!("could not read the file: {}", e),
+                }));
+                continue;
+            }
+        };
+
+        let mut content = original.clone().unwrap_or_default();
+        // Line hints refer to the file before any hunk; the ones applied so far have
+        // moved the lines below them.
+        let mut shift: isize = 0;
+        for hunk in &edit.hunks {
+            let line_hint = hunk.line_hint.map(|line| line.saturating_add_signed(shift).max(1));
+            match apply_hunk(&content, original.is_some(), hunk, line_hint) {
+                Ok(updated) => {
+                    shift += updated.lines().count() as isize - content.lines().count() as isize;
+                    content = updated;
+                }
+                Err(reason) => outcome.failed.push(FailedHunk {
+                    file: edit.file.clone(),
+                    search: hunk.search.clone(),
+                    reason,
+                }),
+            }
+        }
+
+        let before = original.unwrap_or_default();
+        if content == before {
+            continue;
+        }
+        write_atomically(&project_root, &file_path, &content)?;
+
+        let diff = TextDiff::from_lines(&before, &content)
+            .unified_diff()
+            .header(&format!("a/{}", edit.file), &format!("b/{}", edit.file))
+            .to_string();
+        outcome.applied.push(AppliedPatch { file: edit.file.clone(), diff });
+    }
+    Ok(outcome)
+}
+
+/// `line_hint` replaces the hunk's own, once earlier hunks are accounted for.
+fn apply_hunk(content: &str, exists: bool, hunk: &Hunk, line_hint: Option<usize>) -> Result<String, String> {
+    if hunk.search.trim().is_empty() {
+        if exists && !content.trim().is_empty() {
+            return Err("empty SEARCH section for a file that already has content".to_string());
+        }
+        return Ok(ensure_trailing_newline(&hunk.replace));
+    }
+    if !exists {
+        return Err("file does not exist".to_string());
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let search: Vec<&str> = trim_blank_edges(hunk.search.lines().collect());
+    let replace: Vec<&str> = trim_blank_edges(hunk.replace.lines().collect());
+    if search.is_empty() {
+        return Err("SEARCH section only contains blank lines".to_string());
+    }
+
+    let (start, reindent) = find_exact(&lines, &search, line_hint)?
+        .map(|start| (start, false))
+        .or(find_ignoring_whitespace(&lines, &search, line_hint)?.map(|start| (start, true)))
+        .or_else(|| find_fuzzy(&lines, &search).map(|start| (start, true)))
+        .ok_or_else(|| "SEARCH section not found in the file".to_string())?;
+
+    let replacement: Vec<String> = if reindent {
+        reindent_lines(&search, &lines[start..start + search.len()], &replace)
+    } else {
+        replace.iter().map(|line| line.to_string()).collect()
+    };
+
+    let mut result: Vec<String> = lines[..start].iter().map(|line| line.to_string()).collect();
+    result.extend(replacement);
+    result.extend(lines[start + search.len()..].iter().map(|line| line.to_string()));
+
+    // `lines()` drops the `\r` of CRLF endings too; the file keeps the ones it had.
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut updated = result.join(newline);
+    if content.ends_with('\n') {
+        updated.push_str(newline);
+    }
+    Ok(updated)
+}
+
+fn trim_blank_edges(mut lines: Vec<&str>) -> Vec<&str> {
+    while lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn ensure_trailing_newline(text: &str) -> String {
+    if text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+fn find_matches<F: Fn(&str, &str) -> bool>(lines: &[&str], search: &[&str], eq: F) -> Vec<usize> {
+    if search.len() > lines.len() {
+        return vec![];
+    }
+    (0..=lines.len() - search.len())
+        .filter(|&start| search.iter().enumerate().all(|(offset, line)| eq(lines[start + offset], line)))
+        .collect()
+}
+
+/// Several matches are only acceptable when a line hint singles one out.
+fn pick_match(matches: Vec<usize>, line_hint: Option<usize>) -> Result<Option<usize>, String> {
+    match (matches.len(), line_hint) {
+        (0, _) => Ok(None),
+        (1, _) => Ok(Some(matches[0])),
+        (count, Some(hint)) => {
+            let hint = hint.saturating_sub(1);
+            let closest = matches.iter().copied().min_by_key(|start| start.abs_diff(hint));
+            closest.map(Some).ok_or_else(|| format!("SEARCH section matches {} places", count))
+        }
+        (count, None) => Err(format!("SEARCH section matches {} places, add more context to make it unique", count)),
+    }
+}
+
+fn find_exact(lines: &[&str], search: &[&str], line_hint: Option<usize>) -> Result<Option<usize>, String> {
+    pick_match(find_matches(lines, search, |a, b| a == b), line_hint)
+}
+
+fn find_ignoring_whitespace(lines: &[&str], search: &[&str], line_hint: Option<usize>) -> Result<Option<usize>, String> {
+    pick_match(find_matches(lines, search, |a, b| a.trim() == b.trim()), line_hint)
+}
+
+/// Last resort: the window of the same length that reads most like the SEARCH text,
+/// if it clears the threshold and no other window is about as close.
+fn find_fuzzy(lines: &[&str], search: &[&str]) -> Option<usize> {
+    if search.len() > lines.len() {
+        return None;
+    }
+    let needle = search.iter().map(|line| line.trim()).collect::<Vec<_>>().join("\n");
+
+    let mut best: Option<(usize, f32)> = None;
+    let mut runner_up = 0.0;
+    for start in 0..=lines.len() - search.len() {
+        let window = lines[start..start + search.len()].iter().map(|line| line.trim()).collect::<Vec<_>>().join("\n");
+        let ratio = TextDiff::from_chars(needle.as_str(), window.as_str()).ratio();
+        match best {
+            Some((_, best_ratio)) if ratio <= best_ratio => runner_up = f32::max(runner_up, ratio),
+            _ => {
+                if let Some((_, best_ratio)) = best {
+                    runner_up = f32::max(runner_up, best_ratio);
+                }
+                best = Some((start, ratio));
+            }
+        }
+    }
+
+    best.filter(|(_, ratio)| *ratio >= FUZZY_THRESHOLD && *ratio - runner_up > 0.02)
+        .map(|(start, _)| start)
+}
+
+/// Shifts the replacement by the indentation difference between what the model
+/// searched for and what is actually in the file.
+fn reindent_lines(search: &[&str], matched: &[&str], replace: &[&str]) -> Vec<String> {
+    let indent = |line: &str| line[..line.len() - line.trim_start().len()].to_string();
+    let searched = search.iter().find(|line| !line.trim().is_empty()).map(|line| indent(line)).unwrap_or_default();
+    let actual = matched.iter().find(|line| !line.trim().is_empty()).map(|line| indent(line)).unwrap_or_default();
+
+    replace.iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                String::new()
+            } else if let Some(rest) = line.strip_prefix(searched.as_str()) {
+                format!("{}{}", actual, rest)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{apply_edits, apply_hunk, parse_edits};
+
+    #[test]
+    fn removed_sql_comments_do_not_end_a_hunk() {
+        let response = "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,3 +1,2 @@\n--- drop the old table\n-DROP TABLE users;\n CREATE TABLE users (id INTEGER);\n";
+        let edits = parse_edits(response).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].hunks[0].search, "-- drop the old table\nDROP TABLE users;\nCREATE TABLE users (id INTEGER);");
+        assert_eq!(edits[0].hunks[0].replace, "CREATE TABLE users (id INTEGER);");
+    }
+
+    #[test]
+    fn hunk_counts_end_a_hunk_before_the_next_file() {
+        let response = "--- a/a.lua\n+++ b/a.lua\n@@ -1 +1 @@\n--- old\n+-- new\n--- a/b.lua\n+++ b/b.lua\n@@ -1 +1 @@\n-x = 1\n+x = 2\n";
+        let edits = parse_edits(response).unwrap();
+        assert_eq!(edits.iter().map(|edit| edit.file.as_str()).collect::<Vec<_>>(), ["a.lua", "b.lua"]);
+        assert_eq!(edits[0].hunks[0].replace, "-- new");
+        assert_eq!(edits[1].hunks[0].search, "x = 1");
+    }
+
+    #[test]
+    fn later_hunks_in_a_file_use_shifted_line_hints() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("main.py"), "x = 0\nprint(x)\ny = 0\nprint(x)\n").unwrap();
+        // The second hunk's line hint is from before the first one added two lines,
+        // and `print(x)` is repeated, so only the shifted hint picks the right one.
+        let response = "--- a/main.py\n+++ b/main.py\n@@ -1,1 +1,3 @@\n x = 0\n+# one\n+# two\n@@ -4,1 +6,1 @@\n-print(x)\n+print(y)\n";
+        let edits = parse_edits(response).unwrap();
+        assert_eq!(edits[0].hunks.len(), 2);
+
+        let outcome = apply_edits(root.path(), &edits).unwrap();
+        assert!(outcome.failed.is_empty());
+        assert_eq!(fs::read_to_string(root.path().join("main.py")).unwrap(), "x = 0\n# one\n# two\nprint(x)\ny = 0\nprint(y)\n");
+    }
+
+    #[test]
+    fn crlf_files_keep_their_line_endings() {
+        let edits = parse_edits("File: `notes.txt`:\n<<<<<<< SEARCH\nsecond\n=======\n2nd\n>>>>>>> REPLACE\n").unwrap();
+        let updated = apply_hunk("first\r\nsecond\r\nthird\r\n", true, &edits[0].hunks[0], None).unwrap();
+        assert_eq!(updated, "first\r\n2nd\r\nthird\r\n");
+    }
+}
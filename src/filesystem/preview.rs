@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::filesystem::project_path;
+use crate::services::deploy::build_output;
+
+/// Reloads the page whenever the watcher reports a change in the project.
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+  var events = new EventSource("/api/preview/events?project_name={project}");
+  events.addEventListener("change", function () { location.reload(); });
+})();
+</script>
+"#;
+
+pub struct PreviewFile {
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+/// The directory served for a project: its build output when it has one, the
+/// project itself otherwise.
+fn preview_root(project_name: &str) -> Result<PathBuf, String> {
+    if let Ok(output) = build_output(project_name) {
+        return output.canonicalize().map_err(|e| e.to_string());
+    }
+    project_path(project_name)?.canonicalize().map_err(|_| format!("Project not found: {}", project_name))
+}
+
+/// A file of the project's site. Directories serve their `index.html`, or a listing
+/// when there is none; HTML gets the live-reload script.
+pub fn read_preview_file(project_name: &str, file: &Path) -> Result<PreviewFile, String> {
+    let root = preview_root(project_name)?;
+    let path = root.join(file).canonicalize().map_err(|_| format!("File not found: {}", file.display()))?;
+    // Symlinks must not lead out of the project.
+    if !path.starts_with(&root) {
+        return Err(format!("Path is outside the project: {}", file.display()));
+    }
+
+    let project = project_name.to_lowercase().replace(' ', "-");
+    if path.is_dir() {
+        let index = path.join("index.html");
+        if !index.is_file() {
+            let listing = directory_listing(&project, &root, &path)?;
+            return Ok(PreviewFile { content_type: "text/html; charset=utf-8", body: inject_live_reload(&listing, &project).into_bytes() });
+        }
+        return serve(&index, &project);
+    }
+    serve(&path, &project)
+}
+
+fn serve(path: &Path, project: &str) -> Result<PreviewFile, String> {
+    let body = fs::read(path).map_err(|e| e.to_string())?;
+    let content_type = content_type(path, &body);
+    if content_type.starts_with("text/html") {
+        let html = String::from_utf8_lossy(&body);
+        return Ok(PreviewFile { content_type, body: inject_live_reload(&html, project).into_bytes() });
+    }
+    Ok(PreviewFile { content_type, body })
+}
+
+/// MIME type by extension. Unknown files are served as plain text when they are
+/// text, so they show in the browser instead of downloading.
+pub fn content_type(path: &Path, body: &[u8]) -> &'static str {
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" | "cjs" => "text/javascript; charset=utf-8",
+        "json" | "map" | "webmanifest" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ if !body.iter().take(8192).any(|&byte| byte == 0) && std::str::from_utf8(body).is_ok() => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Adds the live-reload script before `</body>`, or at the end when there is none.
+fn inject_live_reload(html: &str, project: &str) -> String {
+    let project: String = url::form_urlencoded::byte_serialize(project.as_bytes()).collect();
+    let script = LIVE_RELOAD_SCRIPT.replace("{project}", &project);
+    let lowercase = html.to_ascii_lowercase();
+    match lowercase.rfind("</body>").or_else(|| lowercase.rfind("</html>")) {
+        Some(position) => format!("{}{}{}", &html[..position], script, &html[position..]),
+        None => format!("{}{}", html, script),
+    }
+}
+
+fn directory_listing(project: &str, root: &Path, directory: &Path) -> Result<String, String> {
+    let relative = directory.strip_prefix(root).map_err(|e| e.to_string())?.to_string_lossy().replace('\\', "/");
+    // Absolute links, so they work with or without a trailing slash in the URL.
+    let base = if relative.is_empty() { format!("/preview/{}/", project) } else { format!("/preview/{}/{}/", project, relative) };
+    let mut entries: Vec<(String, bool)> = fs::read_dir(directory).map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path().is_dir()))
+        .filter(|(name, _)| !name.starts_with('.'))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut items = String::new();
+    for (name, is_dir) in entries {
+        let name = escape_html(&name);
+        let suffix = if is_dir { "/" } else { "" };
+        items.push_str(&format!("<li><a href=\"{0}{1}{2}\">{1}{2}</a></li>\n", escape_html(&base), name, suffix));
+    }
+    let title = escape_html(&format!("/{}", relative));
+    Ok(format!("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body>\n<h1>{0}</h1>\n<ul>\n{1}</ul>\n</body>\n</html>\n", title, items))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::agents::coder::parser::validate_path;
+use crate::filesystem::watcher::content_hash;
+use crate::filesystem::{project_path, write_atomically};
+
+/// Directories that are listed but never expanded; they can hold tens of thousands of files.
+const COLLAPSED_DIRS: &[&str] = &[".git", "node_modules", "target", "__pycache__", "venv", ".venv"];
+
+/// Stop walking after this many entries so a huge project can't stall the request.
+const MAX_TREE_ENTRIES: usize = 5000;
+
+/// Largest file the editor will open.
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    /// Path relative to the project root, with `/` separators.
+    pub path: String,
+    /// `directory`, `file` or `binary`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<TreeNode>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectTree {
+    pub root: TreeNode,
+    /// Set when the walk stopped at `MAX_TREE_ENTRIES`.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectFile {
+    pub path: String,
+    pub content: String,
+    pub size: u64,
+    /// Same hash as the watcher's `file-changed` events.
+    pub hash: String,
+}
+
+fn project_root(project_name: &str) -> Result<PathBuf, String> {
+    let project_path = project_path(project_name)?;
+    if !project_path.is_dir() {
+        return Err(format!("Project not found: {}", project_name));
+    }
+    Ok(project_path)
+}
+
+/// Resolves `file` inside the project, refusing absolute paths, `..` and symlinks
+/// that lead out of the project directory.
+fn resolve(project_root: &Path, file: &str) -> Result<PathBuf, String> {
+    validate_path(file)?;
+    let path = project_root.join(file);
+    if let Ok(canonical) = path.canonicalize() {
+        if !canonical.starts_with(project_root) {
+            return Err(format!("Path is outside the project: {}", file));
+        }
+    }
+    Ok(path)
+}
+
+fn is_binary(path: &Path) -> bool {
+    let mut head = [0; 8192];
+    match fs::File::open(path).and_then(|mut file| file.read(&mut head)) {
+        Ok(read) => head[..read].contains(&0) || std::str::from_utf8(&head[..read]).is_err_and(|e| e.error_len().is_some()),
+        Err(_) => true,
+    }
+}
+
+/// The nested file tree of a project. Symlinks are skipped; directories such as
+/// `.git` and `node_modules` are listed without their contents.
+pub fn project_tree(project_name: &str) -> Result<ProjectTree, String> {
+    let root_path = project_root(project_name)?;
+    let mut remaining = MAX_TREE_ENTRIES;
+    let children = read_tree(&root_path, "", &mut remaining)?;
+    let size = children.iter().map(|child| child.size).sum();
+    let root = TreeNode {
+        name: project_name.to_lowercase().replace(' ', "-"),
+        path: String::new(),
+        kind: "directory",
+        size,
+        children: Some(children),
+    };
+    Ok(ProjectTree { root, truncated: remaining == 0 })
+}
+
+fn read_tree(directory: &Path, prefix: &str, remaining: &mut usize) -> Result<Vec<TreeNode>, String> {
+    let mut entries: Vec<_> = fs::read_dir(directory).map_err(|e| e.to_string())?.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut nodes = vec![];
+    for entry in entries {
+        if *remaining == 0 {
+            break;
+        }
+        let Ok(file_type) = entry.file_type() else { continue };
+        if file_type.is_symlink() {
+            continue;
+        }
+        *remaining -= 1;
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+        if file_type.is_dir() {
+            let children = if COLLAPSED_DIRS.contains(&name.as_str()) {
+                None
+            } else {
+                Some(read_tree(&entry.path(), &path, remaining).unwrap_or_default())
+            };
+            let size = children.iter().flatten().map(|child| child.size).sum();
+            nodes.push(TreeNode { name, path, kind: "directory", size, children });
+        } else if file_type.is_file() {
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            let kind = if is_binary(&entry.path()) { "binary" } else { "file" };
+            nodes.push(TreeNode { name, path, kind, size, children: None });
+        }
+    }
+
+    // Directories first, like the editor's sidebar.
+    nodes.sort_by_key(|node| node.kind != "directory");
+    Ok(nodes)
+}
+
+/// Reads one text file of the project for the editor.
+pub fn read_project_file(project_name: &str, file: &str) -> Result<ProjectFile, String> {
+    let root = project_root(project_name)?;
+    let path = resolve(&root, file)?;
+    let metadata = fs::symlink_metadata(&path).map_err(|_| format!("File not found: {}", file))?;
+    if !metadata.is_file() {
+        return Err(format!("Not a regular file: {}", file));
+    }
+    if metadata.len() > MAX_FILE_SIZE {
+        return Err(format!("File is too large to open ({} KB): {}", metadata.len() / 1024, file));
+    }
+    let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+    if bytes.iter().take(8192).any(|&byte| byte == 0) {
+        return Err(format!("Binary files can't be opened in the editor: {}", file));
+    }
+    let hash = content_hash(&bytes);
+    let content = String::from_utf8(bytes).map_err(|_| format!("Binary files can't be opened in the editor: {}", file))?;
+    Ok(ProjectFile { path: file.to_string(), content, size: metadata.len(), hash })
+}
+
+/// Saves a file edited in the editor, creating parent directories as needed.
+pub fn write_project_file(project_name: &str, file: &str, content: &str) -> Result<ProjectFile, String> {
+    let root = project_root(project_name)?;
+    let path = resolve(&root, file)?;
+    if content.len() as u64 > MAX_FILE_SIZE {
+        return Err(format!("File is too large to save ({} KB): {}", content.len() / 1024, file));
+    }
+    write_atomically(&root, &path, content)?;
+    Ok(ProjectFile {
+        path: file.to_string(),
+        content: content.to_string(),
+        size: content.len() as u64,
+        hash: content_hash(content.as_bytes()),
+    })
+}
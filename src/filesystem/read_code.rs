@@ -0,0 +1,256 @@
+use std::cmp::Reverse;
+use std::fmt::Write;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use ignore::WalkBuilder;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tiktoken::bpe::CoreBpe;
+use tiktoken::encoding::{Dict, Encoding};
+
+use crate::config::Config;
+use crate::filesystem::project_path;
+
+/// Project-specific ignore rules, in `.gitignore` syntax.
+const IGNORE_FILE: &str = ".devikaignore";
+
+/// Dependency and cache directories are skipped even without a `.gitignore`. Names
+/// like `build` or `dist` are often source packages too, so those are left to the
+/// ignore files.
+const SKIPPED_DIRS: &[&str] = &[
+    "node_modules", "target", "vendor", "venv", "__pycache__", "coverage", "bower_components",
+];
+
+/// Generated lockfiles are large and say nothing about the code.
+const SKIPPED_FILES: &[&str] = &[
+    "package-lock.json", "yarn.lock", "pnpm-lock.yaml", "Cargo.lock", "poetry.lock", "Pipfile.lock", "composer.lock",
+    "Gemfile.lock", "go.sum", "bun.lockb",
+];
+
+const ENTRY_POINTS: &[&str] = &[
+    "Cargo.toml", "package.json", "pyproject.toml", "requirements.txt", "setup.py", "go.mod", "Makefile", "Dockerfile",
+    "README.md", "lib.rs", "main.rs", "main.py", "app.py", "main.go", "index.html",
+];
+
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "jsx", "ts", "tsx", "mjs", "cjs", "go", "java", "kt", "c", "h", "cpp", "hpp", "cc", "cs", "rb", "php",
+    "swift", "scala", "lua", "sh", "svelte", "vue", "sql",
+];
+
+const CONFIG_EXTENSIONS: &[&str] = &["toml", "json", "yaml", "yml", "ini", "cfg", "html", "css", "scss", "xml"];
+
+/// Share of the token budget for files in full; the rest lists what was left out.
+const FULL_FILES_SHARE: f64 = 0.9;
+
+const SUMMARY_DEFINITIONS: usize = 6;
+const SUMMARY_LINE_CHARS: usize = 100;
+
+static TOKENIZER: Lazy<CoreBpe> = Lazy::new(|| {
+    let encoding = Encoding::get_by_dict(&Dict::Cl100kBase).expect("cl100k_base encoding");
+    CoreBpe::new(encoding.merging_ranks, encoding.special_tokens, encoding.dict.get_regex_pattern()).expect("cl100k_base tokenizer")
+});
+
+fn count_tokens(text: &str) -> usize {
+    TOKENIZER.encode_native(text).0.len()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeEntry {
+    /// Path relative to the project root, with `/` separators.
+    pub filename: String,
+    pub code: String,
+}
+
+/// A file that is too large to send, still mentioned so the model knows it exists.
+struct LargeFile {
+    filename: String,
+    size: u64,
+}
+
+pub struct ReadCode {
+    directory_path: Option<PathBuf>,
+    max_tokens: usize,
+    max_file_size: u64,
+}
+
+impl ReadCode {
+    pub fn new(project_name: &str) -> Self {
+        let config = Config::new().unwrap();
+        // An invalid project name reads as a project without files.
+        let directory_path = project_path(project_name).ok();
+        Self {
+            directory_path,
+            max_tokens: config.get_read_code_max_tokens(),
+            max_file_size: config.get_read_code_max_file_kb() * 1024,
+        }
+    }
+
+    /// The project's text files, most relevant first. Anything matched by `.gitignore`
+    /// or `.devikaignore`, hidden files, binaries and files over the size limit are left out.
+    pub fn read_directory(&self) -> Vec<CodeEntry> {
+        self.scan().0
+    }
+
+    fn scan(&self) -> (Vec<CodeEntry>, Vec<LargeFile>) {
+        let Some(directory_path) = &self.directory_path else { return (vec![], vec![]) };
+        let walker = WalkBuilder::new(directory_path)
+            .require_git(false)
+            .add_custom_ignore_filename(IGNORE_FILE)
+            .filter_entry(|entry| {
+                let name = entry.file_name().to_string_lossy();
+                let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+                if is_dir {
+                    !SKIPPED_DIRS.contains(&name.as_ref())
+                } else {
+                    !SKIPPED_FILES.contains(&name.as_ref()) && name != IGNORE_FILE
+                }
+            })
+            .build();
+
+        let mut files = vec![];
+        let mut large_files = vec![];
+        for entry in walker.flatten() {
+            if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(directory_path) else { continue };
+            let filename = relative.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let Ok(metadata) = entry.metadata() else { continue };
+
+            if metadata.len() > self.max_file_size {
+                if !looks_binary(entry.path()) {
+                    large_files.push(LargeFile { filename, size: metadata.len() });
+                }
+                continue;
+            }
+            let Ok(bytes) = fs::read(entry.path()) else { continue };
+            if bytes.iter().take(8192).any(|&byte| byte == 0) {
+                continue;
+            }
+            let Ok(code) = String::from_utf8(bytes) else { continue };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((CodeEntry { filename, code }, modified));
+        }
+
+        files.sort_by_cached_key(|(entry, modified)| {
+            (relevance(&entry.filename), entry.filename.matches('/').count(), Reverse(*modified), entry.filename.clone())
+        });
+        (files.into_iter().map(|(entry, _)| entry).collect(), large_files)
+    }
+
+    /// The project as Markdown for the prompts, capped at `[READ_CODE] MAX_TOKENS`.
+    /// Files that don't fit are summarised at the end with their size and top-level
+    /// definitions instead.
+    pub fn code_set_to_markdown(&self) -> String {
+        let (files, large_files) = self.scan();
+        let full_budget = (self.max_tokens as f64 * FULL_FILES_SHARE) as usize;
+
+        let mut markdown = String::new();
+        let mut used = 0;
+        let mut omitted = vec![];
+        for code in files {
+            let block = format!("### {}:\n\n```\n{}\n```\n\n---\n\n", code.filename, code.code);
+            let tokens = count_tokens(&block);
+            if used + tokens <= full_budget {
+                markdown += &block;
+                used += tokens;
+            } else {
+                omitted.push(summarize(&code, tokens));
+            }
+        }
+        for file in large_files {
+            omitted.push(format!("- `{}`: {} KB, too large to include", file.filename, file.size.div_ceil(1024)));
+        }
+
+        if omitted.is_empty() {
+            return markdown;
+        }
+        let heading = "### Omitted files:\n\nThese files were left out to stay within the context budget.\n\n";
+        markdown += heading;
+        used += count_tokens(heading);
+        let total = omitted.len();
+        for (index, summary) in omitted.into_iter().enumerate() {
+            let tokens = count_tokens(&summary) + 1;
+            if used + tokens > self.max_tokens {
+                let _ = writeln!(markdown, "- ...and {} more", total - index);
+                break;
+            }
+            markdown += &summary;
+            markdown.push('\n');
+            used += tokens;
+        }
+        markdown
+    }
+}
+
+/// Lower ranks first: manifests and entry points, source code, configuration and
+/// markup, documentation, tests, then anything else.
+fn relevance(filename: &str) -> u8 {
+    let name = filename.rsplit('/').next().unwrap_or(filename);
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    let is_test = filename.split('/').any(|part| matches!(part, "test" | "tests" | "__tests__" | "spec"))
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec");
+
+    if ENTRY_POINTS.contains(&name) || (matches!(stem, "main" | "index" | "app") && SOURCE_EXTENSIONS.contains(&extension)) {
+        0
+    } else if is_test {
+        4
+    } else if SOURCE_EXTENSIONS.contains(&extension) {
+        1
+    } else if CONFIG_EXTENSIONS.contains(&extension) {
+        2
+    } else if matches!(extension, "md" | "rst" | "txt") {
+        3
+    } else {
+        5
+    }
+}
+
+/// One bullet for an omitted file: its size and the first few top-level definitions.
+fn summarize(code: &CodeEntry, tokens: usize) -> String {
+    let mut summary = format!("- `{}`: {} lines, ~{} tokens", code.filename, code.code.lines().count(), tokens);
+    let definitions: Vec<String> = code.code.lines()
+        .filter(|line| is_definition(line))
+        .take(SUMMARY_DEFINITIONS)
+        .map(|line| {
+            // Just the signature, not a body that starts on the same line.
+            let line = line.split('{').next().unwrap_or(line).trim_end();
+            if line.chars().count() > SUMMARY_LINE_CHARS {
+                format!("{}...", line.chars().take(SUMMARY_LINE_CHARS).collect::<String>())
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !definitions.is_empty() {
+        let _ = write!(summary, "; defines `{}`", definitions.join("`, `"));
+    }
+    summary
+}
+
+fn is_definition(line: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "fn ", "pub fn ", "async fn ", "pub async fn ", "struct ", "pub struct ", "enum ", "pub enum ", "trait ", "pub trait ",
+        "impl ", "impl<", "class ", "def ", "async def ", "function ", "async function ", "interface ", "export ",
+        "func ", "type ",
+    ];
+    // Only unindented lines, so methods and nested helpers don't crowd out the rest.
+    !line.starts_with(char::is_whitespace) && PREFIXES.iter().any(|prefix| line.starts_with(prefix))
+}
+
+fn looks_binary(path: &Path) -> bool {
+    let mut head = [0; 8192];
+    match fs::File::open(path).and_then(|mut file| file.read(&mut head)) {
+        Ok(read) => head[..read].contains(&0),
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
+
+use crate::agents::control;
+use crate::filesystem::project_path;
+use crate::socket_instance::emit_agent;
+
+/// Changes are collected until the project has been quiet for this long, so a
+/// save that touches a file several times is reported once.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// A project stops being watched once nobody has asked for it for this long and no
+/// agent run is working on it.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const IDLE_CHECK: Duration = Duration::from_secs(60);
+
+/// Changes under these directories are not reported, and they are not watched.
+const IGNORED_DIRS: &[&str] = &[".git", "node_modules", "target", "__pycache__", "venv", ".venv"];
+
+struct ProjectWatcher {
+    watcher: RecommendedWatcher,
+    /// Tells this watcher apart from a later one for the same project.
+    generation: u64,
+    last_used: Instant,
+}
+
+static WATCHERS: Lazy<Mutex<HashMap<String, ProjectWatcher>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static WATCHER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Every reported change, for listeners inside the server such as the live preview.
+static CHANGES: Lazy<broadcast::Sender<FileChange>> = Lazy::new(|| broadcast::channel(256).0);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    /// The project's directory name.
+    pub project: String,
+    pub file: String,
+    pub deleted: bool,
+}
+
+/// Receives the changes of all watched projects from now on.
+pub fn subscribe() -> broadcast::Receiver<FileChange> {
+    CHANGES.subscribe()
+}
+
+fn watcher_key(project_name: &str) -> String {
+    project_name.to_lowercase().replace(' ', "-")
+}
+
+/// SHA-256 of a file's contents, as lowercase hex.
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Starts watching the project's directory, creating it if needed. Changes are sent
+/// to clients as `file-changed` and `file-deleted` events; calling this again for a
+/// project that is already watched keeps it from going idle.
+pub fn watch_project(project_name: &str) -> Result<(), String> {
+    let project_path = project_path(project_name)?;
+    let key = watcher_key(project_name);
+    let mut watchers = WATCHERS.lock().unwrap();
+    if let Some(watched) = watchers.get_mut(&key) {
+        watched.last_used = Instant::now();
+        return Ok(());
+    }
+
+    fs::create_dir_all(&project_path).map_err(|e| e.to_string())?;
+    let root = project_path.canonicalize().map_err(|e| e.to_string())?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(|e| e.to_string())?;
+    watcher.watch(&root, RecursiveMode::NonRecursive).map_err(|e| e.to_string())?;
+    let mut watched_dirs = HashSet::from([root.clone()]);
+    watch_tree(&mut watcher, &root, &mut watched_dirs);
+
+    let generation = WATCHER_GENERATION.fetch_add(1, Ordering::Relaxed);
+    watchers.insert(key.clone(), ProjectWatcher { watcher, generation, last_used: Instant::now() });
+
+    let project_name = project_name.to_string();
+    thread::spawn(move || {
+        pump_events(&project_name, &key, generation, &root, watched_dirs, receiver);
+        // The project directory is gone or the project went idle; a later watch_project starts over.
+        let mut watchers = WATCHERS.lock().unwrap();
+        if watchers.get(&key).is_some_and(|watched| watched.generation == generation) {
+            watchers.remove(&key);
+        }
+    });
+    Ok(())
+}
+
+/// Watches every directory below `directory` that isn't watched yet, one level at a
+/// time so ignored directories and symlinks are never entered. Returns the files
+/// found in the newly watched directories.
+fn watch_tree(watcher: &mut RecommendedWatcher, directory: &Path, watched_dirs: &mut HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else { continue };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else { continue };
+            let path = entry.path();
+            if file_type.is_file() {
+                files.push(path);
+            } else if file_type.is_dir()
+                && !IGNORED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+                && !watched_dirs.contains(&path)
+                && watcher.watch(&path, RecursiveMode::NonRecursive).is_ok()
+            {
+                watched_dirs.insert(path.clone());
+                pending.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// Whether the watcher is still the project's and the project is still active.
+/// A live agent run counts as using the project.
+fn still_active(project_name: &str, key: &str, generation: u64) -> bool {
+    let mut watchers = WATCHERS.lock().unwrap();
+    let Some(watched) = watchers.get_mut(key).filter(|watched| watched.generation == generation) else { return false };
+    if control::current(project_name).is_some() {
+        watched.last_used = Instant::now();
+    }
+    watched.last_used.elapsed() < IDLE_TIMEOUT
+}
+
+fn pump_events(
+    project_name: &str,
+    project: &str,
+    generation: u64,
+    root: &Path,
+    mut watched_dirs: HashSet<PathBuf>,
+    receiver: Receiver<notify::Result<Event>>,
+) {
+    // The last hash sent for each file, so touching a file without changing it stays quiet.
+    let mut hashes: HashMap<PathBuf, String> = HashMap::new();
+
+    loop {
+        let event = match receiver.recv_timeout(IDLE_CHECK) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) if still_active(project_name, project, generation) => continue,
+            Err(_) => return,
+        };
+        let mut pending = BTreeSet::new();
+        collect_paths(event, &mut pending);
+        loop {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(event) => collect_paths(event, &mut pending),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        if !root.exists() || !still_active(project_name, project, generation) {
+            return;
+        }
+        let mut pending: Vec<PathBuf> = pending.into_iter().collect();
+        while let Some(path) = pending.pop() {
+            let Some(file) = relative_path(root, &path) else { continue };
+            match fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.is_file() => {
+                    let Ok(bytes) = fs::read(&path) else { continue };
+                    let hash = content_hash(&bytes);
+                    if hashes.get(&path) == Some(&hash) {
+                        continue;
+                    }
+                    hashes.insert(path.clone(), hash.clone());
+                    emit_agent("file-changed", json!({
+                        "project_name": project_name,
+                        "file": file,
+                        "hash": hash,
+                        "size": metadata.len(),
+                    }));
+                    // Nobody listening is not an error.
+                    let _ = CHANGES.send(FileChange { project: project.to_string(), file, deleted: false });
+                }
+                Ok(metadata) if metadata.is_dir() && !watched_dirs.contains(&path) => {
+                    // A new directory: watch it, and report what was written to it before the watch began.
+                    let mut watchers = WATCHERS.lock().unwrap();
+                    let Some(watched) = watchers.get_mut(project).filter(|watched| watched.generation == generation) else { return };
+                    if watched.watcher.watch(&path, RecursiveMode::NonRecursive).is_ok() {
+                        watched_dirs.insert(path.clone());
+                        pending.extend(watch_tree(&mut watched.watcher, &path, &mut watched_dirs));
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    // A removed directory takes everything below it along.
+                    hashes.retain(|known, _| !known.starts_with(&path));
+                    let removed: Vec<PathBuf> = watched_dirs.iter().filter(|known| known.starts_with(&path)).cloned().collect();
+                    if !removed.is_empty() {
+                        if let Some(watched) = WATCHERS.lock().unwrap().get_mut(project).filter(|watched| watched.generation == generation) {
+                            for directory in &removed {
+                                let _ = watched.watcher.unwatch(directory);
+                                watched_dirs.remove(directory);
+                            }
+                        }
+                    }
+                    emit_agent("file-deleted", json!({"project_name": project_name, "file": file}));
+                    let _ = CHANGES.send(FileChange { project: project.to_string(), file, deleted: true });
+                }
+            }
+        }
+    }
+}
+
+fn collect_paths(event: notify::Result<Event>, pending: &mut BTreeSet<PathBuf>) {
+    if let Ok(event) = event {
+        if !event.kind.is_access() {
+            pending.extend(event.paths);
+        }
+    }
+}
+
+/// The path relative to the project, or `None` for ignored directories and the
+/// temp files `write_atomically` renames into place.
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut parts = vec![];
+    for component in relative.components() {
+        let Component::Normal(part) = component else { return None };
+        let part = part.to_string_lossy();
+        if IGNORED_DIRS.contains(&part.as_ref()) {
+            return None;
+        }
+        parts.push(part);
+    }
+    let name = parts.last()?;
+    if name.starts_with('.') && name.ends_with(".tmp") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
@@ -1,5 +1,9 @@
-use crate::config::Config;
 use serde::{Serialize, Deserialize};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::control::CANCELLED;
+use crate::config::Config;
 
 pub struct Groq {
     client: reqwest::Client,
@@ -13,14 +17,23 @@ impl Groq {
         Self { client: reqwest::Client::new(), key: api_key.to_string() }
     }
 
-    pub fn inference(&self, model_id: &str, prompt: &str) -> String {
-        let req = self.client.post("https://api.groq.com/openai/v1/chat/completions").bearer_auth(self.key.clone()).header("content-type", "application/json").body(format!("{{\"messages\": [{{\"role\":\"user\", \"content\":\"{}\"}}], \"model\":\"{}\"}}", prompt, model_id)).build().unwrap();
-        let bytes = req.body().unwrap().as_bytes().unwrap();
-        let string = String::from_utf8(bytes.to_vec()).unwrap();
-
-        let response = serde_json::from_str::<GroqResponse>(&string).unwrap();
-
-        response.choices.get(0).unwrap().message.content.clone()
+    /// Cancelling `cancel` drops the request, which closes the connection.
+    pub fn inference(&self, model_id: &str, prompt: &str, cancel: &CancellationToken) -> Result<String, String> {
+        let request = self.client.post("https://api.groq.com/openai/v1/chat/completions")
+            .bearer_auth(&self.key)
+            .json(&json!({"messages": [{"role": "user", "content": prompt}], "model": model_id}))
+            .send();
+
+        let response = tokio::runtime::Runtime::new().unwrap().block_on(async {
+            tokio::select! {
+                response = async { request.await?.error_for_status()?.json::<GroqResponse>().await } => response.map_err(|e| e.to_string()),
+                _ = cancel.cancelled() => Err(CANCELLED.to_string()),
+            }
+        })?;
+
+        response.choices.into_iter().next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| "Groq returned no choices".to_string())
     }
 }
 
@@ -3,6 +3,9 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::control::{self, CANCELLED};
 use crate::llm::ollama_client::Ollama;
 use crate::llm::groq_client::Groq;
 
@@ -94,7 +97,7 @@ impl LLM {
 
     fn update_global_token_usage(string: &str, project_name: &str) {
         let token_usage = tiktoken::count_text(&TIKTOKEN_ENC, string);
-        let agent_state = AgentState::new(&Config::new().unwrap().get_sqlite_db());
+        let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
         agent_state.update_token_usage(project_name, token_usage.try_into().unwrap());
 
         let usage: i32 = token_usage.try_into().unwrap();
@@ -103,7 +106,11 @@ impl LLM {
         emit_agent("tokens", serde_json::json!({ "token_usage": total }));
     }
 
-    fn inference(&self, prompt: &str, project_name: &str) -> Result<String, String> {
+    pub fn inference(&self, prompt: &str, project_name: &str) -> Result<String, String> {
+        let cancel = control::cancellation_token(project_name);
+        if cancel.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
         Self::update_global_token_usage(prompt, project_name);
 
         let (model_enum, model_name) = match self.model_enum(self.model_id.as_deref().unwrap_or("")) {
@@ -124,14 +131,21 @@ impl LLM {
             let result = Arc::clone(&result);
             let model_name = model_name.clone();
             let prompt = prompt.to_string();
+            let cancel = cancel.clone();
 
             thread::spawn(move || {
-                let inference_result = model.inference(&model_name, &prompt);
+                let inference_result = model.inference(&model_name, &prompt, &cancel);
                 *result.lock().unwrap() = Some(inference_result);
             })
         };
 
         loop {
+            // The model's thread notices the token too and drops its request.
+            if cancel.is_cancelled() {
+                logger.info(&format!("Inference cancelled. Model: {}, Model ID: {:?}", model_enum, self.model_id));
+                return Err(CANCELLED.to_string());
+            }
+
             let elapsed_time = start_time.elapsed().as_secs_f32();
             let elapsed_seconds = format!("{:.2}", elapsed_time);
             emit_agent("inference", serde_json::json!({ "type": "time", "elapsed_time": elapsed_seconds }));
@@ -168,17 +182,18 @@ impl LLM {
 }
 
 trait InferenceModel: Send + Sync {
-    fn inference(&self, model_name: &str, prompt: &str) -> Result<String, String>;
+    /// Gives up on the request once `cancel` is cancelled, where the client allows it.
+    fn inference(&self, model_name: &str, prompt: &str, cancel: &CancellationToken) -> Result<String, String>;
 }
 
 impl InferenceModel for Ollama {
-    fn inference(&self, model_name: &str, prompt: &str) -> Result<String, String> {
-        Ok(self.inference(model_name, prompt))
+    fn inference(&self, model_name: &str, prompt: &str, cancel: &CancellationToken) -> Result<String, String> {
+        self.inference(model_name, prompt, cancel)
     }
 }
 
 impl InferenceModel for Groq {
-    fn inference(&self, model_name: &str, prompt: &str) -> Result<String, String> {
-        Ok(self.inference(model_name, prompt))
+    fn inference(&self, model_name: &str, prompt: &str, cancel: &CancellationToken) -> Result<String, String> {
+        self.inference(model_name, prompt, cancel)
     }
 }
\ No newline at end of file
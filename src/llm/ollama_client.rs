@@ -1,4 +1,7 @@
 use ollama_rs::generation::completion::request::GenerationRequest;
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::control::CANCELLED;
 
 use crate::config::Config;
 
@@ -14,14 +17,19 @@ impl Ollama {
         let ollama = ollama_rs::Ollama::new(url.host().unwrap().to_string(), url.port().unwrap());
         Self { url: config.get_ollama_api_endpoint().to_string(), client: Some(ollama) }
     }
-    pub fn inference(&self, model_id: &str, prompt: &str) -> String {
+    /// Cancelling `cancel` drops the request, which closes the connection and stops
+    /// Ollama generating.
+    pub fn inference(&self, model_id: &str, prompt: &str, cancel: &CancellationToken) -> Result<String, String> {
         let client =  self.client.as_ref().unwrap();
         let genres = client.generate(GenerationRequest::new(model_id.to_string(), prompt.to_string()));
 
-        let res = tokio::runtime::Runtime::new().unwrap().block_on(genres).unwrap();
-
-
+        let res = tokio::runtime::Runtime::new().unwrap().block_on(async {
+            tokio::select! {
+                res = genres => res.map_err(|e| e.to_string()),
+                _ = cancel.cancelled() => Err(CANCELLED.to_string()),
+            }
+        })?;
 
-        return res.response
+        Ok(res.response)
     }
 }
\ No newline at end of file
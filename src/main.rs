@@ -4,21 +4,41 @@ pub mod socket_instance;
 pub mod llm;
 pub mod logger;
 pub mod project;
+pub mod browser;
+pub mod agents;
+pub mod services;
+pub mod memory;
+pub mod bert;
+pub mod filesystem;
+pub mod documenter;
+pub mod sandbox;
+#[cfg(unix)]
+pub mod terminal;
 
 #[macro_use] extern crate rocket;
 extern crate serde;
 
+use agents::agent::Agent;
+use agents::control;
+use agents::questions;
 use logger::Logger;
 use project::ProjectManager;
 use rocket::serde::{json::Json, Deserialize, Serialize};
 use rocket::fs::NamedFile;
+use rocket::http::{ContentType, Header};
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::Shutdown;
 use rocket::State;
 use std::str::FromStr;
 use std::sync::{Mutex, Arc};
 use std::path::PathBuf;
+use std::time::Duration;
 use serde_json::json;
 use state::AgentState;
 use config::Config;
+use socket_instance::emit_agent;
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -36,19 +56,77 @@ struct AppState {
 async fn data(state: &State<Arc<AppState>>) -> Json<serde_json::Value> {
     let agent_state = state.agent_state.clone();
     let project = ProjectManager::new().unwrap().get_project_list().await;
-    let models = llm::llm::LLM::new(Some(String::new())).list_models();
-    let search_engines = vec!["Bing", "Google", "DuckDuckGo"];
+    let llm = llm::llm::LLM::new(Some(String::new()));
+    let models = llm.list_models();
+    let search_engines = vec!["Bing", "Google", "DuckDuckGo", "SearXNG", "Fixture"];
     Json(json!({"projects": project, "models": models, "search_engines": search_engines}))
 }
 
 #[post("/api/messages", format = "application/json", data = "<data>")]
-async fn get_messages(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
-    let agent_state = state.agent_state.clone();
+async fn get_messages(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
     let project_name = data["project_name"].as_str().unwrap();
-    let messages = agent_state.get_messages_for_project(project_name).await;
+    let messages = ProjectManager::new().unwrap().get_messages(project_name).await;
     Json(json!({"messages": messages}))
 }
 
+#[post("/api/user-message", format = "application/json", data = "<data>")]
+async fn user_message(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let agent_state = state.agent_state.clone();
+    let message = data["message"].as_str().unwrap_or_default().to_string();
+    let base_model = data["base_model"].as_str().unwrap_or_default().to_string();
+    let project_name = data["project_name"].as_str().unwrap_or_default().to_string();
+    let search_engine = data["search_engine"].as_str().unwrap_or_default().to_lowercase();
+    let task_branch = data["task_branch"].as_bool()
+        .unwrap_or_else(|| state.config.lock().unwrap().get_git_branch_per_task());
+
+    // While the agent waits on a question, the message is its answer. The agent stores
+    // it, so one it no longer takes is handled like any other message.
+    if questions::is_waiting(&project_name) && questions::answer(&project_name, &message) {
+        return Json(json!({"message": "Answer received"}));
+    }
+
+    // A project without state frames has never had a run, so it is idle.
+    let is_busy = control::current(&project_name).is_some()
+        || agent_state.is_agent_active(&project_name).unwrap_or(false)
+        || !agent_state.is_agent_completed(&project_name).unwrap_or(true);
+    if is_busy {
+        emit_agent("info", json!({"type": "warning", "message": "previous agent doesn't completed it's task."}));
+        return Json(json!({"message": "Agent is busy with this project"}));
+    }
+
+    // The first message of a project starts the agentic flow; later ones are follow-ups.
+    let has_messages = ProjectManager::new().unwrap().get_messages(&project_name).await
+        .is_some_and(|messages| !messages.is_empty());
+    let first_message = agent_state.get_latest_state(&project_name).is_none() || !has_messages;
+
+    // Registered here rather than on the agent's thread, so a second message can't
+    // slip in before the run exists.
+    if let Err(e) = control::start(&project_name) {
+        return Json(json!({"message": e}));
+    }
+    std::thread::spawn(move || {
+        let logger = Logger::new("devika_agent.log");
+        match Agent::new(&base_model, &search_engine) {
+            Ok(agent) => {
+                let result = if first_message {
+                    agent.execute(&message, &project_name, task_branch)
+                } else {
+                    agent.subsequent_execute(&message, &project_name, task_branch)
+                };
+                if let Err(e) = result {
+                    logger.error(&format!("Agent failed for {}: {}", project_name, e));
+                }
+            }
+            Err(e) => {
+                control::finish(&project_name);
+                logger.error(&format!("Could not start the agent: {}", e));
+            }
+        }
+    });
+
+    Json(json!({"message": "Message received"}))
+}
+
 #[post("/api/is-agent-active", format = "application/json", data = "<data>")]
 async fn is_agent_active(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
     let agent_state = state.agent_state.clone();
@@ -57,6 +135,70 @@ async fn is_agent_active(state: &State<Arc<AppState>>, data: Json<serde_json::Va
     Json(json!({"is_active": is_active}))
 }
 
+#[post("/api/agent/cancel", format = "application/json", data = "<data>")]
+async fn cancel_agent(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    if !control::cancel(project_name) {
+        return Json(json!({"error": "No run in progress"}));
+    }
+    Json(json!({"message": "Cancelling"}))
+}
+
+#[post("/api/agent/pause", format = "application/json", data = "<data>")]
+async fn pause_agent(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    if !control::pause(project_name) {
+        return Json(json!({"error": "No run in progress"}));
+    }
+    Json(json!({"message": "Pausing after the current step"}))
+}
+
+/// Lets a paused run go on, or starts an agent on a cancelled one from the step
+/// after the last it finished.
+#[post("/api/agent/resume", format = "application/json", data = "<data>")]
+async fn resume_agent(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default().to_string();
+    if control::resume(&project_name) {
+        return Json(json!({"message": "Resumed"}));
+    }
+    if control::current(&project_name).is_some() {
+        return Json(json!({"error": "The run is not paused"}));
+    }
+    let Some(interruption) = state.agent_state.get_interruption(&project_name) else {
+        return Json(json!({"error": "Nothing to resume"}));
+    };
+    if let Err(e) = control::start(&project_name) {
+        return Json(json!({"error": e}));
+    }
+
+    let progress = &interruption["progress"];
+    let base_model = data["base_model"].as_str()
+        .or(progress["base_model"].as_str())
+        .unwrap_or_default()
+        .to_string();
+    let search_engine = data["search_engine"].as_str()
+        .or(progress["search_engine"].as_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    std::thread::spawn(move || {
+        let logger = Logger::new("devika_agent.log");
+        match Agent::new(&base_model, &search_engine) {
+            Ok(agent) => {
+                if let Err(e) = agent.resume(&project_name) {
+                    logger.error(&format!("Agent failed for {}: {}", project_name, e));
+                }
+            }
+            Err(e) => {
+                control::finish(&project_name);
+                logger.error(&format!("Could not start the agent: {}", e));
+            }
+        }
+    });
+
+    Json(json!({"message": "Resuming"}))
+}
+
 #[post("/api/get-agent-state", format = "application/json", data = "<data>")]
 async fn get_agent_state(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
     let agent_state = state.agent_state.clone();
@@ -72,11 +214,245 @@ async fn project_files(state: &State<Arc<AppState>>, project_name: String) -> Js
     Json(json!({"files": files}))
 }
 
+#[get("/api/project-tree?<project_name>")]
+fn project_tree(project_name: String) -> Json<serde_json::Value> {
+    // The editor asks for the tree when a project is opened; keep it posted from then on.
+    if let Err(e) = filesystem::watcher::watch_project(&project_name) {
+        Logger::new("devika_agent.log").warning(&format!("Could not watch {}: {}", project_name, e));
+    }
+    match filesystem::project_tree::project_tree(&project_name) {
+        Ok(tree) => Json(json!(tree)),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[get("/api/project-file?<project_name>&<path>")]
+fn get_project_file(project_name: String, path: String) -> Json<serde_json::Value> {
+    match filesystem::project_tree::read_project_file(&project_name, &path) {
+        Ok(file) => Json(json!(file)),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[put("/api/project-file", format = "application/json", data = "<data>")]
+fn save_project_file(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let path = data["path"].as_str().unwrap_or_default();
+    let Some(content) = data["content"].as_str() else {
+        return Json(json!({"error": "Missing file content"}));
+    };
+    match filesystem::project_tree::write_project_file(project_name, path, content) {
+        Ok(file) => Json(json!({"message": "File saved", "path": file.path, "size": file.size, "hash": file.hash})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[get("/api/project/history?<project_name>&<limit>")]
+fn project_history(project_name: String, limit: Option<usize>) -> Json<serde_json::Value> {
+    match services::git::Git::for_project(&project_name).and_then(|git| git.get_commits(limit.unwrap_or(50))) {
+        Ok(commits) => Json(json!({"commits": commits})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[get("/api/project/checkpoints?<project_name>")]
+fn project_checkpoints(project_name: String) -> Json<serde_json::Value> {
+    let git = match services::git::Git::for_project(&project_name) {
+        Ok(git) => git,
+        Err(e) => return Json(json!({"error": e})),
+    };
+    match git.get_checkpoints(100) {
+        Ok(checkpoints) => Json(json!({"checkpoints": checkpoints, "branch": git.current_branch().ok()})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+/// A run waiting on the user or paused is marked inactive but still owns the
+/// working tree, so the registered run counts too.
+fn agent_is_working(state: &AppState, project_name: &str) -> bool {
+    control::current(project_name).is_some() || state.agent_state.is_agent_active(project_name).unwrap_or(false)
+}
+
+#[post("/api/project/rollback", format = "application/json", data = "<data>")]
+fn project_rollback(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let checkpoint = data["checkpoint"].as_str().unwrap_or_default();
+    if agent_is_working(state, project_name) {
+        return Json(json!({"error": "The agent is still working on this project"}));
+    }
+    match services::git::Git::for_project(project_name).and_then(|git| git.rollback(checkpoint)) {
+        Ok(checkpoint) => {
+            state.agent_state.truncate_state(project_name, checkpoint.state_index);
+            Json(json!({"message": "Project rolled back", "checkpoint": checkpoint}))
+        }
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[get("/api/project/branches?<project_name>")]
+fn project_branches(project_name: String) -> Json<serde_json::Value> {
+    let git = match services::git::Git::for_project(&project_name) {
+        Ok(git) => git,
+        Err(e) => return Json(json!({"error": e})),
+    };
+    match git.get_task_branches() {
+        Ok(branches) => Json(json!({"branches": branches, "current": git.current_branch().ok()})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[post("/api/project/merge-branch", format = "application/json", data = "<data>")]
+fn merge_project_branch(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let branch = data["branch"].as_str().unwrap_or_default();
+    if agent_is_working(state, project_name) {
+        return Json(json!({"error": "The agent is still working on this project"}));
+    }
+    match services::git::Git::for_project(project_name).and_then(|git| git.merge_task_branch(branch)) {
+        Ok(branch) => Json(json!({"message": format!("Merged {} into {}", branch.name, branch.base)})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[post("/api/project/discard-branch", format = "application/json", data = "<data>")]
+fn discard_project_branch(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let branch = data["branch"].as_str().unwrap_or_default();
+    if agent_is_working(state, project_name) {
+        return Json(json!({"error": "The agent is still working on this project"}));
+    }
+    let git = match services::git::Git::for_project(project_name) {
+        Ok(git) => git,
+        Err(e) => return Json(json!({"error": e})),
+    };
+    // The agent's frames since the branch started only go when its work goes.
+    let was_checked_out = git.current_branch().is_ok_and(|current| current == branch);
+    match git.discard_task_branch(branch) {
+        Ok(branch) => {
+            if was_checked_out {
+                state.agent_state.truncate_state(project_name, branch.state_index);
+            }
+            Json(json!({"message": format!("Discarded {}", branch.name)}))
+        }
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[post("/api/project/pull-request", format = "application/json", data = "<data>")]
+async fn open_pull_request(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default().to_string();
+    if agent_is_working(state, &project_name) {
+        return Json(json!({"error": "The agent is still working on this project"}));
+    }
+    let branch = data["branch"].as_str().map(str::to_string);
+    let title = data["title"].as_str().map(str::to_string);
+    // Pushing and the forge's API are blocking calls.
+    let result = rocket::tokio::task::spawn_blocking(move || {
+        services::forge::open_pull_request(&project_name, branch.as_deref(), title.as_deref())
+    }).await;
+    match result {
+        Ok(Ok(pull_request)) => Json(json!({"url": pull_request.url, "number": pull_request.number})),
+        Ok(Err(e)) => Json(json!({"error": e})),
+        Err(e) => Json(json!({"error": e.to_string()})),
+    }
+}
+
+#[post("/api/project/deploy", format = "application/json", data = "<data>")]
+async fn deploy_project(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default().to_string();
+    let target = match data["target"].as_str() {
+        Some(target) => target.to_string(),
+        None => state.config.lock().unwrap().get_deploy_target().clone(),
+    };
+    // Uploading to Netlify blocks until the deploy is live.
+    let result = rocket::tokio::task::spawn_blocking(move || {
+        services::deploy::get_deployer(&target)?.deploy(&project_name)
+    }).await;
+    match result {
+        Ok(Ok(deployment)) => Json(json!(deployment)),
+        Ok(Err(e)) => Json(json!({"error": e})),
+        Err(e) => Json(json!({"error": e.to_string()})),
+    }
+}
+
+/// A response with one extra header.
+#[derive(Responder)]
+struct WithHeader<R> {
+    inner: R,
+    header: Header<'static>,
+}
+
+/// A project's site for the preview pane and local deploys, reloading itself when
+/// the project changes. Its pages come from the agent or an imported repository, so
+/// they run in an opaque origin rather than the API's, which would let their scripts
+/// call every `/api/*` route.
+#[get("/preview/<project_name>/<file..>")]
+fn preview(project_name: String, file: PathBuf) -> Option<WithHeader<(ContentType, Vec<u8>)>> {
+    let file = filesystem::preview::read_preview_file(&project_name, &file).ok()?;
+    let content_type = ContentType::parse_flexible(file.content_type).unwrap_or(ContentType::Binary);
+    Some(WithHeader {
+        inner: (content_type, file.body),
+        header: Header::new("Content-Security-Policy", "sandbox allow-scripts"),
+    })
+}
+
+/// Server-sent `change` events for the live-reload script of `/preview`, which runs
+/// in an opaque origin and so needs CORS to read them.
+#[get("/api/preview/events?<project_name>")]
+fn preview_events(project_name: String, mut shutdown: Shutdown) -> WithHeader<EventStream![]> {
+    if let Err(e) = filesystem::watcher::watch_project(&project_name) {
+        Logger::new("devika_agent.log").warning(&format!("Could not watch {}: {}", project_name, e));
+    }
+    let project = project_name.to_lowercase().replace(' ', "-");
+    let mut changes = filesystem::watcher::subscribe();
+    let mut keep_watching = rocket::tokio::time::interval(Duration::from_secs(60));
+    let events = EventStream! {
+        loop {
+            let change = select! {
+                change = changes.recv() => match change {
+                    Ok(change) => change,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                // An open preview keeps the project watched.
+                _ = keep_watching.tick() => {
+                    let _ = filesystem::watcher::watch_project(&project_name);
+                    continue;
+                },
+                _ = &mut shutdown => break,
+            };
+            if change.project == project {
+                yield Event::json(&change).event("change");
+            }
+        }
+    };
+    WithHeader { inner: events, header: Header::new("Access-Control-Allow-Origin", "*") }
+}
+
 #[get("/api/get-browser-snapshot?<snapshot_path>")]
 async fn browser_snapshot(snapshot_path: String) -> Option<NamedFile> {
     NamedFile::open(PathBuf::from_str(snapshot_path.as_str()).unwrap()).await.ok()
 }
 
+#[get("/api/download-project-pdf?<project_name>")]
+async fn download_project_pdf(state: &State<Arc<AppState>>, project_name: String) -> Option<NamedFile> {
+    let pdf_dir = state.config.lock().unwrap().get_pdfs_dir().clone();
+    let pdf_path = documenter::pdf::pdf_file_path(&PathBuf::from(pdf_dir), &project_name).ok()?;
+    NamedFile::open(pdf_path).await.ok()
+}
+
+#[get("/api/project-diagram?<project_name>&<kind>&<format>")]
+fn project_diagram(project_name: String, kind: Option<String>, format: Option<String>) -> Json<serde_json::Value> {
+    let kind = kind.unwrap_or_else(|| "modules".to_string()).to_lowercase();
+    let format = format.unwrap_or_else(|| "mermaid".to_string()).to_lowercase();
+    let diagram = documenter::architecture::scan_project(&project_name)
+        .and_then(|architecture| documenter::render_diagram(&architecture, &kind, &format));
+    match diagram {
+        Ok(diagram) => Json(json!({"kind": kind, "format": format, "diagram": diagram})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
 #[get("/api/get-browser-session?<project_name>")]
 async fn get_browser_session(state: &State<Arc<AppState>>, project_name: String) -> Json<serde_json::Value> {
     let agent_state = state.agent_state.clone();
@@ -100,12 +476,22 @@ async fn get_terminal_session(state: &State<Arc<AppState>>, project_name: String
 }
 
 #[post("/api/run-code", format = "application/json", data = "<data>")]
-fn run_code(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
-    let agent_state = state.agent_state.clone();
-    let project_name = data["project_name"].as_str().unwrap();
-    let code = data["code"].as_str().unwrap();
-    // implement code execution logic here
-    Json(json!({"message": "Code execution started"}))
+fn run_code(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let code = data["code"].as_str();
+    let language = data["language"].as_str();
+    match sandbox::code_runner::start_execution(project_name, code, language) {
+        Ok(execution_id) => Json(json!({"message": "Code execution started", "execution_id": execution_id})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[get("/api/run-code/<execution_id>")]
+fn run_code_status(execution_id: &str) -> Json<serde_json::Value> {
+    match sandbox::code_runner::get_execution(execution_id) {
+        Some(execution) => Json(json!(execution)),
+        None => Json(json!({"error": "Unknown execution id"})),
+    }
 }
 
 #[post("/api/calculate-tokens", format = "application/json", data = "<data>")]
@@ -141,6 +527,89 @@ async fn get_settings(state: &State<Arc<AppState>>) -> Json<serde_json::Value> {
     Json(json!({"settings": *config}))
 }
 
+#[cfg(unix)]
+#[post("/api/terminal/open", format = "application/json", data = "<data>")]
+fn open_terminal(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let rows = data["rows"].as_u64().unwrap_or(24) as u16;
+    let cols = data["cols"].as_u64().unwrap_or(80) as u16;
+    match terminal::session::open_session(project_name, rows, cols) {
+        Ok(_) => Json(json!({"scrollback": terminal::session::get_scrollback(project_name)})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[cfg(unix)]
+#[post("/api/terminal/input", format = "application/json", data = "<data>")]
+fn terminal_input(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let input = data["data"].as_str().unwrap_or_default();
+    let result = terminal::session::get_session(project_name)
+        .ok_or_else(|| "No terminal is open for this project".to_string())
+        .and_then(|session| session.write_input(input));
+    match result {
+        Ok(()) => Json(json!({"message": "ok"})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[cfg(unix)]
+#[post("/api/terminal/resize", format = "application/json", data = "<data>")]
+fn terminal_resize(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let rows = data["rows"].as_u64().unwrap_or(24) as u16;
+    let cols = data["cols"].as_u64().unwrap_or(80) as u16;
+    let result = terminal::session::get_session(project_name)
+        .ok_or_else(|| "No terminal is open for this project".to_string())
+        .and_then(|session| session.resize(rows, cols));
+    match result {
+        Ok(()) => Json(json!({"message": "ok"})),
+        Err(e) => Json(json!({"error": e})),
+    }
+}
+
+#[cfg(unix)]
+#[get("/api/terminal/scrollback?<project_name>")]
+fn terminal_scrollback(project_name: String) -> Json<serde_json::Value> {
+    Json(json!({"scrollback": terminal::session::get_scrollback(&project_name)}))
+}
+
+#[cfg(unix)]
+#[post("/api/terminal/close", format = "application/json", data = "<data>")]
+fn close_terminal(data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    terminal::session::close_session(data["project_name"].as_str().unwrap_or_default());
+    Json(json!({"message": "Terminal closed"}))
+}
+
+#[get("/api/sandbox-policy?<project_name>")]
+async fn get_sandbox_policy(state: &State<Arc<AppState>>, project_name: String) -> Json<serde_json::Value> {
+    let config = state.config.lock().unwrap();
+    let policy = sandbox::SandboxPolicy::for_project(&config, &project_name);
+    Json(json!({
+        "sandbox": policy.backend,
+        "allow_network": policy.allow_network,
+        "timeout": policy.timeout.as_secs(),
+        "cpu_time": policy.cpu_time,
+        "memory_mb": policy.memory_bytes / 1024 / 1024,
+    }))
+}
+
+#[post("/api/sandbox-policy", format = "application/json", data = "<data>")]
+async fn set_sandbox_policy(state: &State<Arc<AppState>>, data: Json<serde_json::Value>) -> Json<serde_json::Value> {
+    let project_name = data["project_name"].as_str().unwrap_or_default();
+    let backend = data["sandbox"].as_str().map(|backend| backend.to_lowercase());
+    if let Some(backend) = &backend {
+        if let Err(e) = sandbox::get_sandbox(backend) {
+            return Json(json!({"error": e}));
+        }
+    }
+    let allow_network = data["allow_network"].as_bool();
+
+    let mut config = state.config.lock().unwrap();
+    config.set_execution_project_policy(project_name, backend, allow_network);
+    Json(json!({"message": "Sandbox policy updated"}))
+}
+
 #[get("/api/status")]
 async fn status() -> Json<serde_json::Value> {
     Json(json!({"status": "server is running!"}))
@@ -148,30 +617,62 @@ async fn status() -> Json<serde_json::Value> {
 
 #[launch]
 fn rocket() -> _ {
-    rocket::build()
+    let rocket = rocket::build()
         .manage(initialize_app_state())
         .mount("/", routes![
             data,
             get_messages,
+            user_message,
             is_agent_active,
+            cancel_agent,
+            pause_agent,
+            resume_agent,
             get_agent_state,
             project_files,
+            project_tree,
+            get_project_file,
+            save_project_file,
+            project_history,
+            project_checkpoints,
+            project_rollback,
+            project_branches,
+            merge_project_branch,
+            discard_project_branch,
+            open_pull_request,
+            deploy_project,
+            preview,
+            preview_events,
             browser_snapshot,
+            download_project_pdf,
+            project_diagram,
             get_browser_session,
             get_terminal_session,
             run_code,
+            run_code_status,
             calculate_tokens,
             token_usage,
             real_time_logs,
             set_settings,
             get_settings,
+            get_sandbox_policy,
+            set_sandbox_policy,
             status,
-        ])
+        ]);
+    // The terminal needs a PTY.
+    #[cfg(unix)]
+    let rocket = rocket.mount("/", routes![
+        open_terminal,
+        terminal_input,
+        terminal_resize,
+        terminal_scrollback,
+        close_terminal,
+    ]);
+    rocket
 }
 
 fn initialize_app_state() -> Arc<AppState> {
     let config = Config::new().unwrap();
-    let agent_state = Arc::new(AgentState::new("sqlite://database_url"));
+    let agent_state = Arc::new(AgentState::new(config.get_agent_state_db()));
 
     Arc::new(AppState {
         config: Mutex::new(config),
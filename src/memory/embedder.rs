@@ -0,0 +1,218 @@
+use serde_json::{json, Value};
+
+use crate::config::Config;
+
+/// Dimensions of the hashing embedder's vectors.
+const HASHING_DIMENSIONS: usize = 512;
+
+/// Texts sent to an embeddings API per request.
+const BATCH_SIZE: usize = 32;
+
+/// Turns texts into vectors whose cosine similarity says how related they are.
+pub trait Embedder: Send {
+    /// Identifies the vector space, e.g. `ollama:nomic-embed-text`; vectors from
+    /// different embedders can't be compared.
+    fn id(&self) -> String;
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// Picks the embedder for `[RAG] EMBEDDER`. Anything unknown uses the hashing
+/// embedder, which needs no model or network.
+pub fn get_embedder(name: &str) -> Result<Box<dyn Embedder>, String> {
+    Ok(match name.to_lowercase().as_str() {
+        "ollama" => Box::new(OllamaEmbedder::new()?),
+        "openai" => Box::new(OpenAiEmbedder::new()?),
+        _ => Box::new(HashingEmbedder::new(HASHING_DIMENSIONS)),
+    })
+}
+
+/// `/api/embed` of an Ollama server.
+pub struct OllamaEmbedder {
+    client: reqwest::blocking::Client,
+    api_endpoint: String,
+    model: String,
+}
+
+impl OllamaEmbedder {
+    pub fn new() -> Result<Self, String> {
+        let config = Config::new().map_err(|e| e.to_string())?;
+        let api_endpoint = if config.get_rag_endpoint().is_empty() { config.get_ollama_api_endpoint() } else { config.get_rag_endpoint() };
+        Ok(Self {
+            client: reqwest::blocking::Client::new(),
+            api_endpoint: api_endpoint.trim_end_matches('/').to_string(),
+            model: config.get_rag_model().to_string(),
+        })
+    }
+}
+
+impl Embedder for OllamaEmbedder {
+    fn id(&self) -> String {
+        format!("ollama:{}", self.model)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut vectors = vec![];
+        for batch in texts.chunks(BATCH_SIZE) {
+            let response: Value = self.client.post(format!("{}/api/embed", self.api_endpoint))
+                .json(&json!({"model": self.model, "input": batch}))
+                .send()
+                .map_err(|e| format!("Could not reach Ollama: {}", e))?
+                .json()
+                .map_err(|e| e.to_string())?;
+            if let Some(error) = response["error"].as_str() {
+                return Err(format!("Ollama could not embed with {}: {}", self.model, error));
+            }
+            vectors.extend(parse_vectors(&response["embeddings"], batch.len())?);
+        }
+        Ok(vectors)
+    }
+}
+
+/// `/embeddings` of the OpenAI API or any server that mimics it.
+pub struct OpenAiEmbedder {
+    client: reqwest::blocking::Client,
+    api_key: String,
+    api_endpoint: String,
+    model: String,
+}
+
+impl OpenAiEmbedder {
+    pub fn new() -> Result<Self, String> {
+        let config = Config::new().map_err(|e| e.to_string())?;
+        let api_endpoint = if config.get_rag_endpoint().is_empty() { config.get_openai_api_base_url() } else { config.get_rag_endpoint() };
+        Ok(Self {
+            client: reqwest::blocking::Client::new(),
+            api_key: config.get_openai_api_key().to_string(),
+            api_endpoint: api_endpoint.trim_end_matches('/').to_string(),
+            model: config.get_rag_model().to_string(),
+        })
+    }
+}
+
+impl Embedder for OpenAiEmbedder {
+    fn id(&self) -> String {
+        format!("openai:{}", self.model)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut vectors = vec![];
+        for batch in texts.chunks(BATCH_SIZE) {
+            let response: Value = self.client.post(format!("{}/embeddings", self.api_endpoint))
+                .bearer_auth(&self.api_key)
+                .json(&json!({"model": self.model, "input": batch}))
+                .send()
+                .map_err(|e| format!("Could not reach the embeddings endpoint: {}", e))?
+                .json()
+                .map_err(|e| e.to_string())?;
+            if let Some(error) = response["error"]["message"].as_str() {
+                return Err(format!("Could not embed with {}: {}", self.model, error));
+            }
+            // Entries carry their input's index and aren't guaranteed to be in order.
+            let mut data = response["data"].as_array().cloned().unwrap_or_default();
+            data.sort_by_key(|entry| entry["index"].as_u64().unwrap_or_default());
+            let embeddings = Value::Array(data.into_iter().map(|entry| entry["embedding"].clone()).collect());
+            vectors.extend(parse_vectors(&embeddings, batch.len())?);
+        }
+        Ok(vectors)
+    }
+}
+
+fn parse_vectors(embeddings: &Value, expected: usize) -> Result<Vec<Vec<f32>>, String> {
+    let vectors: Vec<Vec<f32>> = embeddings.as_array()
+        .map(|vectors| vectors.iter()
+            .map(|vector| vector.as_array().into_iter().flatten().filter_map(Value::as_f64).map(|value| value as f32).collect())
+            .collect())
+        .unwrap_or_default();
+    if vectors.len() != expected || vectors.iter().any(Vec::is_empty) {
+        return Err(format!("Expected {} embeddings, got {}", expected, vectors.len()));
+    }
+    Ok(vectors)
+}
+
+/// Feature hashing of words and word pairs into a fixed number of dimensions.
+/// Deterministic and offline, so retrieval works without a model and gives the
+/// same results on every run; it only captures shared vocabulary, not meaning.
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions: dimensions.max(1) }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        let words: Vec<String> = split_words(text);
+        let bigrams = words.windows(2).map(|pair| format!("{} {}", pair[0], pair[1]));
+        for feature in words.iter().cloned().chain(bigrams) {
+            let hash = fnv1a(feature.as_bytes());
+            // The sign bit keeps colliding features from always adding up.
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimensions as u64) as usize] += sign;
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn id(&self) -> String {
+        format!("hashing:{}", self.dimensions)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// Lowercase words, with `camelCase` and `snake_case` identifiers split into parts
+/// as well as kept whole.
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = vec![];
+    for token in text.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|token| !token.is_empty()) {
+        let mut parts = vec![];
+        let mut current = String::new();
+        let mut previous_lower = false;
+        for c in token.chars() {
+            if (c == '_' || (c.is_uppercase() && previous_lower)) && !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            if c != '_' {
+                current.extend(c.to_lowercase());
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+        if !current.is_empty() {
+            parts.push(current);
+        }
+        let whole = token.to_lowercase().replace('_', "");
+        if parts.len() > 1 {
+            words.push(whole);
+        }
+        words.extend(parts);
+    }
+    words
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+}
+
+/// Cosine similarity; vectors of different lengths are unrelated.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|y| y * y).sum::<f32>().sqrt();
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+
+use crate::config::Config;
+
+/// A stored answer is reused only when its query shares at least this share of
+/// terms with the new one, so "python web framework comparison" doesn't get the
+/// research for "rust web framework comparison".
+const MIN_QUERY_OVERLAP: f64 = 0.75;
+
+/// Candidates BM25 picks before the overlap check.
+const LOOKUP_CANDIDATES: i64 = 10;
+
+/// Matches in the stored query count this much more than matches in the contents.
+const QUERY_WEIGHT: f64 = 10.0;
+
+/// Too common to tell two queries apart.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how", "i", "in", "is", "it",
+    "of", "on", "or", "the", "to", "what", "when", "where", "which", "who", "why", "with",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Knowledge {
+    pub query: String,
+    pub project: String,
+    pub contents: String,
+    /// Unix timestamp of when the research was stored.
+    pub created_at: i64,
+    /// BM25 rank; lower is more relevant.
+    pub score: f64,
+}
+
+/// Formatted research results, searchable with BM25 through SQLite's FTS5. Shares
+/// `SQLITE_DB` with the projects, in its own `knowledge_base` table.
+pub struct KnowledgeBase {
+    pool: SqlitePool,
+}
+
+impl KnowledgeBase {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let config = Config::new()?;
+        let sqlite_path = config.get_sqlite_db();
+        if let Some(parent) = Path::new(sqlite_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(sqlite_path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_lazy_with(options);
+
+        Ok(Self { pool })
+    }
+
+    async fn create_table(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("CREATE VIRTUAL TABLE IF NOT EXISTS knowledge_base USING fts5(query, contents, project UNINDEXED, created_at UNINDEXED, tokenize = 'porter unicode61')")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Stores research for a query, replacing what the project had for the same query.
+    pub async fn add_knowledge(&self, query: &str, project: &str, contents: &str) -> Result<(), sqlx::Error> {
+        self.create_table().await?;
+        let query = query.trim().to_lowercase();
+        sqlx::query("DELETE FROM knowledge_base WHERE query = ? AND project = ?")
+            .bind(&query)
+            .bind(project)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("INSERT INTO knowledge_base (query, contents, project, created_at) VALUES (?, ?, ?, ?)")
+            .bind(&query)
+            .bind(contents)
+            .bind(project)
+            .bind(Utc::now().timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Stored research that answers `query`, if any is younger than `max_age`. The
+    /// project's own research wins over other projects'.
+    pub async fn get_knowledge(&self, query: &str, project: &str, max_age: Duration) -> Option<Knowledge> {
+        let query_terms = terms(query);
+        if query_terms.is_empty() {
+            return None;
+        }
+        let since = Utc::now().timestamp() - max_age.as_secs() as i64;
+        let candidates = self.ranked(&format!("query : ({})", match_any(&query_terms)), None, since, LOOKUP_CANDIDATES).await.ok()?;
+
+        candidates.into_iter()
+            .filter(|candidate| overlap(&query_terms, &terms(&candidate.query)) >= MIN_QUERY_OVERLAP)
+            .min_by(|a, b| (a.project != project).cmp(&(b.project != project)).then(a.score.total_cmp(&b.score)))
+    }
+
+    /// Research of any age matching `text` in its query or contents, best first.
+    pub async fn search(&self, text: &str, project: Option<&str>, limit: i64) -> Vec<Knowledge> {
+        let text_terms = terms(text);
+        if text_terms.is_empty() {
+            return vec![];
+        }
+        self.ranked(&match_any(&text_terms), project, 0, limit).await.unwrap_or_default()
+    }
+
+    /// All research stored for a project, newest first.
+    pub async fn get_project_knowledge(&self, project: &str) -> Result<Vec<Knowledge>, sqlx::Error> {
+        self.create_table().await?;
+        let rows = sqlx::query("SELECT query, contents, project, created_at FROM knowledge_base WHERE project = ? ORDER BY created_at DESC")
+            .bind(project)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter()
+            .map(|row| Knowledge {
+                query: row.get("query"),
+                contents: row.get("contents"),
+                project: row.get("project"),
+                created_at: row.get("created_at"),
+                score: 0.0,
+            })
+            .collect())
+    }
+
+    async fn ranked(&self, expression: &str, project: Option<&str>, since: i64, limit: i64) -> Result<Vec<Knowledge>, sqlx::Error> {
+        self.create_table().await?;
+        let rows = sqlx::query("SELECT query, contents, project, created_at, bm25(knowledge_base, ?, 1.0) AS score FROM knowledge_base WHERE knowledge_base MATCH ? AND (? IS NULL OR project = ?) AND created_at >= ? ORDER BY score LIMIT ?")
+            .bind(QUERY_WEIGHT)
+            .bind(expression)
+            .bind(project)
+            .bind(project)
+            .bind(since)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter()
+            .map(|row| Knowledge {
+                query: row.get("query"),
+                contents: row.get("contents"),
+                project: row.get("project"),
+                created_at: row.get("created_at"),
+                score: row.get("score"),
+            })
+            .collect())
+    }
+}
+
+/// Lowercase words of a query without stop words.
+fn terms(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|term| !term.is_empty() && !STOP_WORDS.contains(&term.as_str()))
+        .filter(|term| seen.insert(term.clone()))
+        .collect()
+}
+
+/// Drops common inflections so "defining routes" and "define route" compare equal;
+/// FTS5's porter tokenizer takes care of the search itself.
+fn stem(term: &str) -> String {
+    const SUFFIXES: &[&str] = &["ings", "ing", "ies", "ied", "ed", "es", "s", "e"];
+    SUFFIXES.iter()
+        .find_map(|suffix| term.strip_suffix(suffix).filter(|stem| stem.chars().count() >= 3))
+        .unwrap_or(term)
+        .to_string()
+}
+
+/// An FTS5 expression matching any of the terms; quoting keeps them from being
+/// read as operators.
+fn match_any(terms: &[String]) -> String {
+    terms.iter().map(|term| format!("\"{}\"", term)).collect::<Vec<_>>().join(" OR ")
+}
+
+/// Shared terms over all terms of both queries.
+fn overlap(a: &[String], b: &[String]) -> f64 {
+    let a: HashSet<_> = a.iter().map(|term| stem(term)).collect();
+    let b: HashSet<_> = b.iter().map(|term| stem(term)).collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
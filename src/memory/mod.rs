@@ -0,0 +1,3 @@
+pub mod embedder;
+pub mod knowledge_base;
+pub mod rag;
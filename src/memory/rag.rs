@@ -0,0 +1,303 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+
+use crate::config::Config;
+use crate::filesystem::read_code::ReadCode;
+use crate::filesystem::watcher::content_hash;
+use crate::logger::Logger;
+use crate::memory::embedder::{cosine_similarity, get_embedder, Embedder};
+use crate::memory::knowledge_base::KnowledgeBase;
+
+/// Long lines, such as minified code, are cut so one chunk can't eat the prompt.
+const MAX_CHUNK_CHARS: usize = 6000;
+
+/// Files named in the overview above the retrieved chunks.
+const MAX_LISTED_FILES: usize = 200;
+
+const RESEARCH_PREFIX: &str = "research: ";
+
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    /// The file path, or `research: <query>` for stored research.
+    pub source: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub content: String,
+    pub score: f32,
+}
+
+/// Retrieval over a project's code and research. Sources are split into line
+/// chunks and embedded once; only sources whose hash changed are embedded again.
+/// Vectors are kept in `SQLITE_DB`, per embedder, in the `rag_chunks` table.
+pub struct Rag {
+    pool: SqlitePool,
+    embedder: Box<dyn Embedder>,
+    top_k: usize,
+    chunk_lines: usize,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Rag {
+    pub fn new() -> Result<Self, String> {
+        let config = Config::new().map_err(|e| e.to_string())?;
+        let sqlite_path = config.get_sqlite_db();
+        if let Some(parent) = Path::new(sqlite_path).parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        // The pool starts its upkeep tasks on the current runtime, so it needs one.
+        let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        let options = SqliteConnectOptions::new()
+            .filename(sqlite_path)
+            .create_if_missing(true);
+        let pool = {
+            let _guard = runtime.enter();
+            SqlitePoolOptions::new().connect_lazy_with(options)
+        };
+
+        Ok(Self {
+            pool,
+            embedder: get_embedder(config.get_rag_embedder())?,
+            top_k: config.get_rag_top_k().max(1),
+            chunk_lines: config.get_rag_chunk_lines().max(1),
+            runtime,
+        })
+    }
+
+    /// The store is async; embedders make blocking requests, so they run outside of it.
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    async fn create_table(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("CREATE TABLE IF NOT EXISTS rag_chunks (id INTEGER PRIMARY KEY, project VARCHAR NOT NULL, embedder VARCHAR NOT NULL, source VARCHAR NOT NULL, source_hash VARCHAR NOT NULL, start_line INTEGER NOT NULL, end_line INTEGER NOT NULL, content VARCHAR NOT NULL, vector BLOB NOT NULL)")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS rag_chunks_project ON rag_chunks (project, embedder)")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Brings the project's chunks up to date with its files and stored research.
+    /// Returns how many sources were embedded again.
+    pub fn index_project(&self, project_name: &str) -> Result<usize, String> {
+        let embedder_id = self.embedder.id();
+        let mut sources: Vec<(String, String)> = ReadCode::new(project_name).read_directory()
+            .into_iter()
+            .map(|entry| (entry.filename, entry.code))
+            .collect();
+        let knowledge_base = {
+            let _guard = self.runtime.enter();
+            KnowledgeBase::new()
+        };
+        if let Ok(knowledge_base) = knowledge_base {
+            let research = self.block_on(knowledge_base.get_project_knowledge(project_name)).unwrap_or_default();
+            sources.extend(research.into_iter().map(|knowledge| (format!("{}{}", RESEARCH_PREFIX, knowledge.query), knowledge.contents)));
+        }
+
+        let indexed: HashMap<String, String> = self.block_on(async {
+            self.create_table().await?;
+            let rows = sqlx::query("SELECT DISTINCT source, source_hash FROM rag_chunks WHERE project = ? AND embedder = ?")
+                .bind(project_name)
+                .bind(&embedder_id)
+                .fetch_all(&self.pool)
+                .await?;
+            Ok::<_, sqlx::Error>(rows.into_iter().map(|row| (row.get("source"), row.get("source_hash"))).collect())
+        }).map_err(|e| e.to_string())?;
+
+        let current: HashSet<&str> = sources.iter().map(|(source, _)| source.as_str()).collect();
+        for source in indexed.keys().filter(|source| !current.contains(source.as_str())) {
+            self.block_on(self.delete_source(project_name, &embedder_id, source)).map_err(|e| e.to_string())?;
+        }
+
+        let mut embedded = 0;
+        for (source, text) in &sources {
+            let hash = content_hash(text.as_bytes());
+            if indexed.get(source) == Some(&hash) {
+                continue;
+            }
+            let chunks = chunk_lines(text, self.chunk_lines);
+            let inputs: Vec<String> = chunks.iter().map(|(_, _, content)| format!("{}\n{}", source, content)).collect();
+            let vectors = self.embedder.embed(&inputs)?;
+
+            self.block_on(async {
+                let mut transaction = self.pool.begin().await?;
+                sqlx::query("DELETE FROM rag_chunks WHERE project = ? AND embedder = ? AND source = ?")
+                    .bind(project_name)
+                    .bind(&embedder_id)
+                    .bind(source)
+                    .execute(&mut *transaction)
+                    .await?;
+                for ((start_line, end_line, content), vector) in chunks.iter().zip(&vectors) {
+                    sqlx::query("INSERT INTO rag_chunks (project, embedder, source, source_hash, start_line, end_line, content, vector) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+                        .bind(project_name)
+                        .bind(&embedder_id)
+                        .bind(source)
+                        .bind(&hash)
+                        .bind(*start_line as i64)
+                        .bind(*end_line as i64)
+                        .bind(content)
+                        .bind(vector_to_bytes(vector))
+                        .execute(&mut *transaction)
+                        .await?;
+                }
+                transaction.commit().await
+            }).map_err(|e| e.to_string())?;
+            embedded += 1;
+        }
+        Ok(embedded)
+    }
+
+    async fn delete_source(&self, project_name: &str, embedder_id: &str, source: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM rag_chunks WHERE project = ? AND embedder = ? AND source = ?")
+            .bind(project_name)
+            .bind(embedder_id)
+            .bind(source)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The `top_k` indexed chunks most similar to `query`, best first.
+    pub fn retrieve(&self, project_name: &str, query: &str, top_k: usize) -> Result<Vec<Chunk>, String> {
+        let query_vector = self.embedder.embed(&[query.to_string()])?.pop().unwrap_or_default();
+        let rows = self.block_on(async {
+            self.create_table().await?;
+            sqlx::query("SELECT source, start_line, end_line, content, vector FROM rag_chunks WHERE project = ? AND embedder = ?")
+                .bind(project_name)
+                .bind(self.embedder.id())
+                .fetch_all(&self.pool)
+                .await
+        }).map_err(|e| e.to_string())?;
+
+        let mut chunks: Vec<Chunk> = rows.into_iter()
+            .map(|row| Chunk {
+                source: row.get("source"),
+                start_line: row.get::<i64, _>("start_line") as usize,
+                end_line: row.get::<i64, _>("end_line") as usize,
+                content: row.get("content"),
+                score: cosine_similarity(&query_vector, &bytes_to_vector(&row.get::<Vec<u8>, _>("vector"))),
+            })
+            .collect();
+        chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+        chunks.truncate(top_k);
+        Ok(chunks)
+    }
+
+    /// The project's code and research most relevant to `query`, as Markdown in
+    /// the same shape as `ReadCode::code_set_to_markdown`, led by a list of every
+    /// file so the model knows what it isn't seeing. Empty for an empty project.
+    pub fn context_markdown(&self, project_name: &str, query: &str) -> Result<String, String> {
+        self.index_project(project_name)?;
+        let chunks = self.retrieve(project_name, query, self.top_k)?;
+        if chunks.is_empty() {
+            return Ok(String::new());
+        }
+
+        let files = self.block_on(async {
+            sqlx::query("SELECT DISTINCT source FROM rag_chunks WHERE project = ? AND embedder = ? AND source NOT LIKE 'research: %' ORDER BY source")
+                .bind(project_name)
+                .bind(self.embedder.id())
+                .fetch_all(&self.pool)
+                .await
+        }).map_err(|e| e.to_string())?;
+        let files: Vec<String> = files.into_iter().map(|row| row.get("source")).collect();
+
+        let mut markdown = String::new();
+        let mut listed = files.iter().take(MAX_LISTED_FILES).map(String::as_str).collect::<Vec<_>>().join(", ");
+        if files.len() > MAX_LISTED_FILES {
+            let _ = write!(listed, " and {} more", files.len() - MAX_LISTED_FILES);
+        }
+        let _ = write!(markdown, "Only the parts of the project most relevant to the request are shown. The project's files are: {}\n\n", listed);
+
+        for chunk in merge_chunks(chunks) {
+            match chunk.source.strip_prefix(RESEARCH_PREFIX) {
+                Some(query) => {
+                    let _ = write!(markdown, "### Research for \"{}\":\n\n```\n{}\n```\n\n---\n\n", query, chunk.content);
+                }
+                None => {
+                    let _ = write!(markdown, "### {} (lines {}-{}):\n\n```\n{}\n```\n\n---\n\n", chunk.source, chunk.start_line, chunk.end_line, chunk.content);
+                }
+            }
+        }
+        Ok(markdown)
+    }
+}
+
+/// The code and research most relevant to `query` when `[RAG]` is enabled, the
+/// whole project within the `[READ_CODE]` budget otherwise or if retrieval fails.
+pub fn project_context(project_name: &str, query: &str, logger: &Logger) -> String {
+    if Config::new().is_ok_and(|config| config.get_rag_enabled()) {
+        match Rag::new().and_then(|rag| rag.context_markdown(project_name, query)) {
+            Ok(markdown) => return markdown,
+            Err(e) => logger.warning(&format!("Retrieval failed for {}, sending the whole project: {}", project_name, e)),
+        }
+    }
+    ReadCode::new(project_name).code_set_to_markdown()
+}
+
+/// `(first line, last line, text)` windows of `size` lines, overlapping by a sixth
+/// so a function cut at a boundary still shows up whole in one of them.
+fn chunk_lines(text: &str, size: usize) -> Vec<(usize, usize, String)> {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return vec![];
+    }
+    let step = (size - size / 6).max(1);
+    let mut chunks = vec![];
+    let mut start = 0;
+    loop {
+        let end = (start + size).min(lines.len());
+        let mut content = lines[start..end].join("\n");
+        if content.len() > MAX_CHUNK_CHARS {
+            let cut = (0..=MAX_CHUNK_CHARS).rev().find(|&index| content.is_char_boundary(index)).unwrap_or(0);
+            content.truncate(cut);
+        }
+        chunks.push((start + 1, end, content));
+        if end == lines.len() {
+            break;
+        }
+        start += step;
+    }
+    chunks
+}
+
+/// Sorts the retrieved chunks by source and line and joins overlapping ones, so no
+/// line is sent twice.
+fn merge_chunks(mut chunks: Vec<Chunk>) -> Vec<Chunk> {
+    chunks.sort_by(|a, b| a.source.cmp(&b.source).then(a.start_line.cmp(&b.start_line)));
+    let mut merged: Vec<Chunk> = vec![];
+    for chunk in chunks {
+        if let Some(last) = merged.last_mut() {
+            if last.source == chunk.source && chunk.start_line <= last.end_line + 1 {
+                if chunk.end_line > last.end_line {
+                    let skip = last.end_line + 1 - chunk.start_line;
+                    for line in chunk.content.lines().skip(skip) {
+                        last.content.push('\n');
+                        last.content.push_str(line);
+                    }
+                    last.end_line = chunk.end_line;
+                }
+                last.score = last.score.max(chunk.score);
+                continue;
+            }
+        }
+        merged.push(chunk);
+    }
+    merged
+}
+
+fn vector_to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn bytes_to_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect()
+}
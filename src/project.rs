@@ -0,0 +1,209 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::config::Config;
+use crate::filesystem::project_path;
+use crate::socket_instance::emit_agent;
+
+/// Per-project message stacks, stored in the same `projects` table the Python
+/// server uses so both can share one `SQLITE_DB`.
+pub struct ProjectManager {
+    pool: SqlitePool,
+}
+
+impl ProjectManager {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let config = Config::new()?;
+        let sqlite_path = config.get_sqlite_db();
+        if let Some(parent) = Path::new(sqlite_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(sqlite_path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_lazy_with(options);
+
+        Ok(Self { pool })
+    }
+
+    async fn create_table(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("CREATE TABLE IF NOT EXISTS projects (id INTEGER PRIMARY KEY, project VARCHAR NOT NULL, message_stack_json VARCHAR NOT NULL)")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_message_stack(&self, project: &str) -> Option<Vec<Value>> {
+        self.create_table().await.ok()?;
+        let row = sqlx::query("SELECT message_stack_json FROM projects WHERE project = ? LIMIT 1")
+            .bind(project)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()??;
+        serde_json::from_str(&row.get::<String, _>("message_stack_json")).ok()
+    }
+
+    pub fn new_message() -> Value {
+        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        json!({
+            "from_devika": true,
+            "message": null,
+            "timestamp": timestamp
+        })
+    }
+
+    pub async fn create_project(&self, project: &str) -> Result<(), sqlx::Error> {
+        self.create_table().await?;
+        sqlx::query("INSERT INTO projects (project, message_stack_json) VALUES (?, ?)")
+            .bind(project)
+            .bind("[]")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_project(&self, project: &str) -> Result<(), sqlx::Error> {
+        self.create_table().await?;
+        sqlx::query("DELETE FROM projects WHERE project = ?")
+            .bind(project)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn add_message_to_project(&self, project: &str, message: Value) -> Result<(), sqlx::Error> {
+        self.create_table().await?;
+        match self.get_message_stack(project).await {
+            Some(mut message_stack) => {
+                message_stack.push(message);
+                sqlx::query("UPDATE projects SET message_stack_json = ? WHERE project = ?")
+                    .bind(serde_json::to_string(&message_stack).unwrap())
+                    .bind(project)
+                    .execute(&self.pool)
+                    .await?;
+            }
+            None => {
+                sqlx::query("INSERT INTO projects (project, message_stack_json) VALUES (?, ?)")
+                    .bind(project)
+                    .bind(serde_json::to_string(&vec![message]).unwrap())
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn add_message_from_devika(&self, project: &str, message: &str) -> Result<(), sqlx::Error> {
+        let mut new_message = Self::new_message();
+        new_message["message"] = json!(message);
+        emit_agent("server-message", json!({"messages": new_message}));
+        self.add_message_to_project(project, new_message).await
+    }
+
+    pub async fn add_message_from_user(&self, project: &str, message: &str) -> Result<(), sqlx::Error> {
+        let mut new_message = Self::new_message();
+        new_message["message"] = json!(message);
+        new_message["from_devika"] = json!(false);
+        emit_agent("server-message", json!({"messages": new_message}));
+        self.add_message_to_project(project, new_message).await
+    }
+
+    pub async fn get_messages(&self, project: &str) -> Option<Vec<Value>> {
+        self.get_message_stack(project).await
+    }
+
+    pub async fn get_latest_message_from_user(&self, project: &str) -> Option<Value> {
+        self.get_message_stack(project).await?
+            .into_iter()
+            .rev()
+            .find(|message| !message["from_devika"].as_bool().unwrap_or(true))
+    }
+
+    pub async fn validate_last_message_is_from_user(&self, project: &str) -> bool {
+        self.get_message_stack(project).await
+            .and_then(|message_stack| message_stack.last().cloned())
+            .map(|message| !message["from_devika"].as_bool().unwrap_or(true))
+            .unwrap_or(false)
+    }
+
+    pub async fn get_latest_message_from_devika(&self, project: &str) -> Option<Value> {
+        self.get_message_stack(project).await?
+            .into_iter()
+            .rev()
+            .find(|message| message["from_devika"].as_bool().unwrap_or(false))
+    }
+
+    pub async fn get_project_list(&self) -> Vec<String> {
+        if self.create_table().await.is_err() {
+            return vec![];
+        }
+        sqlx::query("SELECT project FROM projects")
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows| rows.iter().map(|row| row.get::<String, _>("project")).collect())
+            .unwrap_or_default()
+    }
+
+    pub async fn get_all_messages_formatted(&self, project: &str) -> Vec<String> {
+        self.get_message_stack(project).await
+            .unwrap_or_default()
+            .iter()
+            .map(|message| {
+                let text = message["message"].as_str().unwrap_or_default();
+                if message["from_devika"].as_bool().unwrap_or(false) {
+                    format!("Devika: {}", text)
+                } else {
+                    format!("User: {}", text)
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_project_path(&self, project: &str) -> Result<PathBuf, String> {
+        project_path(project)
+    }
+
+    pub fn project_to_zip(&self, project: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let project_path = self.get_project_path(project)?;
+        let zip_path = self.get_zip_path(project)?;
+        let base = project_path.parent().unwrap_or(Path::new(""));
+
+        let mut zip = ZipWriter::new(File::create(&zip_path)?);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        let mut pending = vec![project_path.clone()];
+        while let Some(directory) = pending.pop() {
+            for entry in fs::read_dir(&directory)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    // The project's history stays on the server.
+                    if !path.ends_with(".git") {
+                        pending.push(path);
+                    }
+                    continue;
+                }
+                let relative_path = path.strip_prefix(base)?.to_string_lossy().replace('\\', "/");
+                let mut contents = vec![];
+                File::open(&path)?.read_to_end(&mut contents)?;
+                zip.start_file(relative_path, options)?;
+                zip.write_all(&contents)?;
+            }
+        }
+        zip.finish()?;
+
+        Ok(zip_path)
+    }
+
+    pub fn get_zip_path(&self, project: &str) -> Result<PathBuf, String> {
+        Ok(PathBuf::from(format!("{}.zip", self.get_project_path(project)?.display())))
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::sandbox::{resolve_workdir, seccomp, Sandbox};
+
+static FILTER_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// `bwrap` with every namespace unshared, `/` bound read-only, a private `/tmp`,
+/// `/dev` and `/proc`, and the default seccomp filter passed in through a file
+/// descriptor.
+pub struct Bubblewrap;
+
+impl Bubblewrap {
+    /// bwrap reads the filter from an inherited descriptor. The backing file is
+    /// unlinked right away, so only the open descriptor keeps it alive.
+    fn seccomp_file() -> Result<File, String> {
+        let path = std::env::temp_dir().join(format!(
+            "devika-seccomp-{}-{}",
+            std::process::id(),
+            FILTER_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut file = File::create(&path).map_err(|e| e.to_string())?;
+        file.write_all(&seccomp::to_bytes(&seccomp::default_filter())).map_err(|e| e.to_string())?;
+        let reader = File::open(&path).map_err(|e| e.to_string());
+        let _ = fs::remove_file(&path);
+        reader
+    }
+}
+
+impl Sandbox for Bubblewrap {
+    fn name(&self) -> &'static str {
+        "bubblewrap"
+    }
+
+    fn available(&self) -> bool {
+        Command::new("bwrap")
+            .args(["--ro-bind", "/", "/", "--unshare-all", "--", "true"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    fn command(&self, argv: &[String], workdir: &Path, allow_network: bool) -> Result<Command, String> {
+        Self::build(argv, workdir, allow_network, true)
+    }
+
+    /// Without `--new-session`, which would take the shell's controlling terminal
+    /// away. The seccomp filter refuses `TIOCSTI` either way, and that is what the
+    /// new session guards against.
+    fn terminal_command(&self, argv: &[String], workdir: &Path, allow_network: bool) -> Result<Command, String> {
+        Self::build(argv, workdir, allow_network, false)
+    }
+}
+
+impl Bubblewrap {
+    fn build(argv: &[String], workdir: &Path, allow_network: bool, new_session: bool) -> Result<Command, String> {
+        if argv.is_empty() {
+            return Err("Empty command".to_string());
+        }
+        let workdir = resolve_workdir(workdir)?;
+        let filter = Self::seccomp_file()?;
+        let filter_fd = filter.as_raw_fd();
+
+        let mut command = Command::new("bwrap");
+        command.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"])
+            .arg("--bind").arg(&workdir).arg(&workdir)
+            .arg("--chdir").arg(&workdir)
+            .args(["--unshare-all", "--die-with-parent", "--cap-drop", "ALL"])
+            .args(["--seccomp", &filter_fd.to_string()]);
+        if new_session {
+            command.arg("--new-session");
+        }
+        if allow_network {
+            command.arg("--share-net");
+        }
+        command.arg("--").args(argv);
+
+        unsafe {
+            command.pre_exec(move || {
+                // Keep the filter open across exec; `filter` lives as long as the command.
+                if libc::fcntl(filter.as_raw_fd(), libc::F_SETFD, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(command)
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::prelude::*;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::agents::control::project_key;
+use crate::config::Config;
+use crate::filesystem::project_path;
+use crate::sandbox::{apply_limits, get_sandbox, kill_leftover_processes, kill_process_group, minimal_env, Sandbox, SandboxPolicy};
+use crate::socket_instance::emit_agent;
+use crate::state::AgentState;
+
+/// Finished executions are kept around for polling, up to this many.
+const MAX_EXECUTIONS: usize = 100;
+/// Per stream; anything past this is dropped and noted once.
+pub const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+/// How long to wait for the output once the process is gone. A process that left
+/// its process group can keep the pipes open for as long as it likes.
+const OUTPUT_GRACE: Duration = Duration::from_secs(2);
+
+static EXECUTIONS: Lazy<Mutex<BTreeMap<String, Execution>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+static EXECUTION_COUNTER: AtomicU32 = AtomicU32::new(0);
+/// Running executions to kill at their next poll.
+static CANCELLED_EXECUTIONS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Running,
+    Completed,
+    Failed,
+    TimedOut,
+    Cancelled,
+}
+
+/// One run of `/api/run-code`, as returned when polling.
+#[derive(Debug, Clone, Serialize)]
+pub struct Execution {
+    pub id: String,
+    pub project_name: String,
+    pub command: String,
+    pub status: ExecutionStatus,
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+pub fn get_execution(execution_id: &str) -> Option<Execution> {
+    EXECUTIONS.lock().unwrap().get(execution_id).cloned()
+}
+
+/// Kills the project's running executions, as when its agent run is cancelled.
+pub fn cancel_executions(project_name: &str) {
+    let running: Vec<String> = EXECUTIONS.lock().unwrap().values()
+        .filter(|execution| project_key(&execution.project_name) == project_key(project_name) && execution.status == ExecutionStatus::Running)
+        .map(|execution| execution.id.clone())
+        .collect();
+    CANCELLED_EXECUTIONS.lock().unwrap().extend(running);
+}
+
+/// Runs `code` (or, without it, the project's entrypoint) in a throwaway copy of
+/// the project, inside the sandbox its policy picks, and returns the execution id
+/// right away. Output is streamed as `terminal_session` events and collected for
+/// `get_execution`.
+pub fn start_execution(project_name: &str, code: Option<&str>, language: Option<&str>) -> Result<String, String> {
+    let config = Config::new().map_err(|e| e.to_string())?;
+    let policy = SandboxPolicy::for_project(&config, project_name);
+    let sandbox = get_sandbox(&policy.backend)?;
+    let project_path = project_path(project_name)?;
+
+    let execution_id = new_execution_id();
+    // A fresh directory only the server's user can enter, removed when dropped.
+    let prefix = format!("devika-run-{}-", execution_id);
+    let mut workdir = tempfile::Builder::new();
+    workdir.prefix(&prefix);
+    #[cfg(unix)]
+    workdir.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o700));
+    let workdir = workdir.tempdir()
+        .map_err(|e| e.to_string())?;
+    let command = prepare_workdir(&project_path, workdir.path(), code, language)?;
+
+    insert_execution(Execution {
+        id: execution_id.clone(),
+        project_name: project_name.to_string(),
+        command: shell_words::join(&command),
+        status: ExecutionStatus::Running,
+        stdout: String::new(),
+        stderr: String::new(),
+        exit_code: None,
+        started_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        finished_at: None,
+    });
+
+    let id = execution_id.clone();
+    thread::spawn(move || run_execution(&id, sandbox.as_ref(), &command, workdir.path(), &policy));
+
+    Ok(execution_id)
+}
+
+/// Time-ordered, so the map's first entries are the oldest executions.
+fn new_execution_id() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let counter = EXECUTION_COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    format!("{:016x}{:04x}", nanos as u64, counter)
+}
+
+fn insert_execution(execution: Execution) {
+    let mut executions = EXECUTIONS.lock().unwrap();
+    executions.insert(execution.id.clone(), execution);
+    while executions.len() > MAX_EXECUTIONS {
+        let oldest_finished = executions.iter()
+            .find(|(_, execution)| execution.status != ExecutionStatus::Running)
+            .map(|(id, _)| id.clone());
+        match oldest_finished {
+            Some(id) => executions.remove(&id),
+            None => break,
+        };
+    }
+}
+
+fn update_execution<F: FnOnce(&mut Execution)>(execution_id: &str, update: F) {
+    if let Some(execution) = EXECUTIONS.lock().unwrap().get_mut(execution_id) {
+        update(execution);
+    }
+}
+
+/// Copies the project into `workdir` and works out what to run there.
+fn prepare_workdir(project_path: &Path, workdir: &Path, code: Option<&str>, language: Option<&str>) -> Result<Vec<String>, String> {
+    if project_path.is_dir() {
+        copy_project(project_path, workdir)?;
+    }
+
+    match code.filter(|code| !code.trim().is_empty()) {
+        Some(code) => {
+            let (file, interpreter) = snippet_runner(language.unwrap_or("python"))?;
+            fs::write(workdir.join(file), code).map_err(|e| e.to_string())?;
+            Ok(vec![interpreter.to_string(), file.to_string()])
+        }
+        None => {
+            if !project_path.is_dir() {
+                return Err(format!("Project {} has no files to run", project_path.display()));
+            }
+            detect_entrypoint(workdir).ok_or_else(|| "Could not find an entrypoint to run in the project".to_string())
+        }
+    }
+}
+
+fn snippet_runner(language: &str) -> Result<(&'static str, &'static str), String> {
+    match language.trim().to_lowercase().as_str() {
+        "python" | "python3" | "py" => Ok(("__snippet__.py", "python3")),
+        "javascript" | "js" | "node" => Ok(("__snippet__.js", "node")),
+        "bash" | "sh" | "shell" => Ok(("__snippet__.sh", "sh")),
+        "ruby" | "rb" => Ok(("__snippet__.rb", "ruby")),
+        "php" => Ok(("__snippet__.php", "php")),
+        other => Err(format!("Running {} snippets is not supported", other)),
+    }
+}
+
+fn detect_entrypoint(workdir: &Path) -> Option<Vec<String>> {
+    let candidates: [(&str, &[&str]); 9] = [
+        ("main.py", &["python3", "main.py"]),
+        ("app.py", &["python3", "app.py"]),
+        ("index.js", &["node", "index.js"]),
+        ("main.js", &["node", "main.js"]),
+        ("package.json", &["npm", "start"]),
+        ("Cargo.toml", &["cargo", "run", "--offline"]),
+        ("main.go", &["go", "run", "."]),
+        ("main.rb", &["ruby", "main.rb"]),
+        ("main.sh", &["sh", "main.sh"]),
+    ];
+    candidates.iter()
+        .find(|(file, _)| workdir.join(file).is_file())
+        .map(|(_, command)| command.iter().map(|part| part.to_string()).collect())
+}
+
+/// Plain files and directories only: `.git` is left out and symlinks are skipped,
+/// since they could point anywhere on the host.
+fn copy_project(from: &Path, to: &Path) -> Result<(), String> {
+    let mut pending = vec![(from.to_path_buf(), to.to_path_buf())];
+    while let Some((source, target)) = pending.pop() {
+        fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+        for entry in fs::read_dir(&source).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let file_type = entry.file_type().map_err(|e| e.to_string())?;
+            if entry.file_name() == ".git" || file_type.is_symlink() {
+                continue;
+            }
+            let target_path = target.join(entry.file_name());
+            if file_type.is_dir() {
+                pending.push((entry.path(), target_path));
+            } else if file_type.is_file() {
+                fs::copy(entry.path(), &target_path).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_execution(execution_id: &str, sandbox: &dyn Sandbox, command: &[String], workdir: &Path, policy: &SandboxPolicy) {
+    let mut child = match spawn_sandboxed(sandbox, command, workdir, policy) {
+        Ok(child) => child,
+        Err(e) => {
+            finish_execution(execution_id, ExecutionStatus::Failed, None, Some(format!("Could not start the process: {}", e)));
+            return;
+        }
+    };
+
+    let readers: Vec<_> = [("stdout", child.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>)),
+                           ("stderr", child.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>))]
+        .into_iter()
+        .filter_map(|(stream, pipe)| pipe.map(|pipe| (stream, pipe)))
+        .map(|(stream, pipe)| {
+            let id = execution_id.to_string();
+            thread::spawn(move || stream_output(&id, stream, pipe))
+        })
+        .collect();
+
+    let deadline = Instant::now() + policy.timeout;
+    let (status, exit_code, note) = loop {
+        match try_wait_with_cpu_time(&mut child) {
+            Ok(Some((exit, cpu_time))) => {
+                let note = cpu_limit_note(&exit, cpu_time, policy.cpu_time);
+                let status = if exit.success() { ExecutionStatus::Completed } else { ExecutionStatus::Failed };
+                break (status, exit.code(), note);
+            }
+            Ok(None) if CANCELLED_EXECUTIONS.lock().unwrap().remove(execution_id) => {
+                kill_process_group(&mut child);
+                break (ExecutionStatus::Cancelled, None, Some("Killed because the run was cancelled".to_string()));
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                kill_process_group(&mut child);
+                let note = format!("Killed after the {}s time limit", policy.timeout.as_secs());
+                break (ExecutionStatus::TimedOut, None, Some(note));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => {
+                kill_process_group(&mut child);
+                break (ExecutionStatus::Failed, None, Some(e.to_string()));
+            }
+        }
+    };
+
+    // Background jobs outlive a process that exited on its own, and keep the pipes open.
+    kill_leftover_processes(child.id());
+    let output_deadline = Instant::now() + OUTPUT_GRACE;
+    while readers.iter().any(|reader| !reader.is_finished()) && Instant::now() < output_deadline {
+        thread::sleep(Duration::from_millis(50));
+    }
+    let mut note = note;
+    if readers.iter().any(|reader| !reader.is_finished()) {
+        let detached = "Stopped reading output: a process that left the process group still holds it open".to_string();
+        note = Some(note.map_or(detached.clone(), |note| format!("{}\n{}", note, detached)));
+    }
+    // Readers still blocked on a pipe are left behind; they end with the process holding it.
+    for reader in readers.into_iter().filter(|reader| reader.is_finished()) {
+        let _ = reader.join();
+    }
+    CANCELLED_EXECUTIONS.lock().unwrap().remove(execution_id);
+    finish_execution(execution_id, status, exit_code, note);
+}
+
+fn stream_output(execution_id: &str, stream: &str, mut pipe: Box<dyn Read + Send>) {
+    let mut buffer = [0u8; 4096];
+    let mut truncated = false;
+    loop {
+        let read = match pipe.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        let chunk = String::from_utf8_lossy(&buffer[..read]).into_owned();
+
+        update_execution(execution_id, |execution| {
+            let output = if stream == "stdout" { &mut execution.stdout } else { &mut execution.stderr };
+            if output.len() + chunk.len() <= MAX_OUTPUT_BYTES {
+                output.push_str(&chunk);
+            } else if !truncated {
+                output.push_str("\n[output truncated]\n");
+                truncated = true;
+            }
+        });
+        emit_agent("terminal_session", json!({
+            "execution_id": execution_id,
+            "stream": stream,
+            "output": chunk,
+        }));
+    }
+}
+
+fn finish_execution(execution_id: &str, status: ExecutionStatus, exit_code: Option<i32>, note: Option<String>) {
+    let mut finished = None;
+    update_execution(execution_id, |execution| {
+        execution.status = status;
+        execution.exit_code = exit_code;
+        execution.finished_at = Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+        if let Some(note) = note {
+            if !execution.stderr.is_empty() && !execution.stderr.ends_with('\n') {
+                execution.stderr.push('\n');
+            }
+            execution.stderr.push_str(&note);
+        }
+        finished = Some(execution.clone());
+    });
+    let Some(execution) = finished else { return };
+
+    let agent_state = AgentState::new(Config::new().unwrap().get_agent_state_db());
+    let mut new_state = AgentState::new_state();
+    if let Some(current_state) = agent_state.get_latest_state(&execution.project_name) {
+        new_state["browser_session"] = current_state["browser_session"].clone(); // keep the browser session
+    }
+    new_state["internal_monologue"] = json!("Running code...");
+    new_state["terminal_session"]["title"] = json!("Terminal");
+    new_state["terminal_session"]["command"] = json!(execution.command);
+    new_state["terminal_session"]["output"] = json!(format!("{}{}", execution.stdout, execution.stderr));
+    new_state["terminal_session"]["exit_code"] = json!(execution.exit_code);
+    new_state["terminal_session"]["execution_id"] = json!(execution.id);
+    agent_state.add_to_current_state(&execution.project_name, &new_state);
+
+    emit_agent("terminal_session", json!({
+        "execution_id": execution.id,
+        "status": execution.status,
+        "exit_code": execution.exit_code,
+        "done": true,
+    }));
+}
+
+/// `Child::try_wait`, plus the CPU time the process and the children it waited for
+/// used, which is what `RLIMIT_CPU` counts.
+#[cfg(unix)]
+fn try_wait_with_cpu_time(child: &mut Child) -> std::io::Result<Option<(ExitStatus, Duration)>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    match unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, libc::WNOHANG, &mut usage) } {
+        0 => Ok(None),
+        pid if pid < 0 => Err(std::io::Error::last_os_error()),
+        _ => {
+            let duration = |time: libc::timeval| Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64);
+            Ok(Some((ExitStatus::from_raw(status), duration(usage.ru_utime) + duration(usage.ru_stime))))
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn try_wait_with_cpu_time(child: &mut Child) -> std::io::Result<Option<(ExitStatus, Duration)>> {
+    Ok(child.try_wait()?.map(|exit| (exit, Duration::ZERO)))
+}
+
+/// The CPU limit is only blamed when the CPU time reached it: the kernel also kills
+/// with SIGKILL when memory runs out.
+#[cfg(unix)]
+fn cpu_limit_note(exit: &ExitStatus, cpu_time: Duration, cpu_limit: u64) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+    if !exit.success() && cpu_time >= Duration::from_secs(cpu_limit) {
+        return Some("Killed after reaching the CPU time limit".to_string());
+    }
+    exit.signal().map(|signal| format!("Killed by signal {}", signal))
+}
+
+#[cfg(not(unix))]
+fn cpu_limit_note(_exit: &ExitStatus, _cpu_time: Duration, _cpu_limit: u64) -> Option<String> {
+    None
+}
+
+fn spawn_sandboxed(sandbox: &dyn Sandbox, command: &[String], workdir: &Path, policy: &SandboxPolicy) -> Result<Child, String> {
+    let mut process = sandbox.command(command, workdir, policy.allow_network)?;
+    minimal_env(&mut process, workdir);
+    process.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    apply_limits(&mut process, policy);
+
+    process.spawn().map_err(|e| format!("{} (sandbox: {})", e, sandbox.name()))
+}
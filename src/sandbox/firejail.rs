@@ -0,0 +1,40 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::sandbox::{resolve_workdir, Sandbox};
+
+/// `firejail` without a profile: everything read-only except the working
+/// directory, all capabilities dropped and firejail's own default seccomp filter.
+pub struct Firejail;
+
+impl Sandbox for Firejail {
+    fn name(&self) -> &'static str {
+        "firejail"
+    }
+
+    fn available(&self) -> bool {
+        Command::new("firejail")
+            .args(["--quiet", "--noprofile", "--", "true"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    fn command(&self, argv: &[String], workdir: &Path, allow_network: bool) -> Result<Command, String> {
+        if argv.is_empty() {
+            return Err("Empty command".to_string());
+        }
+        let workdir = resolve_workdir(workdir)?;
+
+        let mut command = Command::new("firejail");
+        command.args(["--quiet", "--noprofile", "--read-only=/", "--seccomp", "--caps.drop=all", "--nonewprivs", "--nogroups"])
+            .arg(format!("--read-write={}", workdir.display()));
+        if !allow_network {
+            command.arg("--net=none");
+        }
+        command.arg("--").args(argv).current_dir(&workdir);
+        Ok(command)
+    }
+}
@@ -0,0 +1,210 @@
+pub mod code_runner;
+
+#[cfg(target_os = "linux")]
+pub mod bubblewrap;
+#[cfg(target_os = "linux")]
+pub mod firejail;
+#[cfg(target_os = "linux")]
+pub mod seccomp;
+#[cfg(target_os = "linux")]
+pub mod userns;
+
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use crate::config::Config;
+
+/// Everything that decides how one project's code is run. Read from the
+/// `[EXECUTION]` section, with `[EXECUTION.PROJECTS.<project>]` overrides.
+#[derive(Debug, Clone)]
+pub struct SandboxPolicy {
+    /// `auto`, `bubblewrap`, `firejail`, `userns` or `none`.
+    pub backend: String,
+    pub allow_network: bool,
+    pub timeout: Duration,
+    pub cpu_time: u64,
+    pub memory_bytes: u64,
+}
+
+impl SandboxPolicy {
+    pub fn for_project(config: &Config, project_name: &str) -> Self {
+        Self {
+            backend: config.get_execution_sandbox(project_name).to_string(),
+            allow_network: config.get_execution_allow_network(project_name),
+            timeout: Duration::from_secs(config.get_execution_timeout(project_name)),
+            cpu_time: config.get_execution_cpu_time(project_name),
+            memory_bytes: config.get_execution_memory_mb(project_name) * 1024 * 1024,
+        }
+    }
+}
+
+/// Wraps a command so that it can only write to `workdir`, sees the rest of the
+/// filesystem read-only and, unless `allow_network`, has no network.
+pub trait Sandbox: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend works on this host, not just whether it is installed.
+    fn available(&self) -> bool;
+
+    /// The returned command still needs its stdio and environment set up by the caller.
+    fn command(&self, argv: &[String], workdir: &Path, allow_network: bool) -> Result<Command, String>;
+
+    /// Like `command`, for a shell on a PTY: the caller makes the PTY its controlling
+    /// terminal, so the backend must not start a new session of its own.
+    fn terminal_command(&self, argv: &[String], workdir: &Path, allow_network: bool) -> Result<Command, String> {
+        self.command(argv, workdir, allow_network)
+    }
+}
+
+/// No isolation at all, for trusted local use. Only ever picked by name.
+pub struct Unconfined;
+
+impl Sandbox for Unconfined {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn available(&self) -> bool {
+        true
+    }
+
+    fn command(&self, argv: &[String], workdir: &Path, _allow_network: bool) -> Result<Command, String> {
+        let (program, args) = argv.split_first().ok_or("Empty command")?;
+        let mut command = Command::new(program);
+        command.args(args).current_dir(workdir);
+        Ok(command)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn backend(name: &str) -> Option<Box<dyn Sandbox>> {
+    match name {
+        "bubblewrap" | "bwrap" => Some(Box::new(bubblewrap::Bubblewrap)),
+        "firejail" => Some(Box::new(firejail::Firejail)),
+        "userns" => Some(Box::new(userns::UserNamespace)),
+        "none" => Some(Box::new(Unconfined)),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn backend(name: &str) -> Option<Box<dyn Sandbox>> {
+    match name {
+        "none" => Some(Box::new(Unconfined)),
+        _ => None,
+    }
+}
+
+/// Probing runs a process per backend, so `auto` is only resolved once.
+static AUTO_BACKEND: Lazy<Option<&'static str>> = Lazy::new(|| {
+    ["bubblewrap", "firejail", "userns"].into_iter()
+        .find(|name| backend(name).is_some_and(|sandbox| sandbox.available()))
+});
+
+/// Resolves a policy's backend. There is no silent fallback to `none`: if nothing
+/// that isolates works here, running has to be allowed explicitly.
+pub fn get_sandbox(name: &str) -> Result<Box<dyn Sandbox>, String> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name == "auto" {
+        let found = AUTO_BACKEND.ok_or("No sandbox backend works on this host (tried bubblewrap, firejail and user namespaces); set EXECUTION.SANDBOX to \"none\" to run unconfined")?;
+        return backend(found).ok_or_else(|| format!("Unknown sandbox backend: {}", found));
+    }
+
+    let sandbox = backend(&name).ok_or_else(|| format!("Unknown or unsupported sandbox backend: {}", name))?;
+    if !sandbox.available() {
+        return Err(format!("The {} sandbox is not available on this host", sandbox.name()));
+    }
+    Ok(sandbox)
+}
+
+/// Backends bind-mount `workdir` by path, so it has to be absolute and resolved.
+pub fn resolve_workdir(workdir: &Path) -> Result<PathBuf, String> {
+    workdir.canonicalize().map_err(|e| format!("{}: {}", workdir.display(), e))
+}
+
+/// Resets the environment to the bare minimum so no server secrets leak in. `HOME`
+/// points at the working directory, the only place tools can keep caches.
+pub fn minimal_env(command: &mut Command, workdir: &Path) {
+    command.env_clear()
+        .env("PATH", std::env::var("PATH").unwrap_or_else(|_| "/usr/local/bin:/usr/bin:/bin".to_string()))
+        .env("HOME", workdir)
+        .env("LANG", "C.UTF-8");
+}
+
+/// Puts the process in its own process group, so the whole tree can be killed, and
+/// applies the CPU, memory and file size limits of `policy`.
+#[cfg(unix)]
+pub fn apply_limits(command: &mut Command, policy: &SandboxPolicy) {
+    use std::os::unix::process::CommandExt;
+
+    /// Largest file the process may write.
+    const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+    let rlimits = [
+        (libc::RLIMIT_CPU, policy.cpu_time),
+        (libc::RLIMIT_AS, policy.memory_bytes),
+        (libc::RLIMIT_FSIZE, MAX_FILE_SIZE),
+        (libc::RLIMIT_CORE, 0),
+    ];
+    // Set before any of the backend's own setup runs, so a backend that forks in
+    // between, like userns, keeps the process the server waits on in the group.
+    command.process_group(0);
+    unsafe {
+        command.pre_exec(move || {
+            for (resource, limit) in rlimits {
+                let rlimit = libc::rlimit { rlim_cur: limit as libc::rlim_t, rlim_max: limit as libc::rlim_t };
+                if libc::setrlimit(resource, &rlimit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn apply_limits(_command: &mut Command, _policy: &SandboxPolicy) {}
+
+/// Puts the process in its own process group, so the whole tree can be killed.
+#[cfg(unix)]
+pub fn own_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+}
+
+#[cfg(not(unix))]
+pub fn own_process_group(_command: &mut Command) {}
+
+/// Kills the process and, on Unix, the rest of its process group. The process itself
+/// is killed too in case it doesn't lead a group, like a PTY shell in a session of
+/// its own.
+#[cfg(unix)]
+pub fn kill_process_group(child: &mut Child) {
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(not(unix))]
+pub fn kill_process_group(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Kills what is left of the process group of a process that has already exited,
+/// such as background jobs it started.
+#[cfg(unix)]
+pub fn kill_leftover_processes(pid: u32) {
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn kill_leftover_processes(_pid: u32) {}
@@ -0,0 +1,123 @@
+//! The default seccomp filter shared by the bubblewrap and userns backends. It does
+//! not try to be a full allowlist; it refuses the syscalls a build or a test run
+//! never needs and that are the usual way out of a namespace sandbox.
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+/// Syscalls of the x32 ABI carry this bit; they would bypass the x86_64 numbers below.
+#[cfg(target_arch = "x86_64")]
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    // The newer mount API; any of these could undo the read-only remounts.
+    libc::SYS_fsopen,
+    libc::SYS_fsconfig,
+    libc::SYS_fsmount,
+    libc::SYS_move_mount,
+    libc::SYS_open_tree,
+    libc::SYS_mount_setattr,
+    libc::SYS_setns,
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_kexec_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_acct,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_userfaultfd,
+    libc::SYS_open_by_handle_at,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_settimeofday,
+    libc::SYS_clock_settime,
+    libc::SYS_syslog,
+    libc::SYS_quotactl,
+];
+
+fn statement(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+}
+
+fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt, jf, k }
+}
+
+/// Denied syscalls fail with EPERM rather than killing the process, so tools that
+/// probe for them degrade gracefully. A foreign architecture is killed outright.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn default_filter() -> Vec<libc::sock_filter> {
+    let deny = libc::SECCOMP_RET_ERRNO | libc::EPERM as u32;
+
+    let mut filter = vec![
+        statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 4), // seccomp_data.arch
+        jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, AUDIT_ARCH, 1, 0),
+        statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 0), // seccomp_data.nr
+    ];
+    #[cfg(target_arch = "x86_64")]
+    {
+        filter.push(jump(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, X32_SYSCALL_BIT, 0, 1));
+        filter.push(statement(libc::BPF_RET | libc::BPF_K, deny));
+    }
+    for syscall in DENIED_SYSCALLS {
+        filter.push(jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, *syscall as u32, 0, 1));
+        filter.push(statement(libc::BPF_RET | libc::BPF_K, deny));
+    }
+    // ioctls that push input into the terminal, which a sandboxed process sharing the
+    // user's PTY could use to type commands outside the sandbox.
+    filter.push(jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, libc::SYS_ioctl as u32, 0, 4));
+    filter.push(statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 24)); // low half of seccomp_data.args[1]
+    filter.push(jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, libc::TIOCSTI as u32, 1, 0));
+    filter.push(jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, libc::TIOCLINUX as u32, 0, 1));
+    filter.push(statement(libc::BPF_RET | libc::BPF_K, deny));
+    filter.push(statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+    filter
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn default_filter() -> Vec<libc::sock_filter> {
+    vec![]
+}
+
+/// The raw program, as bubblewrap reads it from `--seccomp <fd>`.
+pub fn to_bytes(filter: &[libc::sock_filter]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(filter.len() * 8);
+    for instruction in filter {
+        bytes.extend_from_slice(&instruction.code.to_ne_bytes());
+        bytes.push(instruction.jt);
+        bytes.push(instruction.jf);
+        bytes.extend_from_slice(&instruction.k.to_ne_bytes());
+    }
+    bytes
+}
+
+/// Installs `filter` on the calling process. Only raw syscalls, so it is safe to
+/// call between fork and exec.
+pub fn install(filter: &mut [libc::sock_filter]) -> std::io::Result<()> {
+    if filter.is_empty() {
+        return Ok(());
+    }
+    let program = libc::sock_fprog { len: filter.len() as u16, filter: filter.as_mut_ptr() };
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
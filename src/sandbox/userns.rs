@@ -0,0 +1,253 @@
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::sandbox::{resolve_workdir, seccomp, Sandbox};
+
+/// No external tool: the child unshares user, mount, PID, IPC and UTS namespaces (and
+/// the network namespace unless allowed), runs the command under a minimal init with
+/// a `/proc` of its own, remounts every mount read-only except a bind mount of the
+/// working directory, and installs the default seccomp filter. Where `/proc` can't
+/// be mounted, the backend is not available.
+pub struct UserNamespace;
+
+/// A mount point and the flags a read-only remount has to keep; mounts inherited
+/// into a new user namespace are locked and refuse to lose nosuid/nodev/noexec.
+struct MountPoint {
+    path: CString,
+    flags: libc::c_ulong,
+}
+
+/// `struct __user_cap_header_struct`; libc has no wrapper for `capset`.
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+/// `struct __user_cap_data_struct`; version 3 takes two of them, for 64 capabilities.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// Closes everything but stdio in a process that only waits. Among them is the pipe
+/// that tells the server whether the exec worked, which would otherwise stay open
+/// until the command ends.
+unsafe fn close_inherited_fds() {
+    if libc::syscall(libc::SYS_close_range, 3, libc::c_uint::MAX, 0) != 0 {
+        for fd in 3..libc::sysconf(libc::_SC_OPEN_MAX).max(3) as libc::c_int {
+            libc::close(fd);
+        }
+    }
+}
+
+/// Reaps children until `child` ends, then exits the way it did. A signal is raised
+/// again where that works, and becomes exit code 128 + signal otherwise, like a
+/// shell reports it. Only raw syscalls, as it runs between fork and exec.
+unsafe fn exit_with(child: libc::pid_t) -> ! {
+    let mut status = 0;
+    loop {
+        let pid = libc::waitpid(-1, &mut status, 0);
+        if pid == child {
+            break;
+        }
+        if pid < 0 && std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            libc::_exit(127);
+        }
+    }
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        libc::signal(signal, libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal);
+        libc::_exit(128 + signal);
+    }
+    libc::_exit(libc::WEXITSTATUS(status))
+}
+
+/// `/proc/self/mountinfo` escapes spaces and the like as `\040`.
+fn unescape_mount_path(path: &str) -> PathBuf {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            if let Ok(value) = u8::from_str_radix(&path[i + 1..i + 4], 8) {
+                decoded.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    PathBuf::from(std::ffi::OsStr::from_bytes(&decoded))
+}
+
+fn mount_points(workdir: &Path) -> Result<Vec<MountPoint>, String> {
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").map_err(|e| e.to_string())?;
+    let mut mounts = vec![];
+    for line in mountinfo.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (Some(path), Some(options)) = (fields.get(4), fields.get(5)) else { continue };
+        let path = unescape_mount_path(path);
+        if path.starts_with(workdir) {
+            continue;
+        }
+
+        let mut flags = libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY;
+        for option in options.split(',') {
+            flags |= match option {
+                "nosuid" => libc::MS_NOSUID,
+                "nodev" => libc::MS_NODEV,
+                "noexec" => libc::MS_NOEXEC,
+                "noatime" => libc::MS_NOATIME,
+                "nodiratime" => libc::MS_NODIRATIME,
+                "relatime" => libc::MS_RELATIME,
+                _ => 0,
+            };
+        }
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+        mounts.push(MountPoint { path, flags });
+    }
+    Ok(mounts)
+}
+
+impl Sandbox for UserNamespace {
+    fn name(&self) -> &'static str {
+        "userns"
+    }
+
+    fn available(&self) -> bool {
+        let Ok(workdir) = std::env::current_dir() else { return false };
+        self.command(&["true".to_string()], &workdir, false)
+            .and_then(|mut command| {
+                command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+                    .status()
+                    .map_err(|e| e.to_string())
+            })
+            .is_ok_and(|status| status.success())
+    }
+
+    fn command(&self, argv: &[String], workdir: &Path, allow_network: bool) -> Result<Command, String> {
+        let (program, args) = argv.split_first().ok_or("Empty command")?;
+        let workdir = resolve_workdir(workdir)?;
+
+        // Everything the child needs is prepared before the fork; only raw syscalls run after it.
+        let root = CString::new("/").unwrap();
+        let proc_path = CString::new("/proc").unwrap();
+        let proc_type = CString::new("proc").unwrap();
+        let workdir_path = CString::new(workdir.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+        let mounts = mount_points(&workdir)?;
+        let id_maps = [
+            (CString::new("/proc/self/setgroups").unwrap(), "deny".to_string()),
+            (CString::new("/proc/self/uid_map").unwrap(), format!("{0} {0} 1", unsafe { libc::getuid() })),
+            (CString::new("/proc/self/gid_map").unwrap(), format!("{0} {0} 1", unsafe { libc::getgid() })),
+        ];
+        let mut filter = seccomp::default_filter();
+        let mut namespaces = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWPID | libc::CLONE_NEWIPC | libc::CLONE_NEWUTS;
+        if !allow_network {
+            namespaces |= libc::CLONE_NEWNET;
+        }
+
+        let mut command = Command::new(program);
+        command.args(args).current_dir(&workdir);
+        unsafe {
+            command.pre_exec(move || {
+                let fail = || -> std::io::Result<()> { Err(std::io::Error::last_os_error()) };
+
+                if libc::unshare(namespaces) != 0 {
+                    return fail();
+                }
+                for (path, contents) in &id_maps {
+                    let fd = libc::open(path.as_ptr(), libc::O_WRONLY);
+                    if fd < 0 {
+                        return fail();
+                    }
+                    let written = libc::write(fd, contents.as_ptr() as *const libc::c_void, contents.len());
+                    libc::close(fd);
+                    if written < 0 {
+                        return fail();
+                    }
+                }
+
+                // Only a child joins the new PID namespace. This process stays outside
+                // it, waits for the child and exits the way it did, so the server
+                // still sees the command's status.
+                let child = libc::fork();
+                if child < 0 {
+                    return fail();
+                }
+                if child > 0 {
+                    close_inherited_fds();
+                    exit_with(child);
+                }
+                // Killing the waiting process, which is all the server can see, takes
+                // the whole namespace down with it. Until then the child is still in its
+                // process group, which is what the server kills first.
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) != 0 {
+                    return fail();
+                }
+                // PID 1 ignores signals it has no handler for, even SIGKILL from inside
+                // the namespace, and inherits every orphan. It stays a plain init that
+                // reaps them and runs the command as its own child.
+                let workload = libc::fork();
+                if workload < 0 {
+                    return fail();
+                }
+                if workload > 0 {
+                    close_inherited_fds();
+                    exit_with(workload);
+                }
+
+                // Keep the remounts below from propagating back to the host.
+                if libc::mount(std::ptr::null(), root.as_ptr(), std::ptr::null(), libc::MS_REC | libc::MS_PRIVATE, std::ptr::null()) != 0 {
+                    return fail();
+                }
+                // The working directory becomes its own mount first, so the read-only
+                // remounts of the mounts above it leave it writable.
+                if libc::mount(workdir_path.as_ptr(), workdir_path.as_ptr(), std::ptr::null(), libc::MS_BIND | libc::MS_REC, std::ptr::null()) != 0 {
+                    return fail();
+                }
+                // The host's /proc would show, and let the command read the
+                // environment of, every process of the server's user.
+                if libc::mount(proc_type.as_ptr(), proc_path.as_ptr(), proc_type.as_ptr(), libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC, std::ptr::null()) != 0 {
+                    return fail();
+                }
+                for mount in &mounts {
+                    let result = libc::mount(std::ptr::null(), mount.path.as_ptr(), std::ptr::null(), mount.flags, std::ptr::null());
+                    // Some pseudo filesystems refuse a remount; the root must not.
+                    if result != 0 && mount.path.as_bytes() == b"/" {
+                        return fail();
+                    }
+                }
+                if libc::chdir(workdir_path.as_ptr()) != 0 {
+                    return fail();
+                }
+
+                // The mounts were the last thing that needed capabilities. The process
+                // owns the namespace and would keep them all across exec otherwise.
+                let header = CapHeader { version: LINUX_CAPABILITY_VERSION_3, pid: 0 };
+                let data = [CapData::default(); 2];
+                if libc::syscall(libc::SYS_capset, &header as *const CapHeader, data.as_ptr()) != 0 {
+                    return fail();
+                }
+                // Nothing exec'd from here on can gain privileges back, setuid binaries included.
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                    return fail();
+                }
+
+                seccomp::install(&mut filter)
+            });
+        }
+        Ok(command)
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::config::Config;
+use crate::filesystem::project_path;
+
+/// Where build tools put a static site, in the order they are tried.
+const BUILD_DIRS: &[&str] = &["dist", "build", "out", "public", "_site"];
+
+/// Never shipped when the project root itself is the site.
+const EXCLUDED_DIRS: &[&str] = &["node_modules", "target", "__pycache__", "venv"];
+
+/// How long to wait for Netlify to finish processing an upload.
+const NETLIFY_READY_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Deployment {
+    /// Name of the deployer that published it.
+    pub target: &'static str,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deploy_id: Option<String>,
+}
+
+/// Publishes a project's static build output somewhere it can be opened in a browser.
+pub trait Deployer {
+    fn name(&self) -> &'static str;
+    fn deploy(&self, project_name: &str) -> Result<Deployment, String>;
+}
+
+/// Picks the deployer for `[DEPLOY] TARGET`. Anything unknown deploys locally, so
+/// deploying works without an account or a network.
+pub fn get_deployer(target: &str) -> Result<Box<dyn Deployer>, String> {
+    Ok(match target.to_lowercase().as_str() {
+        "netlify" => Box::new(NetlifyDeployer::new()?),
+        _ => Box::new(LocalDeployer),
+    })
+}
+
+/// The directory holding the project's site: the first of `BUILD_DIRS` with an
+/// `index.html`, or the project itself when the HTML sits at its root.
+pub fn build_output(project_name: &str) -> Result<PathBuf, String> {
+    let project_path = project_path(project_name)?;
+    if !project_path.is_dir() {
+        return Err(format!("Project not found: {}", project_name));
+    }
+    BUILD_DIRS.iter()
+        .map(|dir| project_path.join(dir))
+        .chain([project_path.clone()])
+        .find(|dir| dir.join("index.html").is_file())
+        .ok_or_else(|| format!(
+            "{} has no index.html to deploy; build the project first (looked in {} and the project root)",
+            project_name,
+            BUILD_DIRS.join(", "),
+        ))
+}
+
+/// Serves the build output from this server under `/preview/<project>/`.
+pub struct LocalDeployer;
+
+impl Deployer for LocalDeployer {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn deploy(&self, project_name: &str) -> Result<Deployment, String> {
+        build_output(project_name)?;
+        Ok(Deployment {
+            target: self.name(),
+            url: format!("http://127.0.0.1:1337/preview/{}/", project_name.to_lowercase().replace(' ', "-")),
+            site_id: None,
+            deploy_id: None,
+        })
+    }
+}
+
+/// Uploads the build output as a zip to a Netlify site, creating the site on the
+/// project's first deploy.
+pub struct NetlifyDeployer {
+    client: reqwest::blocking::Client,
+    api_key: String,
+    api_endpoint: String,
+}
+
+impl NetlifyDeployer {
+    pub fn new() -> Result<Self, String> {
+        let config = Config::new().map_err(|e| e.to_string())?;
+        Ok(Self {
+            client: reqwest::blocking::Client::new(),
+            api_key: config.get_netlify_api_key().to_string(),
+            api_endpoint: config.get_netlify_api_endpoint().trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn request(&self, request: reqwest::blocking::RequestBuilder) -> Result<Value, String> {
+        let response = request.bearer_auth(&self.api_key).send().map_err(|e| format!("Could not reach Netlify: {}", e))?;
+        let status = response.status();
+        let body: Value = response.json().unwrap_or_default();
+        if !status.is_success() {
+            let message = body["message"].as_str().or_else(|| body["error_message"].as_str()).unwrap_or_default();
+            return Err(format!("Netlify answered {}: {}", status.as_u16(), message));
+        }
+        Ok(body)
+    }
+
+    fn create_site(&self) -> Result<String, String> {
+        let site = self.request(self.client.post(format!("{}/sites", self.api_endpoint)).json(&json!({})))?;
+        site["id"].as_str().map(str::to_string).ok_or_else(|| format!("Netlify did not return a site id: {}", site))
+    }
+
+    fn upload(&self, site_id: &str, archive: Vec<u8>) -> Result<Value, String> {
+        self.request(self.client.post(format!("{}/sites/{}/deploys", self.api_endpoint, site_id))
+            .header("Content-Type", "application/zip")
+            .body(archive))
+    }
+
+    /// Netlify processes a zip after accepting it; the deploy is live once it is `ready`.
+    /// Not being ready within `NETLIFY_READY_TIMEOUT` is an error.
+    fn wait_until_ready(&self, mut deploy: Value) -> Result<Value, String> {
+        let started = Instant::now();
+        loop {
+            match deploy["state"].as_str().unwrap_or_default() {
+                "ready" => return Ok(deploy),
+                "error" => {
+                    return Err(format!("Netlify could not publish the deploy: {}", deploy["error_message"].as_str().unwrap_or("unknown error")));
+                }
+                state if started.elapsed() > NETLIFY_READY_TIMEOUT => {
+                    return Err(format!(
+                        "Netlify deploy {} is still `{}` after {}s; check the Netlify dashboard",
+                        deploy["id"].as_str().unwrap_or_default(),
+                        state,
+                        NETLIFY_READY_TIMEOUT.as_secs(),
+                    ));
+                }
+                _ => {}
+            }
+            thread::sleep(Duration::from_secs(2));
+            let deploy_id = deploy["id"].as_str().unwrap_or_default().to_string();
+            deploy = self.request(self.client.get(format!("{}/deploys/{}", self.api_endpoint, deploy_id)))?;
+        }
+    }
+}
+
+impl Deployer for NetlifyDeployer {
+    fn name(&self) -> &'static str {
+        "netlify"
+    }
+
+    fn deploy(&self, project_name: &str) -> Result<Deployment, String> {
+        if self.api_key.is_empty() {
+            return Err("Set API_KEYS.NETLIFY to deploy to Netlify".to_string());
+        }
+        let archive = zip_directory(&build_output(project_name)?)?;
+
+        let saved_site = Config::new().ok().and_then(|config| config.get_deploy_netlify_site(project_name).cloned());
+        let (site_id, deploy) = match saved_site {
+            Some(site_id) => match self.upload(&site_id, archive.clone()) {
+                Ok(deploy) => (site_id, deploy),
+                // The site was deleted on Netlify's side; start over with a new one.
+                Err(e) if e.starts_with("Netlify answered 404") => {
+                    let site_id = self.create_site()?;
+                    (site_id.clone(), self.upload(&site_id, archive)?)
+                }
+                Err(e) => return Err(e),
+            },
+            None => {
+                let site_id = self.create_site()?;
+                (site_id.clone(), self.upload(&site_id, archive)?)
+            }
+        };
+        if let Ok(mut config) = Config::new() {
+            config.set_deploy_netlify_site(project_name, site_id.clone());
+        }
+
+        let deploy = self.wait_until_ready(deploy)?;
+        let url = ["ssl_url", "deploy_ssl_url", "url", "deploy_url"].iter()
+            .find_map(|key| deploy[*key].as_str().filter(|url| !url.is_empty()))
+            .ok_or_else(|| format!("Netlify did not return a URL for the deploy: {}", deploy))?;
+        Ok(Deployment {
+            target: self.name(),
+            url: url.to_string(),
+            site_id: Some(site_id),
+            deploy_id: deploy["id"].as_str().map(str::to_string),
+        })
+    }
+}
+
+/// Zips a directory with paths relative to it, leaving out hidden files and
+/// dependency directories.
+fn zip_directory(root: &Path) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(&directory).map_err(|e| e.to_string())?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(file_type) = entry.file_type() else { continue };
+            if name.starts_with('.') || file_type.is_symlink() {
+                continue;
+            }
+            let path = entry.path();
+            if file_type.is_dir() {
+                if !EXCLUDED_DIRS.contains(&name.as_str()) {
+                    pending.push(path);
+                }
+                continue;
+            }
+            let relative_path = path.strip_prefix(root).map_err(|e| e.to_string())?.to_string_lossy().replace('\\', "/");
+            let contents = fs::read(&path).map_err(|e| e.to_string())?;
+            zip.start_file(relative_path, options).map_err(|e| e.to_string())?;
+            zip.write_all(&contents).map_err(|e| e.to_string())?;
+        }
+    }
+    let archive = zip.finish().map_err(|e| e.to_string())?;
+    Ok(archive.into_inner())
+}
@@ -0,0 +1,249 @@
+use std::fmt::Write;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::services::git::Git;
+use crate::state::AgentState;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub url: String,
+}
+
+/// `owner/name` of a repository on a forge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repository {
+    pub owner: String,
+    pub name: String,
+}
+
+/// A code hosting service that pull requests can be opened on.
+pub trait Forge {
+    fn name(&self) -> &'static str;
+
+    /// Header git sends to authenticate pushes over HTTP(S).
+    fn push_auth_header(&self) -> Option<String>;
+
+    fn create_pull_request(&self, repository: &Repository, head: &str, base: &str, title: &str, body: &str) -> Result<PullRequest, String>;
+}
+
+/// GitHub for remotes on github.com, Gitea for remotes on the host of
+/// `API_ENDPOINTS.GITEA`. Tokens only go to the forge they belong to, so any other
+/// remote is refused.
+pub fn get_forge(remote_url: &str) -> Result<Box<dyn Forge>, String> {
+    let config = Config::new().map_err(|e| e.to_string())?;
+    let host = remote_host(remote_url).ok_or_else(|| format!("Could not tell the host of {}", remote_url))?;
+    if host.eq_ignore_ascii_case("github.com") {
+        return Ok(Box::new(GitHub::new(config.get_github_api_endpoint(), config.get_github_api_key())));
+    }
+
+    let api_endpoint = config.get_gitea_api_endpoint();
+    if api_endpoint.is_empty() {
+        return Err(format!("Set API_ENDPOINTS.GITEA to open pull requests for {}", remote_url));
+    }
+    if !remote_host(api_endpoint).is_some_and(|forge_host| forge_host.eq_ignore_ascii_case(host)) {
+        return Err(format!("{} is not on the Gitea server at {}, so its token won't be sent there", remote_url, api_endpoint));
+    }
+    Ok(Box::new(Gitea::new(api_endpoint, config.get_gitea_api_key())))
+}
+
+pub struct GitHub {
+    client: reqwest::blocking::Client,
+    api_endpoint: String,
+    token: String,
+}
+
+impl GitHub {
+    pub fn new(api_endpoint: &str, token: &str) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api_endpoint: api_endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+}
+
+impl Forge for GitHub {
+    fn name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    fn push_auth_header(&self) -> Option<String> {
+        if self.token.is_empty() {
+            return None;
+        }
+        Some(format!("Authorization: Basic {}", BASE64.encode(format!("x-access-token:{}", self.token))))
+    }
+
+    fn create_pull_request(&self, repository: &Repository, head: &str, base: &str, title: &str, body: &str) -> Result<PullRequest, String> {
+        let url = format!("{}/repos/{}/{}/pulls", self.api_endpoint, repository.owner, repository.name);
+        let request = self.client.post(url)
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "devika")
+            .json(&json!({"title": title, "head": head, "base": base, "body": body}));
+        send_pull_request(self.name(), request)
+    }
+}
+
+pub struct Gitea {
+    client: reqwest::blocking::Client,
+    api_endpoint: String,
+    token: String,
+}
+
+impl Gitea {
+    pub fn new(api_endpoint: &str, token: &str) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api_endpoint: api_endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+}
+
+impl Forge for Gitea {
+    fn name(&self) -> &'static str {
+        "Gitea"
+    }
+
+    fn push_auth_header(&self) -> Option<String> {
+        if self.token.is_empty() {
+            return None;
+        }
+        Some(format!("Authorization: token {}", self.token))
+    }
+
+    fn create_pull_request(&self, repository: &Repository, head: &str, base: &str, title: &str, body: &str) -> Result<PullRequest, String> {
+        let url = format!("{}/repos/{}/{}/pulls", self.api_endpoint, repository.owner, repository.name);
+        let request = self.client.post(url)
+            .header("Authorization", format!("token {}", self.token))
+            .json(&json!({"title": title, "head": head, "base": base, "body": body}));
+        send_pull_request(self.name(), request)
+    }
+}
+
+/// Both APIs answer with the new pull request's `number` and `html_url`, and with a
+/// `message` when they refuse.
+fn send_pull_request(forge: &str, request: reqwest::blocking::RequestBuilder) -> Result<PullRequest, String> {
+    let response = request.send().map_err(|e| format!("Could not reach {}: {}", forge, e))?;
+    let status = response.status();
+    let body: Value = response.json().unwrap_or_default();
+    if !status.is_success() {
+        let detail = body["errors"][0]["message"].as_str()
+            .or_else(|| body["message"].as_str())
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or_default());
+        return Err(format!("{} refused the pull request ({}): {}", forge, status.as_u16(), detail));
+    }
+    Ok(PullRequest {
+        number: body["number"].as_u64().ok_or_else(|| format!("Unexpected answer from {}: {}", forge, body))?,
+        url: body["html_url"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+fn remote_host(url: &str) -> Option<&str> {
+    if let Some((_, rest)) = url.split_once("://") {
+        let authority = rest.split('/').next()?;
+        let host = authority.rsplit('@').next()?;
+        return Some(host.split(':').next().unwrap_or(host));
+    }
+    // scp-like `git@host:owner/name.git`
+    let (user_host, _) = url.split_once(':')?;
+    user_host.rsplit('@').next()
+}
+
+/// The last two path segments of a remote URL: `.../owner/name(.git)`.
+pub fn parse_repository(url: &str) -> Result<Repository, String> {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').map_or("", |(_, path)| path),
+        None => url.split_once(':').map_or(url, |(_, path)| path),
+    };
+    let mut segments = path.trim_end_matches('/').rsplit('/');
+    let name = segments.next().unwrap_or_default().trim_end_matches(".git");
+    let owner = segments.next().unwrap_or_default();
+    if owner.is_empty() || name.is_empty() {
+        return Err(format!("Could not tell the repository's owner and name from {}", url));
+    }
+    Ok(Repository { owner: owner.to_string(), name: name.to_string() })
+}
+
+/// Pushes the project's branch to `origin` and opens a pull request for it. The
+/// body lists the plan, every step committed on the branch and the tokens spent.
+/// `branch` defaults to the checked out one; a task branch targets the branch it
+/// was started from, anything else the remote's default branch.
+pub fn open_pull_request(project_name: &str, branch: Option<&str>, title: Option<&str>) -> Result<PullRequest, String> {
+    let git = Git::for_project(project_name)?;
+    if !git.is_repo() {
+        return Err(format!("The project {} has no repository yet", project_name));
+    }
+    let current = git.current_branch()?;
+    let head = branch.filter(|branch| !branch.is_empty()).unwrap_or(&current).to_string();
+    let base = match git.task_branch(&head) {
+        Ok(task_branch) => task_branch.base,
+        Err(_) => git.default_branch().unwrap_or_else(|| "main".to_string()),
+    };
+    if head == base {
+        return Err(format!("{} is the base branch; run the request on a task branch first", head));
+    }
+    if head == current {
+        git.commit_all("Changes before opening a pull request")?;
+    }
+
+    // Nothing is pushed for a branch that has nothing to review.
+    let commits = git.get_commits_between(&base, &head)?;
+    if commits.is_empty() {
+        return Err(format!("{} has no commits that are not on {}", head, base));
+    }
+
+    let remote_url = git.remote_url("origin").map_err(|_| "The project has no origin remote to push to".to_string())?;
+    let forge = get_forge(&remote_url)?;
+    let repository = parse_repository(&remote_url)?;
+    git.push("origin", &head, forge.push_auth_header().as_deref())?;
+    let token_usage = Config::new().ok()
+        .and_then(|config| AgentState::new(config.get_agent_state_db()).get_latest_token_usage(project_name))
+        .unwrap_or(0);
+
+    let title = match title.filter(|title| !title.trim().is_empty()) {
+        Some(title) => title.trim().to_string(),
+        None => strip_step(&commits[0].message).to_string(),
+    };
+    let body = pull_request_body(&commits, token_usage);
+    forge.create_pull_request(&repository, &head, &base, &title, &body)
+}
+
+/// `Coder: build a todo app` -> `build a todo app`
+fn strip_step(message: &str) -> &str {
+    ["Coder: ", "Patcher: ", "Feature: "].iter()
+        .find_map(|prefix| message.strip_prefix(prefix))
+        .unwrap_or(message)
+}
+
+fn pull_request_body(commits: &[crate::services::git::Commit], token_usage: i64) -> String {
+    let mut body = String::new();
+
+    // The Coder's checkpoint carries the plan in its body.
+    let plans: Vec<String> = commits.iter()
+        .map(|commit| commit.body.lines()
+            .filter(|line| !line.starts_with("Agent-State:"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string())
+        .filter(|plan| !plan.is_empty())
+        .collect();
+    if !plans.is_empty() {
+        let _ = write!(body, "## Plan\n\n{}\n\n", plans.join("\n\n"));
+    }
+
+    body.push_str("## Steps\n\n");
+    for (index, commit) in commits.iter().enumerate() {
+        let _ = writeln!(body, "{}. {} (`{}`)", index + 1, commit.message, &commit.hash[..commit.hash.len().min(8)]);
+    }
+    let _ = write!(body, "\n## Token usage\n\n{} tokens were used on this project.\n", token_usage);
+    body
+}
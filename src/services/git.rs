@@ -0,0 +1,583 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::filesystem::project_path;
+
+/// Field and record separators for `git log`; neither can appear in a commit subject.
+const FIELD_SEPARATOR: char = '\u{1f}';
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Commit {
+    pub hash: String,
+    pub message: String,
+    pub author: String,
+    /// Commit time as a Unix timestamp.
+    pub timestamp: i64,
+    /// Everything after the subject line.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub body: String,
+}
+
+/// A commit made after an agent step, with how long the project's `AgentState`
+/// stack was at that point so a rollback can cut it back too.
+#[derive(Debug, Clone, Serialize)]
+pub struct Checkpoint {
+    pub hash: String,
+    pub message: String,
+    pub timestamp: i64,
+    pub state_index: usize,
+}
+
+/// A branch a single user request ran on, and the branch it was started from.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskBranch {
+    pub name: String,
+    pub base: String,
+    pub state_index: usize,
+}
+
+/// Trailer that marks a commit as a checkpoint.
+const CHECKPOINT_TRAILER: &str = "Agent-State:";
+
+/// How far back `rollback` looks for the checkpoint.
+const MAX_CHECKPOINTS: usize = 10_000;
+
+/// Prefix of the branches `start_task_branch` creates.
+const TASK_BRANCH_PREFIX: &str = "devika/task-";
+
+/// A repository driven through the `git` command line, so no libgit2 is needed and
+/// whatever credentials the host's git is set up with are used for remotes.
+pub struct Git {
+    path: PathBuf,
+    author_name: String,
+    author_email: String,
+}
+
+impl Git {
+    pub fn new(path: &Path) -> Self {
+        let config = Config::new().unwrap();
+        Self {
+            path: path.to_path_buf(),
+            author_name: config.get_git_author_name().to_string(),
+            author_email: config.get_git_author_email().to_string(),
+        }
+    }
+
+    /// The repository of `PROJECTS_DIR/<project>`, which may not be initialized yet.
+    pub fn for_project(project_name: &str) -> Result<Self, String> {
+        Ok(Self::new(&project_path(project_name)?))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A git command for this repository. Fails if the repository's own config would
+    /// make git run a program: code running in the project can write `.git/config`,
+    /// and git runs on the host, outside any sandbox.
+    fn command(&self) -> Result<Command, String> {
+        let in_repository = || {
+            let mut command = git_command();
+            command.arg("-C").arg(&self.path);
+            // A project that isn't a repository yet must not pick up one it sits in.
+            if let Some(parent) = self.path.parent() {
+                command.env("GIT_CEILING_DIRECTORIES", parent);
+            }
+            command
+        };
+        let local_keys = in_repository()
+            .args(["config", "--local", "--name-only", "--list"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default();
+        if let Some(key) = local_keys.lines().find(|key| runs_programs(key)) {
+            return Err(format!("Refusing to run git in {}: its .git/config sets {}", self.path.display(), key));
+        }
+
+        let mut command = in_repository();
+        command.args(["-c", &format!("user.name={}", self.author_name)])
+            .args(["-c", &format!("user.email={}", self.author_email)])
+            .args(["-c", "commit.gpgsign=false"]);
+        Ok(command)
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, String> {
+        run(self.command()?.args(args))
+    }
+
+    /// False for a repository without commits yet.
+    fn has_head(&self) -> Result<bool, String> {
+        Ok(run(self.command()?.args(["rev-parse", "--verify", "--quiet", "HEAD"])).is_ok())
+    }
+
+    pub fn is_repo(&self) -> bool {
+        self.path.join(".git").exists()
+    }
+
+    /// Creates the repository if there isn't one yet.
+    pub fn init(&self) -> Result<(), String> {
+        if self.is_repo() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.path).map_err(|e| e.to_string())?;
+        self.run(&["init", "--quiet", "--initial-branch=main"])?;
+        Ok(())
+    }
+
+    /// Clones `url` into `REPOS_DIR/<host>/<owner>/<name>`, or fetches it again if it
+    /// was cloned from the same URL before.
+    pub fn clone(url: &str) -> Result<Self, String> {
+        validate_url(url)?;
+        let config = Config::new().map_err(|e| e.to_string())?;
+        let repos_dir = Path::new(config.get_repos_dir());
+        fs::create_dir_all(repos_dir).map_err(|e| e.to_string())?;
+
+        let git = Self::new(&repos_dir.join(cache_path(url)?));
+        git.clone_or_fetch(url)?;
+        Ok(git)
+    }
+
+    /// A cached clone of some other remote, such as one that only differs in case,
+    /// is thrown away and cloned again.
+    fn clone_or_fetch(&self, url: &str) -> Result<(), String> {
+        if self.is_repo() && self.remote_url("origin").is_ok_and(|origin| origin == url) {
+            self.run(&["fetch", "--quiet", "origin"])?;
+            return Ok(());
+        }
+        if self.path.exists() {
+            fs::remove_dir_all(&self.path).map_err(|e| format!("Could not replace the clone at {}: {}", self.path.display(), e))?;
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        run(git_command()
+            .args(["clone", "--quiet", "--", url])
+            .arg(&self.path))?;
+        Ok(())
+    }
+
+    /// Makes a clone from `REPOS_DIR` the working copy of a project. The project keeps
+    /// the history, and its `origin` points at the original remote, not the cache.
+    pub fn import_as_project(&self, project_name: &str) -> Result<Git, String> {
+        let project = Self::for_project(project_name)?;
+        self.import_into(&project)?;
+        Ok(project)
+    }
+
+    /// The project may be missing, empty, or hold nothing but a repository without
+    /// commits, as `init` leaves it. The clone is made next to it and moved in, so the
+    /// project directory, and a watcher on it, stay in place.
+    fn import_into(&self, project: &Git) -> Result<(), String> {
+        let name = project.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let entries: Vec<_> = fs::read_dir(&project.path)
+            .map(|entries| entries.filter_map(Result::ok).map(|entry| entry.file_name()).collect())
+            .unwrap_or_default();
+        let fresh_repository = || -> Result<bool, String> {
+            Ok(project.path.join(".git").is_dir() && !project.path.join(".git/index").exists() && !project.has_head()?)
+        };
+        if !entries.is_empty() && (entries.iter().any(|entry| entry != ".git") || !fresh_repository()?) {
+            return Err(format!("The project {} already has files; create a new project to import into", name));
+        }
+        let parent = project.path.parent().ok_or_else(|| format!("Invalid project path: {}", project.path.display()))?;
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+
+        // Project names can't start with a dot, so this never collides with a project.
+        let staging = parent.join(format!(".import-{}", name));
+        let _ = fs::remove_dir_all(&staging);
+        run(git_command()
+            .args(["clone", "--quiet", "--no-hardlinks", "--"])
+            .arg(&self.path)
+            .arg(&staging))?;
+        let moved = (|| -> std::io::Result<()> {
+            fs::create_dir_all(&project.path)?;
+            if project.is_repo() {
+                fs::remove_dir_all(project.path.join(".git"))?;
+            }
+            for entry in fs::read_dir(&staging)? {
+                let entry = entry?;
+                fs::rename(entry.path(), project.path.join(entry.file_name()))?;
+            }
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&staging);
+        moved.map_err(|e| format!("Could not move the clone into {}: {}", name, e))?;
+
+        if let Ok(upstream) = self.run(&["remote", "get-url", "origin"]) {
+            project.run(&["remote", "set-url", "origin", upstream.trim()])?;
+        }
+        Ok(())
+    }
+
+    /// Stages everything and commits it. Returns the new commit's hash, or `None`
+    /// when there was nothing to commit.
+    pub fn commit_all(&self, message: &str) -> Result<Option<String>, String> {
+        self.init()?;
+        self.run(&["add", "--all"])?;
+        if self.run(&["status", "--porcelain"])?.trim().is_empty() {
+            return Ok(None);
+        }
+        self.run(&["commit", "--quiet", "--no-verify", "--message", message])?;
+        Ok(Some(self.run(&["rev-parse", "HEAD"])?.trim().to_string()))
+    }
+
+    /// Commits everything as a checkpoint of an agent step; `state_index` is the length
+    /// of the project's `AgentState` stack after the step.
+    pub fn commit_checkpoint(&self, message: &str, state_index: usize) -> Result<Option<String>, String> {
+        self.commit_all(&format!("{}\n\n{} {}", message, CHECKPOINT_TRAILER, state_index))
+    }
+
+    /// The checkpoints reachable from the current branch, newest first.
+    pub fn get_checkpoints(&self, limit: usize) -> Result<Vec<Checkpoint>, String> {
+        if !self.is_repo() || !self.has_head()? {
+            return Ok(vec![]);
+        }
+        let format = format!("--format=%H{0}%at{0}%s{0}%b{1}", FIELD_SEPARATOR, RECORD_SEPARATOR);
+        let output = self.run(&["log", &format!("--max-count={}", limit), &format, "--grep", &format!("^{}", CHECKPOINT_TRAILER)])?;
+        Ok(output.split(RECORD_SEPARATOR)
+            .filter_map(|record| {
+                let mut fields = record.trim().splitn(4, FIELD_SEPARATOR);
+                let hash = fields.next().filter(|hash| !hash.is_empty())?.to_string();
+                let timestamp = fields.next()?.parse().ok()?;
+                let message = fields.next()?.to_string();
+                let state_index = fields.next()?
+                    .lines()
+                    .find_map(|line| line.strip_prefix(CHECKPOINT_TRAILER))?
+                    .trim()
+                    .parse()
+                    .ok()?;
+                Some(Checkpoint { hash, message, timestamp, state_index })
+            })
+            .collect())
+    }
+
+    /// Puts the working tree back to `checkpoint`, dropping every later commit on the
+    /// current branch and any uncommitted changes. Returns the checkpoint.
+    pub fn rollback(&self, checkpoint: &str) -> Result<Checkpoint, String> {
+        let target = self.get_checkpoints(MAX_CHECKPOINTS)?
+            .into_iter()
+            .find(|candidate| !checkpoint.is_empty() && candidate.hash.starts_with(checkpoint))
+            .ok_or_else(|| format!("No checkpoint {} on the current branch", checkpoint))?;
+        self.run(&["reset", "--quiet", "--hard", &target.hash])?;
+        self.run(&["clean", "--quiet", "-d", "--force"])?;
+        Ok(target)
+    }
+
+    /// Starts a branch for one user request off the current branch, committing any
+    /// pending changes first so the branch starts from what is on disk.
+    pub fn start_task_branch(&self, state_index: usize) -> Result<TaskBranch, String> {
+        self.init()?;
+        self.commit_all("Changes before a new task")?;
+        if !self.has_head()? {
+            self.run(&["commit", "--quiet", "--allow-empty", "--message", "Start of the project"])?;
+        }
+
+        let base = self.current_branch()?;
+        let name = format!("{}{}", TASK_BRANCH_PREFIX, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        self.run(&["checkout", "--quiet", "-b", &name])?;
+        self.run(&["config", &format!("branch.{}.devikaBase", name), &base])?;
+        self.run(&["config", &format!("branch.{}.devikaState", name), &state_index.to_string()])?;
+        Ok(TaskBranch { name, base, state_index })
+    }
+
+    pub fn get_task_branches(&self) -> Result<Vec<TaskBranch>, String> {
+        if !self.is_repo() {
+            return Ok(vec![]);
+        }
+        Ok(self.get_branches()?
+            .into_iter()
+            .filter(|branch| branch.starts_with(TASK_BRANCH_PREFIX))
+            .filter_map(|name| self.task_branch(&name).ok())
+            .collect())
+    }
+
+    pub fn task_branch(&self, name: &str) -> Result<TaskBranch, String> {
+        if !name.starts_with(TASK_BRANCH_PREFIX) {
+            return Err(format!("{} is not a task branch", name));
+        }
+        let base = self.run(&["config", &format!("branch.{}.devikaBase", name)])
+            .map_err(|_| format!("Unknown task branch: {}", name))?;
+        let state_index = self.run(&["config", &format!("branch.{}.devikaState", name)])
+            .ok()
+            .and_then(|index| index.trim().parse().ok())
+            .unwrap_or(0);
+        Ok(TaskBranch { name: name.to_string(), base: base.trim().to_string(), state_index })
+    }
+
+    /// Merges a task branch into the branch it was started from and deletes it.
+    pub fn merge_task_branch(&self, name: &str) -> Result<TaskBranch, String> {
+        let branch = self.task_branch(name)?;
+        if self.current_branch()? == branch.name {
+            self.commit_all("Changes before merging")?;
+        }
+        self.run(&["checkout", "--quiet", &branch.base])?;
+        if let Err(e) = self.run(&["merge", "--quiet", "--no-ff", "--no-edit", &branch.name]) {
+            let _ = self.run(&["merge", "--abort"]);
+            return Err(e);
+        }
+        self.run(&["branch", "--quiet", "-D", &branch.name])?;
+        Ok(branch)
+    }
+
+    /// Throws a task branch away and goes back to the branch it was started from.
+    pub fn discard_task_branch(&self, name: &str) -> Result<TaskBranch, String> {
+        let branch = self.task_branch(name)?;
+        if self.current_branch()? == branch.name {
+            self.run(&["reset", "--quiet", "--hard"])?;
+            self.run(&["clean", "--quiet", "-d", "--force"])?;
+            self.run(&["checkout", "--quiet", &branch.base])?;
+        }
+        self.run(&["branch", "--quiet", "-D", &branch.name])?;
+        Ok(branch)
+    }
+
+    /// The newest `limit` commits of the current branch, newest first.
+    pub fn get_commits(&self, limit: usize) -> Result<Vec<Commit>, String> {
+        if !self.is_repo() {
+            return Ok(vec![]);
+        }
+        // An empty repository has no HEAD to log yet.
+        if !self.has_head()? {
+            return Ok(vec![]);
+        }
+        self.log(&[&format!("--max-count={}", limit)])
+    }
+
+    /// The commits on `head` that are not on `base`, oldest first.
+    pub fn get_commits_between(&self, base: &str, head: &str) -> Result<Vec<Commit>, String> {
+        self.log(&["--reverse", &format!("{}..{}", base, head)])
+    }
+
+    fn log(&self, args: &[&str]) -> Result<Vec<Commit>, String> {
+        let format = format!("--format=%H{0}%an{0}%at{0}%s{0}%b{1}", FIELD_SEPARATOR, RECORD_SEPARATOR);
+        let mut log_args = vec!["log", format.as_str()];
+        log_args.extend_from_slice(args);
+        let output = self.run(&log_args)?;
+        Ok(output.split(RECORD_SEPARATOR)
+            .filter_map(|record| {
+                let mut fields = record.trim().splitn(5, FIELD_SEPARATOR);
+                Some(Commit {
+                    hash: fields.next().filter(|hash| !hash.is_empty())?.to_string(),
+                    author: fields.next()?.to_string(),
+                    timestamp: fields.next()?.parse().ok()?,
+                    message: fields.next()?.to_string(),
+                    body: fields.next()?.trim().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    pub fn get_branches(&self) -> Result<Vec<String>, String> {
+        let output = self.run(&["branch", "--format=%(refname:short)"])?;
+        Ok(output.lines().map(str::to_string).collect())
+    }
+
+    pub fn current_branch(&self) -> Result<String, String> {
+        Ok(self.run(&["rev-parse", "--abbrev-ref", "HEAD"])?.trim().to_string())
+    }
+
+    pub fn remote_url(&self, remote: &str) -> Result<String, String> {
+        Ok(self.run(&["remote", "get-url", remote])?.trim().to_string())
+    }
+
+    /// The branch `origin/HEAD` points at, which is what pull requests usually target.
+    pub fn default_branch(&self) -> Option<String> {
+        let head = self.run(&["symbolic-ref", "--quiet", "--short", "refs/remotes/origin/HEAD"]).ok()?;
+        head.trim().strip_prefix("origin/").map(str::to_string)
+    }
+
+    /// Pushes `branch` to the same name on `remote`. `auth_header` is sent along with
+    /// HTTP(S) requests, so tokens never end up in the remote's URL or on disk. It is
+    /// passed in the environment, since the command line is visible to every user.
+    pub fn push(&self, remote: &str, branch: &str, auth_header: Option<&str>) -> Result<(), String> {
+        let mut command = self.command()?;
+        if let Some(header) = auth_header {
+            command.env("GIT_CONFIG_COUNT", "1")
+                .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+                .env("GIT_CONFIG_VALUE_0", header);
+        }
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+        run(command.args(["push", "--quiet", remote, &refspec]))?;
+        Ok(())
+    }
+
+    /// A file's contents as of `commit`.
+    pub fn get_file(&self, commit: &str, file: &str) -> Result<String, String> {
+        self.run(&["show", &format!("{}:{}", commit, file)])
+    }
+}
+
+/// `git` with hooks and the filesystem monitor switched off, since both run
+/// programs from the repository.
+fn git_command() -> Command {
+    let mut command = Command::new("git");
+    command.args(["-c", "core.hooksPath=/dev/null", "-c", "core.fsmonitor=false"])
+        // Never wait for a password prompt nobody will answer.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null());
+    command
+}
+
+/// Config keys that make git run a program, or send requests (and with them the
+/// forge token) somewhere other than the remote's URL. Hooks and the filesystem
+/// monitor are switched off by `git_command` instead.
+fn runs_programs(key: &str) -> bool {
+    let key = key.to_lowercase();
+    let section = key.split('.').next().unwrap_or_default();
+    let name = key.rsplit('.').next().unwrap_or_default();
+    match section {
+        "core" => matches!(name, "sshcommand" | "askpass" | "editor" | "pager" | "gitproxy" | "alternaterefscommand"),
+        "sequence" => name == "editor",
+        "credential" => name == "helper",
+        "filter" => matches!(name, "clean" | "smudge" | "process"),
+        "diff" => matches!(name, "external" | "textconv" | "command"),
+        "merge" => name == "driver",
+        "remote" => matches!(name, "uploadpack" | "receivepack" | "vcs" | "proxy"),
+        "url" => matches!(name, "insteadof" | "pushinsteadof"),
+        "http" => name == "proxy",
+        "gpg" => name == "program",
+        "include" | "includeif" => true,
+        _ => false,
+    }
+}
+
+fn run(command: &mut Command) -> Result<String, String> {
+    let output = command.output().map_err(|e| format!("Could not run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("git failed: {}", stderr.trim()))
+    }
+}
+
+/// Only the usual transports; git's `ext::` and friends would run arbitrary commands.
+fn validate_url(url: &str) -> Result<(), String> {
+    let allowed = ["https://", "http://", "ssh://", "git://", "file://"];
+    let is_scp_style = url.starts_with("git@") && url.contains(':');
+    if allowed.iter().any(|scheme| url.starts_with(scheme)) || is_scp_style {
+        Ok(())
+    } else {
+        Err(format!("Unsupported repository URL: {}", url))
+    }
+}
+
+/// `https://github.com/user/repo.git` -> `github.com/user/repo`, keeping only
+/// characters that are safe in a directory name. `file://` URLs have no host and go
+/// under `local`.
+fn cache_path(url: &str) -> Result<PathBuf, String> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let host = authority.rsplit('@').next().unwrap_or_default();
+            (host.split(':').next().unwrap_or_default(), path)
+        }
+        // scp-like `git@host:owner/name.git`
+        None => {
+            let (user_host, path) = url.split_once(':').unwrap_or_default();
+            (user_host.rsplit('@').next().unwrap_or_default(), path)
+        }
+    };
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty()).rev();
+    let name = segments.next().unwrap_or_default().trim_end_matches(".git");
+    let owner = segments.next().unwrap_or("_");
+    let host = if host.is_empty() { "local" } else { host };
+
+    let mut cache_path = PathBuf::new();
+    for part in [host, owner, name] {
+        let part: String = part.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '-' })
+            .collect();
+        if part.is_empty() || part.starts_with('.') {
+            return Err(format!("Could not tell the repository name from {}", url));
+        }
+        cache_path.push(part.to_lowercase());
+    }
+    Ok(cache_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{cache_path, Git};
+
+    fn repository(path: &Path) -> Git {
+        Git { path: path.to_path_buf(), author_name: "Devika".to_string(), author_email: "devika@localhost".to_string() }
+    }
+
+    /// A repository with one commit, and a clone of it in a `REPOS_DIR` stand-in.
+    fn cached_clone(root: &Path) -> (Git, String) {
+        let remote = repository(&root.join("remote"));
+        remote.init().unwrap();
+        fs::write(remote.path.join("README.md"), "hello\n").unwrap();
+        remote.commit_all("Initial commit").unwrap();
+
+        let url = format!("file://{}", remote.path.display());
+        let cache = repository(&root.join("repos").join("remote"));
+        cache.clone_or_fetch(&url).unwrap();
+        (cache, url)
+    }
+
+    #[test]
+    fn imports_a_file_url_clone_into_a_fresh_project() {
+        let root = tempfile::tempdir().unwrap();
+        let (cache, url) = cached_clone(root.path());
+        // What a run leaves behind before its Decision steps.
+        let project = repository(&root.path().join("projects").join("app"));
+        project.init().unwrap();
+
+        cache.import_into(&project).unwrap();
+        assert_eq!(fs::read_to_string(project.path.join("README.md")).unwrap(), "hello\n");
+        assert_eq!(project.remote_url("origin").unwrap().trim(), url);
+        assert_eq!(project.get_commits(10).unwrap()[0].message, "Initial commit");
+        assert!(!root.path().join("projects").join(".import-app").exists());
+    }
+
+    #[test]
+    fn refuses_to_import_over_a_project_with_history_or_files() {
+        let root = tempfile::tempdir().unwrap();
+        let (cache, _) = cached_clone(root.path());
+
+        let with_files = repository(&root.path().join("projects").join("files"));
+        fs::create_dir_all(&with_files.path).unwrap();
+        fs::write(with_files.path.join("main.py"), "print()\n").unwrap();
+        assert!(cache.import_into(&with_files).is_err());
+
+        let with_history = repository(&root.path().join("projects").join("history"));
+        with_history.init().unwrap();
+        with_history.run(&["commit", "--quiet", "--allow-empty", "--message", "Start of the project"]).unwrap();
+        assert!(cache.import_into(&with_history).is_err());
+    }
+
+    #[test]
+    fn keys_cached_clones_on_host_owner_and_name() {
+        assert_eq!(cache_path("https://github.com/a/utils.git").unwrap(), Path::new("github.com/a/utils"));
+        assert_eq!(cache_path("git@github.com:b/utils.git").unwrap(), Path::new("github.com/b/utils"));
+        assert_eq!(cache_path("https://user@gitea.local:3000/a/utils/").unwrap(), Path::new("gitea.local/a/utils"));
+        assert_eq!(cache_path("file:///srv/git/a/utils").unwrap(), Path::new("local/a/utils"));
+        assert!(cache_path("https://github.com/a/..").is_err());
+    }
+
+    #[test]
+    fn clones_again_when_the_cache_holds_another_remote() {
+        let root = tempfile::tempdir().unwrap();
+        let (cache, _) = cached_clone(root.path());
+
+        let other = repository(&root.path().join("other"));
+        other.init().unwrap();
+        fs::write(other.path.join("OTHER.md"), "other\n").unwrap();
+        other.commit_all("Other commit").unwrap();
+        let other_url = format!("file://{}", other.path.display());
+
+        cache.clone_or_fetch(&other_url).unwrap();
+        assert_eq!(cache.remote_url("origin").unwrap(), other_url);
+        assert!(cache.path.join("OTHER.md").exists());
+        assert!(!cache.path.join("README.md").exists());
+    }
+}
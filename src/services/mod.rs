@@ -0,0 +1,4 @@
+pub mod deploy;
+pub mod forge;
+pub mod git;
+pub mod utils;
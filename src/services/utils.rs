@@ -0,0 +1,72 @@
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::agents::control;
+use crate::logger::Logger;
+use crate::socket_instance::emit_agent;
+
+const MAX_TRIES: usize = 5;
+
+/// Re-runs `func` until it yields a valid response, up to five attempts.
+/// Unlike the Python wrapper this hands the failure back instead of exiting the process.
+pub fn retry_wrapper<T, F>(mut func: F) -> Result<T, String>
+where
+    F: FnMut() -> Result<T, String>,
+{
+    let logger = Logger::new("devika_agent.log");
+    let mut last_error = String::new();
+    for _ in 0..MAX_TRIES {
+        match func() {
+            Ok(result) => return Ok(result),
+            // A cancelled run has nothing to retry.
+            Err(e) if control::is_cancellation(&e) => return Err(e),
+            Err(e) => {
+                logger.warning(&format!("Invalid response from the model, I'm trying again... ({})", e));
+                emit_agent("info", json!({"type": "warning", "message": "Invalid response from the model, trying again..."}));
+                last_error = e;
+            }
+        }
+        thread::sleep(Duration::from_secs(2));
+    }
+
+    logger.error(&format!("Maximum {} attempts reached. try other models", MAX_TRIES));
+    emit_agent("info", json!({"type": "error", "message": "Maximum attempts reached. model keeps failing."}));
+    Err(last_error)
+}
+
+/// Pulls the JSON object out of a model response, trying the same fallbacks as the
+/// Python `validate_responses` decorator: the raw text, the first fenced block, the
+/// outermost braces, then any single line.
+pub fn validate_responses(response: &str) -> Result<Value, String> {
+    let response = response.trim();
+
+    if let Ok(value) = serde_json::from_str(response) {
+        return Ok(value);
+    }
+
+    if let Some(block) = response.split("```").nth(1) {
+        let block = block.trim_start_matches("json").trim();
+        if let Ok(value) = serde_json::from_str(block) {
+            return Ok(value);
+        }
+    }
+
+    if let (Some(start), Some(end)) = (response.find('{'), response.rfind('}')) {
+        if start < end {
+            if let Ok(value) = serde_json::from_str(&response[start..=end]) {
+                return Ok(value);
+            }
+        }
+    }
+
+    for line in response.lines() {
+        if let Ok(value) = serde_json::from_str(line) {
+            return Ok(value);
+        }
+    }
+
+    emit_agent("info", json!({"type": "error", "message": "Failed to parse response as JSON"}));
+    Err("Failed to parse response as JSON".to_string())
+}
@@ -1,15 +1,20 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use chrono::prelude::*;
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::fs;
+use std::io::ErrorKind;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use lazy_static::lazy_static;
 
+use crate::filesystem::read_code::ReadCode;
+use crate::logger::Logger;
 use crate::socket_instance::{self, emit_agent};
 
 lazy_static! {
     static ref TIKTOKEN_ENC: &'static str = "cl100k_base";
+    /// Agent threads and routes all rewrite the whole store, so they take turns.
+    static ref STORE_LOCK: Mutex<()> = Mutex::new(());
 }
 
 #[derive(Serialize, Deserialize)]
@@ -48,216 +53,240 @@ impl AgentState {
             "completed": false,
             "agent_is_active": true,
             "token_usage": 0,
+            "pending_question": null,
+            "paused": false,
+            "interruption": null,
             "timestamp": timestamp
         })
     }
 
+    /// Reads every project's state stack. A missing or empty file is an empty store;
+    /// one that can't be read or parsed is an error, not an empty store.
+    fn load(&self) -> Result<Vec<AgentStateModel>, String> {
+        match fs::read_to_string(&self.db_file) {
+            Ok(contents) if contents.trim().is_empty() => Ok(vec![]),
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Could not parse {}: {}", self.db_file.display(), e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(format!("Could not read {}: {}", self.db_file.display(), e)),
+        }
+    }
+
+    /// Writes through a temp file and a rename, so a crash mid-write never leaves a
+    /// half-written store behind.
+    fn save(&self, agent_states: &[AgentStateModel]) -> Result<(), String> {
+        if let Some(parent) = self.db_file.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let serialized = serde_json::to_string(agent_states).map_err(|e| e.to_string())?;
+        let temp_path = self.db_file.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&temp_path, serialized).map_err(|e| e.to_string())?;
+        fs::rename(&temp_path, &self.db_file).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            e.to_string()
+        })
+    }
+
+    /// Every project's state stack; an unreadable store is logged and reads as empty.
+    fn read(&self) -> Vec<AgentStateModel> {
+        let _lock = STORE_LOCK.lock().unwrap();
+        self.load().unwrap_or_else(|e| {
+            Logger::new("devika_agent.log").error(&e);
+            vec![]
+        })
+    }
+
+    /// Loads, changes and saves the store in one go. Nothing is written when the store
+    /// can't be read, so a corrupt file is never replaced by an empty one.
+    fn modify<R, F: FnOnce(&mut Vec<AgentStateModel>) -> R>(&self, change: F) -> Option<R> {
+        let _lock = STORE_LOCK.lock().unwrap();
+        let result = self.load().and_then(|mut agent_states| {
+            let result = change(&mut agent_states);
+            self.save(&agent_states).map(|_| result)
+        });
+        result.map_err(|e| Logger::new("devika_agent.log").error(&format!("Agent state not saved: {}", e))).ok()
+    }
+
     pub fn create_state(&self, project: &str) {
         let mut new_state = Self::new_state();
         new_state["step"] = json!(1);
         new_state["internal_monologue"] = json!("I'm starting the work...");
 
-        let agent_state = AgentStateModel {
-            project: project.to_string(),
-            state_stack: vec![new_state.clone().to_owned()],
-        };
-
-        let mut file = File::create(&self.db_file).unwrap();
-        let serialized = serde_json::to_string(&agent_state).unwrap();
-        file.write_all(serialized.as_bytes()).unwrap();
+        self.modify(|agent_states| {
+            agent_states.retain(|state| state.project != project);
+            agent_states.push(AgentStateModel {
+                project: project.to_string(),
+                state_stack: vec![new_state.clone()],
+            });
+        });
 
         socket_instance::emit_agent("agent-state", json!({"new_state": new_state }));
     }
 
     pub fn delete_state(&self, project: &str) {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let mut agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            agent_states.retain(|state| state.project != project);
-            let serialized = serde_json::to_string(&agent_states).unwrap();
-            file.write_all(serialized.as_bytes()).unwrap();
-        }
+        self.modify(|agent_states| agent_states.retain(|state| state.project != project));
     }
 
     pub fn add_to_current_state(&self, project: &str, state: &Value) {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let mut agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter_mut().find(|state| state.project == project) {
-                agent_state.state_stack.push(state.clone().to_owned());
+        self.modify(|agent_states| {
+            match agent_states.iter_mut().find(|agent_state| agent_state.project == project) {
+                Some(agent_state) => agent_state.state_stack.push(state.clone()),
+                None => agent_states.push(AgentStateModel {
+                    project: project.to_string(),
+                    state_stack: vec![state.clone()],
+                }),
             }
-            let serialized = serde_json::to_string(&agent_states).unwrap();
-            file.write_all(serialized.as_bytes()).unwrap();
-        }
+        });
 
         socket_instance::emit_agent("agent-state", json!({"state": state}));
     }
 
+    /// Cuts the project's stack back to its first `len` frames, as after a rollback.
+    /// The frame left on top was written mid-run, so it is marked as finished.
+    pub fn truncate_state(&self, project: &str, len: usize) -> Option<Value> {
+        let latest_state = self.modify(|agent_states| {
+            let agent_state = agent_states.iter_mut().find(|state| state.project == project)?;
+            agent_state.state_stack.truncate(len.max(1));
+            let latest_state = agent_state.state_stack.last_mut()?;
+            latest_state["agent_is_active"] = json!(false);
+            latest_state["completed"] = json!(true);
+            Some(latest_state.clone())
+        })??;
+
+        socket_instance::emit_agent("agent-state", json!({"state": latest_state}));
+        Some(latest_state)
+    }
+
     pub fn get_current_state(&self, project: &str) -> Option<Vec<Value>> {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter().find(|state| state.project == project) {
-                return Some(agent_state.state_stack.clone());
-            }
-        }
-        None
+        self.read().into_iter()
+            .find(|state| state.project == project)
+            .map(|agent_state| agent_state.state_stack)
     }
 
     pub fn update_latest_state(&self, project: &str, state: Value) {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let mut agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
+        self.modify(|agent_states| {
             if let Some(agent_state) = agent_states.iter_mut().find(|state| state.project == project) {
                 agent_state.state_stack.pop();
                 agent_state.state_stack.push(state.clone());
             }
-            let serialized = serde_json::to_string(&agent_states).unwrap();
-            file.write_all(serialized.as_bytes()).unwrap();
-        }
+        });
 
         socket_instance::emit_agent("agent-state", json!({"state": state.clone()}));
     }
 
     pub fn get_latest_state(&self, project: &str) -> Option<Value> {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter().find(|state| state.project == project) {
-                return agent_state.state_stack.last().cloned();
+        self.read().into_iter()
+            .find(|state| state.project == project)
+            .and_then(|mut agent_state| agent_state.state_stack.pop())
+    }
+
+    fn update_latest<F: FnOnce(&mut Value)>(&self, project: &str, update: F) {
+        self.modify(|agent_states| {
+            if let Some(latest_state) = agent_states.iter_mut()
+                .find(|state| state.project == project)
+                .and_then(|agent_state| agent_state.state_stack.last_mut())
+            {
+                update(latest_state);
             }
-        }
-        None
+        });
     }
 
     pub fn set_agent_active(&self, project: &str, is_active: bool) {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let mut agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter_mut().find(|state| state.project == project) {
-                if let Some(latest_state) = agent_state.state_stack.last_mut() {
-                    latest_state["agent_is_active"] = json!(is_active);
-                }
-            }
-            let serialized = serde_json::to_string(&agent_states).unwrap();
-            file.write_all(serialized.as_bytes()).unwrap();
-        }
+        self.update_latest(project, |latest_state| {
+            latest_state["agent_is_active"] = json!(is_active);
+        });
 
         socket_instance::emit_agent("agent-state", json!({"is_active": is_active}));
     }
 
     pub fn is_agent_active(&self, project: &str) -> Option<bool> {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter().find(|state| state.project == project) {
-                if let Some(latest_state) = agent_state.state_stack.last() {
-                    return Some(latest_state["agent_is_active"].as_bool().unwrap_or(false));
-                }
-            }
-        }
-        None
+        self.get_latest_state(project)
+            .map(|latest_state| latest_state["agent_is_active"].as_bool().unwrap_or(false))
     }
 
     pub fn set_agent_completed(&self, project: &str, is_completed: bool) {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let mut agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter_mut().find(|state| state.project == project) {
-                if let Some(latest_state) = agent_state.state_stack.last_mut() {
-                    latest_state["internal_monologue"] = json!("Agent has completed the task.");
-                    latest_state["completed"] = json!(is_completed);
-                }
-            }
-            let serialized = serde_json::to_string(&agent_states).unwrap();
-            file.write_all(serialized.as_bytes()).unwrap();
-        }
+        self.update_latest(project, |latest_state| {
+            latest_state["internal_monologue"] = json!("Agent has completed the task.");
+            latest_state["completed"] = json!(is_completed);
+        });
 
         socket_instance::emit_agent("agent-state", json!({"is_completed": is_completed}));
     }
 
     pub fn is_agent_completed(&self, project: &str) -> Option<bool> {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter().find(|state| state.project == project) {
-                if let Some(latest_state) = agent_state.state_stack.last() {
-                    return Some(latest_state["completed"].as_bool().unwrap_or(false));
-                }
-            }
-        }
-        None
+        self.get_latest_state(project)
+            .map(|latest_state| latest_state["completed"].as_bool().unwrap_or(false))
     }
 
-    pub fn update_token_usage(&self, project: &str, token_usage: i32) {
-        if let Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let mut agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
+    pub fn set_agent_paused(&self, project: &str, is_paused: bool) {
+        self.update_latest(project, |latest_state| {
+            latest_state["paused"] = json!(is_paused);
+        });
+
+        socket_instance::emit_agent("agent-state", json!({"is_paused": is_paused}));
+    }
+
+    /// Where the project's last cancelled run stopped, if it can still be resumed.
+    pub fn get_interruption(&self, project: &str) -> Option<Value> {
+        self.get_current_state(project)?
+            .into_iter()
+            .rev()
+            .map(|state| state["interruption"].clone())
+            .find(|interruption| !interruption.is_null())
+    }
+
+    /// Forgets where earlier runs stopped, once one is resumed or a new one starts.
+    pub fn clear_interruption(&self, project: &str) {
+        self.modify(|agent_states| {
             if let Some(agent_state) = agent_states.iter_mut().find(|state| state.project == project) {
-                if let Some(latest_state) = agent_state.state_stack.last_mut() {
-                    let current_usage: i64 = latest_state["token_usage"].as_i64().unwrap_or(0);
-                    latest_state["token_usage"] = json!(current_usage + token_usage as i64);
+                for state in agent_state.state_stack.iter_mut() {
+                    if !state["interruption"].is_null() {
+                        state["interruption"] = Value::Null;
+                    }
                 }
             }
-            let serialized = serde_json::to_string(&agent_states).unwrap();
-            file.write_all(serialized.as_bytes()).unwrap();
-        }
+        });
+    }
+
+    /// The question the agent is waiting for the user to answer, if any.
+    pub fn get_pending_question(&self, project: &str) -> Option<Value> {
+        self.get_latest_state(project)
+            .map(|latest_state| latest_state["pending_question"].clone())
+            .filter(|question| !question.is_null())
+    }
+
+    pub fn clear_pending_question(&self, project: &str) {
+        self.update_latest(project, |latest_state| {
+            latest_state["pending_question"] = Value::Null;
+        });
+
+        socket_instance::emit_agent("agent-state", json!({"pending_question": null}));
+    }
+
+    pub fn update_token_usage(&self, project: &str, token_usage: i32) {
+        self.update_latest(project, |latest_state| {
+            let current_usage: i64 = latest_state["token_usage"].as_i64().unwrap_or(0);
+            latest_state["token_usage"] = json!(current_usage + token_usage as i64);
+        });
     }
 
     pub fn get_latest_token_usage(&self, project: &str) -> Option<i64> {
-        if let         Ok(mut file) = File::open(&self.db_file) {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let agent_states: Vec<AgentStateModel> = serde_json::from_str(&contents).unwrap();
-            if let Some(agent_state) = agent_states.iter().find(|state| state.project == project) {
-                if let Some(latest_state) = agent_state.state_stack.last() {
-                    return Some(latest_state["token_usage"].as_i64()).unwrap_or(Some(0));
-                }
-            }
-        }
-        None
+        self.get_latest_state(project)
+            .map(|latest_state| latest_state["token_usage"].as_i64().unwrap_or(0))
     }
 
+    /// Every text file of the project with its path relative to the project root.
+    /// Binaries and anything ignored by `ReadCode` are left out.
     pub fn get_project_files(&self, project_name: &str) -> Vec<Value> {
         if project_name.is_empty() {
             return vec![];
         }
 
-        let project_directory = project_name.replace(" ", "-");
-        let mut directory = std::env::current_dir().unwrap();
-        directory.push("data");
-        directory.push("projects");
-        directory.push(&project_directory);
-
-        if !directory.exists() {
-            return vec![];
-        }
-
-        let mut files = vec![];
-        let entries = fs::read_dir(directory).unwrap();
-        for entry in entries {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.is_file() {
-                let relative_path = path.strip_prefix(std::env::current_dir().unwrap()).unwrap().to_str().unwrap().to_string();
-                let content = fs::read_to_string(&path).unwrap();
-                files.push(json!({
-                    "file": relative_path,
-                    "code": content,
-                }));
-            }
-        }
-        files
+        ReadCode::new(project_name)
+            .read_directory()
+            .into_iter()
+            .map(|entry| json!({"file": entry.filename, "code": entry.code}))
+            .collect()
     }
     pub fn update_global_token_usage(&self, string: &str, project_name: &str) {
         let token_usage = tiktoken::count_text(&TIKTOKEN_ENC, string);
@@ -0,0 +1,50 @@
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+/// Both ends of a pseudo terminal. The slave becomes the shell's stdio and
+/// controlling terminal; the server reads and writes the master.
+pub struct Pty {
+    pub master: File,
+    pub slave: File,
+}
+
+pub fn open_pty(rows: u16, cols: u16) -> Result<Pty, String> {
+    let last_error = || std::io::Error::last_os_error().to_string();
+
+    let master = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC) };
+    if master < 0 {
+        return Err(last_error());
+    }
+    // Owned from here on, so every early return closes it.
+    let master = unsafe { File::from_raw_fd(master) };
+
+    if unsafe { libc::grantpt(master.as_raw_fd()) } != 0 || unsafe { libc::unlockpt(master.as_raw_fd()) } != 0 {
+        return Err(last_error());
+    }
+
+    let mut name = [0 as libc::c_char; 128];
+    if unsafe { libc::ptsname_r(master.as_raw_fd(), name.as_mut_ptr(), name.len()) } != 0 {
+        return Err(last_error());
+    }
+    let slave_path = unsafe { CStr::from_ptr(name.as_ptr()) }.to_string_lossy().into_owned();
+    let slave = OpenOptions::new()
+        .read(true)
+        .write(true)
+        // The server must not pick up the terminal as its own controlling terminal.
+        .custom_flags(libc::O_NOCTTY)
+        .open(&slave_path)
+        .map_err(|e| format!("{}: {}", slave_path, e))?;
+
+    set_window_size(&master, rows, cols)?;
+    Ok(Pty { master, slave })
+}
+
+pub fn set_window_size(master: &File, rows: u16, cols: u16) -> Result<(), String> {
+    let size = libc::winsize { ws_row: rows, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 };
+    if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::filesystem::project_path;
+use crate::sandbox::{get_sandbox, kill_process_group, minimal_env, SandboxPolicy};
+use crate::socket_instance::emit_agent;
+use crate::terminal::pty::{open_pty, set_window_size};
+
+/// How much output is kept, in memory and on disk, per project.
+const SCROLLBACK_BYTES: usize = 256 * 1024;
+
+static SESSIONS: Lazy<Mutex<HashMap<String, Arc<TerminalSession>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A shell on a PTY, rooted in the project directory and started through the
+/// project's sandbox. There is at most one per project. The user types into it,
+/// the commands the agent runs show up in it through `mirror_output`, and everyone
+/// watching gets the same output.
+pub struct TerminalSession {
+    project_name: String,
+    master: Mutex<File>,
+    child: Mutex<Child>,
+    scrollback: Mutex<String>,
+    scrollback_file: PathBuf,
+}
+
+fn session_key(project_name: &str) -> String {
+    project_name.to_lowercase().replace(' ', "-")
+}
+
+fn scrollback_path(project_name: &str) -> PathBuf {
+    let config = Config::new().unwrap();
+    Path::new(config.get_terminals_dir()).join(format!("{}.log", session_key(project_name)))
+}
+
+/// Returns the project's running session, starting one if there is none.
+pub fn open_session(project_name: &str, rows: u16, cols: u16) -> Result<Arc<TerminalSession>, String> {
+    let mut sessions = SESSIONS.lock().unwrap();
+    if let Some(session) = sessions.get(&session_key(project_name)) {
+        return Ok(session.clone());
+    }
+
+    let session = Arc::new(TerminalSession::spawn(project_name, rows, cols)?);
+    sessions.insert(session_key(project_name), session.clone());
+
+    let reader = session.master.lock().unwrap().try_clone().map_err(|e| e.to_string())?;
+    let output_session = session.clone();
+    thread::spawn(move || output_session.pump_output(reader));
+
+    Ok(session)
+}
+
+pub fn get_session(project_name: &str) -> Option<Arc<TerminalSession>> {
+    SESSIONS.lock().unwrap().get(&session_key(project_name)).cloned()
+}
+
+pub fn close_session(project_name: &str) {
+    let session = SESSIONS.lock().unwrap().remove(&session_key(project_name));
+    if let Some(session) = session {
+        session.kill();
+    }
+}
+
+/// The live scrollback if a shell is running, otherwise what was persisted last.
+pub fn get_scrollback(project_name: &str) -> String {
+    match get_session(project_name) {
+        Some(session) => session.scrollback.lock().unwrap().clone(),
+        None => fs::read(scrollback_path(project_name))
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// Shows what the agent ran elsewhere in the project's terminal, without running
+/// it again. Does nothing when no terminal is open.
+pub fn mirror_output(project_name: &str, text: &str) {
+    if let Some(session) = get_session(project_name) {
+        session.push_output(&text.replace('\n', "\r\n"));
+    }
+}
+
+/// Background jobs get process groups of their own, but they stay in the session
+/// the shell leads.
+#[cfg(target_os = "linux")]
+fn kill_session(session_id: u32) {
+    let session_id = session_id as libc::pid_t;
+    if unsafe { libc::getsid(0) } == session_id {
+        return;
+    }
+    let Ok(entries) = fs::read_dir("/proc") else { return };
+    for pid in entries.flatten().filter_map(|entry| entry.file_name().to_str()?.parse::<libc::pid_t>().ok()) {
+        unsafe {
+            if libc::getsid(pid) == session_id {
+                libc::kill(pid, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn kill_session(_session_id: u32) {}
+
+impl TerminalSession {
+    fn spawn(project_name: &str, rows: u16, cols: u16) -> Result<Self, String> {
+        let config = Config::new().map_err(|e| e.to_string())?;
+        let project_path = project_path(project_name)?;
+        fs::create_dir_all(&project_path).map_err(|e| e.to_string())?;
+
+        let policy = SandboxPolicy::for_project(&config, project_name);
+        let sandbox = get_sandbox(&policy.backend)?;
+
+        let shell = ["/bin/bash", "/bin/sh"].into_iter()
+            .find(|shell| Path::new(shell).exists())
+            .ok_or("No shell found")?;
+        let argv = vec![shell.to_string(), "-i".to_string()];
+
+        let pty = open_pty(rows, cols)?;
+        let mut command = sandbox.terminal_command(&argv, &project_path, policy.allow_network)?;
+        minimal_env(&mut command, &project_path.canonicalize().map_err(|e| e.to_string())?);
+        command.env("TERM", "xterm-256color")
+            .stdin(Stdio::from(pty.slave.try_clone().map_err(|e| e.to_string())?))
+            .stdout(Stdio::from(pty.slave.try_clone().map_err(|e| e.to_string())?))
+            .stderr(Stdio::from(pty.slave));
+        unsafe {
+            command.pre_exec(|| {
+                // A new session with the PTY as its controlling terminal, so job
+                // control and Ctrl-C work like in a normal terminal.
+                if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn().map_err(|e| format!("{} (sandbox: {})", e, sandbox.name()))?;
+
+        let scrollback_file = scrollback_path(project_name);
+        if let Some(parent) = scrollback_file.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let scrollback = fs::read(&scrollback_file)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default();
+
+        Ok(Self {
+            project_name: project_name.to_string(),
+            master: Mutex::new(pty.master),
+            child: Mutex::new(child),
+            scrollback: Mutex::new(scrollback),
+            scrollback_file,
+        })
+    }
+
+    /// Keystrokes from the UI, as raw terminal input. They are not broadcast: the
+    /// shell echoes what should be seen, and not what shouldn't, like passwords.
+    pub fn write_input(&self, data: &str) -> Result<(), String> {
+        self.master.lock().unwrap().write_all(data.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    pub fn resize(&self, rows: u16, cols: u16) -> Result<(), String> {
+        set_window_size(&self.master.lock().unwrap(), rows, cols)
+    }
+
+    /// Kills the shell's process group and whatever else is left in its session.
+    fn kill(&self) {
+        let mut child = self.child.lock().unwrap();
+        kill_session(child.id());
+        kill_process_group(&mut child);
+    }
+
+    fn pump_output(&self, mut reader: File) {
+        let mut buffer = [0u8; 4096];
+        loop {
+            // EIO once the shell and everything it started have exited.
+            let read = match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            self.push_output(&String::from_utf8_lossy(&buffer[..read]));
+        }
+
+        let _ = self.child.lock().unwrap().wait();
+        let mut sessions = SESSIONS.lock().unwrap();
+        if sessions.get(&session_key(&self.project_name)).is_some_and(|session| std::ptr::eq(session.as_ref(), self)) {
+            sessions.remove(&session_key(&self.project_name));
+        }
+        drop(sessions);
+        emit_agent("terminal-closed", json!({"project_name": self.project_name}));
+    }
+
+    fn push_output(&self, data: &str) {
+        let mut scrollback = self.scrollback.lock().unwrap();
+        scrollback.push_str(data);
+
+        // Appending is cheap; the file is only rewritten once it has doubled the cap.
+        let appended = OpenOptions::new().create(true).append(true).open(&self.scrollback_file)
+            .and_then(|mut file| file.write_all(data.as_bytes()));
+        if scrollback.len() > SCROLLBACK_BYTES * 2 || appended.is_err() {
+            let mut cut = scrollback.len().saturating_sub(SCROLLBACK_BYTES);
+            while !scrollback.is_char_boundary(cut) {
+                cut += 1;
+            }
+            scrollback.drain(..cut);
+            let _ = fs::write(&self.scrollback_file, scrollback.as_bytes());
+        }
+        drop(scrollback);
+
+        emit_agent("terminal-output", json!({
+            "project_name": self.project_name,
+            "data": data,
+        }));
+    }
+}
@@ -163,3 +163,140 @@ export async function fetchLogs() {
   const data = await response.json();
   return data.logs;
 }
+
+export async function openTerminal(projectName, rows, cols) {
+  const response = await fetch(`${API_BASE_URL}/api/terminal/open`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, rows: rows, cols: cols }),
+  });
+  return await response.json();
+}
+
+export async function sendTerminalInput(projectName, data) {
+  await fetch(`${API_BASE_URL}/api/terminal/input`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, data: data }),
+  });
+}
+
+export async function resizeTerminal(projectName, rows, cols) {
+  await fetch(`${API_BASE_URL}/api/terminal/resize`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, rows: rows, cols: cols }),
+  });
+}
+
+export async function fetchProjectTree(projectName) {
+  const response = await fetch(`${API_BASE_URL}/api/project-tree?project_name=${encodeURIComponent(projectName)}`);
+  return await response.json();
+}
+
+export async function fetchProjectFile(projectName, path) {
+  const response = await fetch(
+    `${API_BASE_URL}/api/project-file?project_name=${encodeURIComponent(projectName)}&path=${encodeURIComponent(path)}`
+  );
+  return await response.json();
+}
+
+export async function saveProjectFile(projectName, path, content) {
+  const response = await fetch(`${API_BASE_URL}/api/project-file`, {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, path: path, content: content }),
+  });
+  return await response.json();
+}
+
+async function controlAgent(action, body) {
+  const response = await fetch(`${API_BASE_URL}/api/agent/${action}`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(body),
+  });
+  return await response.json();
+}
+
+export async function cancelAgent(projectName) {
+  return await controlAgent("cancel", { project_name: projectName });
+}
+
+export async function pauseAgent(projectName) {
+  return await controlAgent("pause", { project_name: projectName });
+}
+
+export async function resumeAgent(projectName) {
+  const selectedModel = localStorage.getItem("selectedModel");
+  const searchEngine = localStorage.getItem("selectedSearchEngine");
+  return await controlAgent("resume", {
+    project_name: projectName,
+    ...(selectedModel ? { base_model: selectedModel } : {}),
+    ...(searchEngine ? { search_engine: searchEngine } : {}),
+  });
+}
+
+export async function fetchCheckpoints(projectName) {
+  const response = await fetch(`${API_BASE_URL}/api/project/checkpoints?project_name=${encodeURIComponent(projectName)}`);
+  return await response.json();
+}
+
+export async function rollbackProject(projectName, checkpoint) {
+  const response = await fetch(`${API_BASE_URL}/api/project/rollback`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, checkpoint: checkpoint }),
+  });
+  return await response.json();
+}
+
+export async function fetchTaskBranches(projectName) {
+  const response = await fetch(`${API_BASE_URL}/api/project/branches?project_name=${encodeURIComponent(projectName)}`);
+  return await response.json();
+}
+
+export async function mergeTaskBranch(projectName, branch) {
+  const response = await fetch(`${API_BASE_URL}/api/project/merge-branch`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, branch: branch }),
+  });
+  return await response.json();
+}
+
+export async function discardTaskBranch(projectName, branch) {
+  const response = await fetch(`${API_BASE_URL}/api/project/discard-branch`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, branch: branch }),
+  });
+  return await response.json();
+}
+
+export async function openPullRequest(projectName, branch, title) {
+  const response = await fetch(`${API_BASE_URL}/api/project/pull-request`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ project_name: projectName, branch: branch, title: title }),
+  });
+  return await response.json();
+}
@@ -90,7 +90,9 @@ function switchTab(editor, models, filename, tabElement) {
   tabElement.classList.add("bg-secondary");
 }
 
-export function sidebar(editor, models, sidebarContainer) {
+// `treeFiles` are project files that have no model yet; clicking one calls
+// `openFile` so it is only fetched when the user asks for it.
+export function sidebar(editor, models, sidebarContainer, treeFiles = [], openFile = null) {
   sidebarContainer.innerHTML = "";
   const createSidebarElement = (filename, isFolder) => {
     const sidebarElement = document.createElement("div");
@@ -114,7 +116,11 @@ export function sidebar(editor, models, sidebarContainer) {
 
   const folders = {};
 
-  Object.entries(models).forEach(([filename, model], modelIndex) => {
+  const modelNames = Object.keys(models);
+  const filenames = [...modelNames, ...treeFiles.filter((filename) => !models[filename])];
+
+  filenames.forEach((filename) => {
+    const modelIndex = modelNames.indexOf(filename);
     const parts = filename.split('/');
     let currentFolder = sidebarContainer;
 
@@ -122,7 +128,11 @@ export function sidebar(editor, models, sidebarContainer) {
       if (index === parts.length - 1) {
         const fileElement = createSidebarElement(part, false);
         fileElement.addEventListener("click", () => {
-          editor.setModel(model);
+          if (modelIndex === -1) {
+            if (openFile) openFile(filename);
+            return;
+          }
+          editor.setModel(models[filename]);
           changeTabColor(modelIndex);
         });
         currentFolder.appendChild(fileElement);